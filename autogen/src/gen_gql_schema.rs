@@ -9,7 +9,7 @@ use schemars::schema::{
 use tailcall::config;
 
 static GRAPHQL_SCHEMA_FILE: &str = "generated/.tailcallrc.graphql";
-static DIRECTIVE_ALLOW_LIST: [(&str, Entity, bool); 13] = [
+static DIRECTIVE_ALLOW_LIST: [(&str, Entity, bool); 14] = [
     ("server", Entity::Schema, false),
     ("link", Entity::Schema, true),
     ("upstream", Entity::Schema, false),
@@ -21,6 +21,7 @@ static DIRECTIVE_ALLOW_LIST: [(&str, Entity, bool); 13] = [
     ("const", Entity::FieldDefinition, false),
     ("graphQL", Entity::FieldDefinition, false),
     ("cache", Entity::FieldDefinition, false),
+    ("cacheInvalidate", Entity::FieldDefinition, false),
     ("expr", Entity::FieldDefinition, false),
     ("js", Entity::FieldDefinition, false),
 ];
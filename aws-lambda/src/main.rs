@@ -2,11 +2,12 @@ use std::sync::Arc;
 
 use http::{to_request, to_response};
 use lambda_http::{run, service_fn, Body, Error, Response};
-use runtime::init_runtime;
+use runtime::{init_cache, init_runtime};
 use tailcall::async_graphql_hyper::GraphQLRequest;
 use tailcall::blueprint::Blueprint;
 use tailcall::config::reader::ConfigReader;
 use tailcall::http::{handle_request, AppContext};
+use tailcall::runtime::TargetRuntime;
 
 mod http;
 mod runtime;
@@ -26,6 +27,7 @@ async fn main() -> Result<(), Error> {
         .read("./config.graphql")
         .await?;
     let blueprint = Blueprint::try_from(&config)?;
+    let runtime = TargetRuntime { cache: init_cache(&blueprint.server.cache), ..runtime };
 
     let app_ctx = Arc::new(AppContext::new(blueprint, runtime));
 
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use tailcall::cache::InMemoryCache;
+use tailcall::config::ServerCache;
 use tailcall::runtime::TargetRuntime;
 use tailcall::{EntityCache, EnvIO, FileIO};
 use tokio::io::AsyncReadExt;
@@ -33,12 +34,16 @@ impl FileIO for LambdaFileIO {
     }
 
     async fn read<'a>(&'a self, path: &'a str) -> anyhow::Result<String> {
+        Ok(String::from_utf8(self.read_bytes(path).await?)?)
+    }
+
+    async fn read_bytes<'a>(&'a self, path: &'a str) -> anyhow::Result<Vec<u8>> {
         let mut file = tokio::fs::File::open(path).await?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)
             .await
             .map_err(anyhow::Error::from)?;
-        Ok(String::from_utf8(buffer)?)
+        Ok(buffer)
     }
 }
 
@@ -46,8 +51,8 @@ pub fn init_file() -> Arc<LambdaFileIO> {
     Arc::new(LambdaFileIO)
 }
 
-pub fn init_cache() -> Arc<EntityCache> {
-    Arc::new(InMemoryCache::new())
+pub fn init_cache(cache: &ServerCache) -> Arc<EntityCache> {
+    Arc::new(InMemoryCache::init(cache))
 }
 
 pub fn init_runtime() -> TargetRuntime {
@@ -57,6 +62,6 @@ pub fn init_runtime() -> TargetRuntime {
         http2_only: http,
         file: init_file(),
         env: init_env(),
-        cache: init_cache(),
+        cache: init_cache(&ServerCache::default()),
     }
 }
@@ -4,15 +4,15 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use async_graphql::futures_util::future::join_all;
-use async_graphql_value::ConstValue;
 use async_trait::async_trait;
 use criterion::{criterion_group, criterion_main, Criterion};
 use hyper::body::Bytes;
 use reqwest::Request;
 use tailcall::config::Batch;
 use tailcall::http::{DataLoaderRequest, HttpDataLoader, Response};
+use tailcall::lambda::CachedValue;
 use tailcall::runtime::TargetRuntime;
-use tailcall::{EnvIO, FileIO, HttpIO};
+use tailcall::{CacheEntry, EnvIO, FileIO, HttpIO};
 
 #[derive(Clone)]
 struct MockHttpClient {
@@ -52,13 +52,29 @@ struct Cache;
 #[async_trait]
 impl tailcall::Cache for Cache {
     type Key = u64;
-    type Value = ConstValue;
+    type Value = CachedValue;
 
     async fn set<'a>(&'a self, _: Self::Key, _: Self::Value, _: NonZeroU64) -> anyhow::Result<()> {
         unimplemented!("Not needed for this bench")
     }
 
-    async fn get<'a>(&'a self, _: &'a Self::Key) -> anyhow::Result<Option<Self::Value>> {
+    async fn get_entry<'a>(
+        &'a self,
+        _: &'a Self::Key,
+    ) -> anyhow::Result<Option<CacheEntry<Self::Value>>> {
+        unimplemented!("Not needed for this bench")
+    }
+
+    async fn tag<'a>(
+        &'a self,
+        _: &'a Self::Key,
+        _: Vec<String>,
+        _: NonZeroU64,
+    ) -> anyhow::Result<()> {
+        unimplemented!("Not needed for this bench")
+    }
+
+    async fn invalidate<'a>(&'a self, _: &'a [String]) -> anyhow::Result<()> {
         unimplemented!("Not needed for this bench")
     }
 }
@@ -254,6 +254,8 @@ fn request_context() -> RequestContext {
         grpc_data_loaders: Arc::new(vec![]),
         min_max_age: Arc::new(Mutex::new(None)),
         cache_public: Arc::new(Mutex::new(None)),
+        response_headers: Arc::new(Mutex::new(HeaderMap::new())),
+        grpc_metadata: Arc::new(Mutex::new(HeaderMap::new())),
         runtime,
     }
 }
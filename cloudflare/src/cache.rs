@@ -1,10 +1,12 @@
+use std::fmt::Write;
 use std::num::NonZeroU64;
 use std::rc::Rc;
+use std::time::Duration;
 
 use anyhow::Result;
-use async_graphql_value::ConstValue;
 use serde_json::Value;
-use tailcall::Cache;
+use tailcall::lambda::CachedValue;
+use tailcall::{Cache, CacheEntry};
 use worker::kv::KvStore;
 
 use crate::to_anyhow;
@@ -25,17 +27,57 @@ impl CloudflareChronoCache {
         self.env.kv("TMP_KV").map_err(to_anyhow)
     }
 }
+
+fn now() -> u64 {
+    worker::Date::now().as_millis()
+}
+
+/// Every entry associated with a tag is marked by a key of its own, made of
+/// the tag and the key of the entry, so concurrent writers don't overwrite
+/// each other's associations. The tag is hex encoded, so the prefix of a tag
+/// never lists the markers of another tag containing `:`.
+fn tag_prefix(tag: &str) -> String {
+    let mut prefix = "tag:".to_string();
+    for byte in tag.bytes() {
+        let _ = write!(prefix, "{:02x}", byte);
+    }
+    prefix.push(':');
+    prefix
+}
+
+/// The minimum `expiration_ttl` accepted by KV.
+const MIN_TTL: u64 = 60;
+
+async fn get_tagged_keys(kv_store: &KvStore, tag: &str) -> Result<Vec<String>> {
+    let prefix = tag_prefix(tag);
+    let mut keys = Vec::new();
+    let mut cursor = None;
+    loop {
+        let mut list = kv_store.list().prefix(prefix.clone());
+        if let Some(cursor) = cursor {
+            list = list.cursor(cursor);
+        }
+        let response = list.execute().await.map_err(to_anyhow)?;
+        keys.extend(response.keys.into_iter().map(|key| key.name));
+        if response.list_complete || response.cursor.is_none() {
+            return Ok(keys);
+        }
+        cursor = response.cursor;
+    }
+}
+
 // TODO: Needs fix
 #[async_trait::async_trait]
 impl Cache for CloudflareChronoCache {
     type Key = u64;
-    type Value = ConstValue;
-    async fn set<'a>(&'a self, key: u64, value: ConstValue, ttl: NonZeroU64) -> Result<()> {
+    type Value = CachedValue;
+    async fn set<'a>(&'a self, key: u64, value: CachedValue, ttl: NonZeroU64) -> Result<()> {
         let kv_store = self.get_kv()?;
         let ttl = ttl.get();
+        let entry = serde_json::json!({ "value": value, "storedAt": now() });
         async_std::task::spawn_local(async move {
             kv_store
-                .put(&key.to_string(), value.to_string())
+                .put(&key.to_string(), entry.to_string())
                 .map_err(to_anyhow)?
                 .expiration_ttl(ttl)
                 .execute()
@@ -45,16 +87,62 @@ impl Cache for CloudflareChronoCache {
         .await
     }
 
-    async fn get<'a>(&'a self, key: &'a u64) -> Result<Option<Self::Value>> {
+    async fn get_entry<'a>(&'a self, key: &'a u64) -> Result<Option<CacheEntry<CachedValue>>> {
         let kv_store = self.get_kv()?;
         let key = key.to_string();
         async_std::task::spawn_local(async move {
-            let val = kv_store
+            let Some(mut entry) = kv_store
                 .get(&key)
                 .json::<Value>()
                 .await
-                .map_err(to_anyhow)?;
-            Ok(val.map(ConstValue::from_json).transpose()?)
+                .map_err(to_anyhow)?
+            else {
+                return Ok(None);
+            };
+            let stored_at = entry["storedAt"].as_u64().unwrap_or_default();
+            let value = serde_json::from_value(entry["value"].take())?;
+            let age = Duration::from_millis(now().saturating_sub(stored_at));
+            Ok(Some(CacheEntry { value, age }))
+        })
+        .await
+    }
+
+    async fn tag<'a>(&'a self, key: &'a u64, tags: Vec<String>, ttl: NonZeroU64) -> Result<()> {
+        let kv_store = self.get_kv()?;
+        let key = key.to_string();
+        let ttl = ttl.get().max(MIN_TTL);
+        async_std::task::spawn_local(async move {
+            for tag in tags {
+                kv_store
+                    .put(&format!("{}{}", tag_prefix(&tag), key), "")
+                    .map_err(to_anyhow)?
+                    .expiration_ttl(ttl)
+                    .execute()
+                    .await
+                    .map_err(to_anyhow)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn invalidate<'a>(&'a self, tags: &'a [String]) -> Result<()> {
+        let kv_store = self.get_kv()?;
+        let tags = tags.to_vec();
+        async_std::task::spawn_local(async move {
+            for tag in tags {
+                let prefix = tag_prefix(&tag);
+                for tag_key in get_tagged_keys(&kv_store, &tag).await? {
+                    let key = tag_key
+                        .strip_prefix(&prefix)
+                        .and_then(|key| key.parse::<u64>().ok());
+                    if let Some(key) = key {
+                        kv_store.delete(&key.to_string()).await.map_err(to_anyhow)?;
+                    }
+                    kv_store.delete(&tag_key).await.map_err(to_anyhow)?;
+                }
+            }
+            Ok(())
         })
         .await
     }
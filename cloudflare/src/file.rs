@@ -28,7 +28,7 @@ impl CloudflareFileIO {
 unsafe impl Sync for CloudflareFileIO {}
 unsafe impl Send for CloudflareFileIO {}
 
-async fn get(bucket: Rc<worker::Bucket>, path: String) -> anyhow::Result<String> {
+async fn get(bucket: Rc<worker::Bucket>, path: String) -> anyhow::Result<Vec<u8>> {
     let maybe_object = bucket
         .get(path.clone())
         .execute()
@@ -37,8 +37,8 @@ async fn get(bucket: Rc<worker::Bucket>, path: String) -> anyhow::Result<String>
     let object = maybe_object.ok_or(anyhow!("File '{}' was not found in bucket", path))?;
 
     let body = match object.body() {
-        Some(body) => body.text().await.map_err(to_anyhow),
-        None => Ok("".to_string()),
+        Some(body) => body.bytes().await.map_err(to_anyhow),
+        None => Ok(Vec::new()),
     };
     body
 }
@@ -60,6 +60,14 @@ impl FileIO for CloudflareFileIO {
     }
 
     async fn read<'a>(&'a self, path: &'a str) -> anyhow::Result<String> {
+        let bucket = self.bucket.clone();
+        let path_cloned = path.to_string();
+        let content = spawn_local(get(bucket, path_cloned)).await?;
+        log::info!("File read: {} ... ok", path);
+        Ok(String::from_utf8(content)?)
+    }
+
+    async fn read_bytes<'a>(&'a self, path: &'a str) -> anyhow::Result<Vec<u8>> {
         let bucket = self.bucket.clone();
         let path_cloned = path.to_string();
         let content = spawn_local(get(bucket, path_cloned)).await?;
@@ -35,8 +35,8 @@ impl HttpIO for CloudflareHttp {
         let url = request.url().clone();
         // TODO: remove spawn local
         let res = spawn_local(async move {
-            let response = client.execute(request).await?.error_for_status()?;
-            Response::from_reqwest(response).await
+            let response = client.execute(request).await?;
+            Response::from_reqwest_checked(response).await
         })
         .await?;
         log::info!("{} {} {}", method, url, res.status.as_u16());
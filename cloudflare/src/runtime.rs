@@ -2,9 +2,8 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use anyhow::anyhow;
-use async_graphql_value::ConstValue;
 use tailcall::runtime::TargetRuntime;
-use tailcall::{EnvIO, FileIO, HttpIO};
+use tailcall::{EntityCache, EnvIO, FileIO, HttpIO};
 
 use crate::{cache, env, file, http};
 
@@ -20,7 +19,7 @@ fn init_http() -> Arc<dyn HttpIO> {
     Arc::new(http::CloudflareHttp::init())
 }
 
-fn init_cache(env: Rc<worker::Env>) -> Arc<dyn tailcall::Cache<Key = u64, Value = ConstValue>> {
+fn init_cache(env: Rc<worker::Env>) -> Arc<EntityCache> {
     Arc::new(cache::CloudflareChronoCache::init(env))
 }
 
@@ -37,18 +37,34 @@ impl AppContext {
                     field.map_expr(|expr| {
                         expr.modify(|expr| match expr {
                             Expression::IO(io) => match io {
-                                IO::Http { req_template, group_by, .. } => {
+                                IO::Http {
+                                    req_template,
+                                    group_by,
+                                    hedge,
+                                    response_format,
+                                    paginate,
+                                    on_status,
+                                    propagate_headers,
+                                    ..
+                                } => {
                                     let data_loader = HttpDataLoader::new(
                                         runtime.clone(),
                                         group_by.clone(),
                                         matches!(of_type, ListType { .. }),
                                     )
+                                    .hedge(hedge.clone())
+                                    .response_format(response_format.clone())
                                     .to_data_loader(upstream_batch.clone().unwrap_or_default());
 
                                     let result = Some(Expression::IO(IO::Http {
                                         req_template: req_template.clone(),
                                         group_by: group_by.clone(),
                                         dl_id: Some(DataLoaderId(http_data_loaders.len())),
+                                        hedge: hedge.clone(),
+                                        response_format: response_format.clone(),
+                                        paginate: paginate.clone(),
+                                        on_status: on_status.clone(),
+                                        propagate_headers: propagate_headers.clone(),
                                     }));
 
                                     http_data_loaders.push(data_loader);
@@ -75,7 +91,9 @@ impl AppContext {
                                     result
                                 }
 
-                                IO::Grpc { req_template, group_by, .. } => {
+                                IO::Grpc {
+                                    req_template, group_by, on_status, metadata, ..
+                                } => {
                                     let data_loader = GrpcDataLoader {
                                         runtime: runtime.clone(),
                                         operation: req_template.operation.clone(),
@@ -88,6 +106,8 @@ impl AppContext {
                                         req_template: req_template.clone(),
                                         group_by: group_by.clone(),
                                         dl_id: Some(DataLoaderId(grpc_data_loaders.len())),
+                                        on_status: on_status.clone(),
+                                        metadata: metadata.clone(),
                                     }));
 
                                     grpc_data_loaders.push(data_loader);
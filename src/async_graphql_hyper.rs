@@ -1,7 +1,9 @@
 use std::any::Any;
 
 use anyhow::Result;
-use async_graphql::{BatchResponse, Executor};
+use async_graphql::{BatchRequest, BatchResponse, Executor};
+use futures_util::stream::{self, BoxStream};
+use futures_util::{future, StreamExt};
 use hyper::header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
 use hyper::{Body, Response, StatusCode};
 use once_cell::sync::Lazy;
@@ -13,6 +15,11 @@ pub trait GraphQLRequestLike {
     async fn execute<E>(self, executor: &E) -> GraphQLResponse
     where
         E: Executor;
+    /// Executes the request as a stream of responses, one for each event of
+    /// a subscription.
+    fn execute_stream<E>(self, executor: &E) -> BoxStream<'static, async_graphql::Response>
+    where
+        E: Executor;
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +41,22 @@ impl GraphQLRequestLike for GraphQLBatchRequest {
     {
         GraphQLResponse(executor.execute_batch(self.0).await)
     }
+
+    fn execute_stream<E>(self, executor: &E) -> BoxStream<'static, async_graphql::Response>
+    where
+        E: Executor,
+    {
+        match self.0 {
+            BatchRequest::Single(request) => executor.execute_stream(request, None),
+            BatchRequest::Batch(requests) => {
+                let streams = requests
+                    .into_iter()
+                    .map(|request| executor.execute_stream(request, None))
+                    .collect::<Vec<_>>();
+                stream::iter(streams).flatten().boxed()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -55,6 +78,13 @@ impl GraphQLRequestLike for GraphQLRequest {
     {
         GraphQLResponse(executor.execute(self.0).await.into())
     }
+
+    fn execute_stream<E>(self, executor: &E) -> BoxStream<'static, async_graphql::Response>
+    where
+        E: Executor,
+    {
+        executor.execute_stream(self.0, None)
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -109,6 +139,29 @@ impl GraphQLQuery {
 static APPLICATION_JSON: Lazy<HeaderValue> =
     Lazy::new(|| HeaderValue::from_static("application/json"));
 
+static TEXT_EVENT_STREAM: Lazy<HeaderValue> =
+    Lazy::new(|| HeaderValue::from_static("text/event-stream"));
+
+/// Sends the responses as they come, using the distinct connections mode of
+/// the GraphQL over Server-Sent Events protocol.
+pub fn to_event_stream(
+    responses: BoxStream<'static, async_graphql::Response>,
+) -> Result<Response<hyper::Body>> {
+    let events = responses
+        .map(|response| {
+            serde_json::to_string(&response).map(|data| format!("event: next\ndata: {}\n\n", data))
+        })
+        .chain(stream::once(future::ready(Ok(
+            "event: complete\ndata:\n\n".to_string(),
+        ))));
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, TEXT_EVENT_STREAM.as_ref())
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::wrap_stream(events))?)
+}
+
 impl GraphQLResponse {
     pub fn to_response(self) -> Result<Response<hyper::Body>> {
         let mut response = Response::builder()
@@ -128,6 +181,21 @@ impl GraphQLResponse {
         Ok(response)
     }
 
+    /// Adds the extension `name` to the responses.
+    pub fn set_extension(mut self, name: &str, value: async_graphql::Value) -> GraphQLResponse {
+        match self.0 {
+            BatchResponse::Single(ref mut res) => {
+                res.extensions.insert(name.to_string(), value);
+            }
+            BatchResponse::Batch(ref mut list) => {
+                for res in list {
+                    res.extensions.insert(name.to_string(), value.clone());
+                }
+            }
+        };
+        self
+    }
+
     /// Sets the `cache_control` for a given `GraphQLResponse`.
     ///
     /// The function modifies the `GraphQLResponse` to set the `cache_control` `max_age`
@@ -113,6 +113,7 @@ pub struct EnumValueDefinition {
 pub struct SchemaDefinition {
     pub query: String,
     pub mutation: Option<String>,
+    pub subscription: Option<String>,
     pub directives: Vec<Directive>,
 }
 
@@ -191,6 +192,10 @@ impl Blueprint {
         self.schema.mutation.clone()
     }
 
+    pub fn subscription(&self) -> Option<String> {
+        self.schema.subscription.clone()
+    }
+
     fn drop_resolvers(mut self) -> Self {
         for def in self.definitions.iter_mut() {
             if let Definition::ObjectTypeDefinition(def) = def {
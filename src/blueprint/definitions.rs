@@ -7,9 +7,10 @@ use crate::blueprint::*;
 use crate::config;
 use crate::config::{Config, Field, GraphQLOperationType, Union};
 use crate::directive::DirectiveCodec;
-use crate::lambda::{Cache, Context, Expression};
+use crate::lambda::{Cache, CacheInvalidate, Context, Expression};
+use crate::mustache::Mustache;
 use crate::try_fold::TryFold;
-use crate::valid::{Valid, Validator};
+use crate::valid::{Valid, ValidationError, Validator};
 
 pub fn to_scalar_type_definition(name: &str) -> Valid<Definition, String> {
     Valid::succeed(Definition::ScalarTypeDefinition(ScalarTypeDefinition {
@@ -340,18 +341,94 @@ pub fn update_nested_resolvers<'a>(
     )
 }
 
+fn to_template(
+    template: &str,
+    b_field: &FieldDefinition,
+    type_of: &config::Type,
+    config: &Config,
+) -> Valid<Mustache, String> {
+    Valid::from(Mustache::parse(template).map_err(|e| ValidationError::new(e.to_string())))
+        .and_then(|mustache| {
+            b_field
+                .validate_template(&mustache, type_of, config)
+                .map_to(mustache)
+        })
+}
+
+/// Parses cache tags, in which `value` refers to the value of the field
+/// instead of its parent.
+fn to_tags(
+    tags: &[String],
+    b_field: &FieldDefinition,
+    field: &Field,
+    config: &Config,
+) -> Valid<Vec<Mustache>, String> {
+    let default_type = config::Type::default();
+    let output_type = config.find_type(&field.type_of).unwrap_or(&default_type);
+
+    Valid::from_iter(tags, |tag| to_template(tag, b_field, output_type, config)).trace("tags")
+}
+
 /// Wraps the IO Expression with Expression::Cached
 /// if `Field::cache` is present for that field
 pub fn update_cache_resolvers<'a>(
 ) -> TryFold<'a, (&'a ConfigModule, &'a Field, &'a config::Type, &'a str), FieldDefinition, String>
 {
     TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
-        move |(_config, field, _, _name), mut b_field| {
-            if let Some(config::Cache { max_age }) = field.cache.as_ref() {
-                b_field.map_expr(|expression| Cache::wrap(*max_age, expression))
-            }
+        move |(config, field, type_of, _name), mut b_field| {
+            let Some(cache) = field.cache.as_ref() else {
+                return Valid::succeed(b_field);
+            };
+
+            let key = match cache.key.as_ref() {
+                Some(key) => to_template(key, &b_field, type_of, config)
+                    .trace("key")
+                    .map(Some),
+                None => Valid::succeed(None),
+            };
+
+            Valid::<(), String>::fail("negativeStatuses requires a negativeTtl".to_string())
+                .when(|| !cache.negative_statuses.is_empty() && cache.negative_ttl.is_none())
+                .trace("negativeStatuses")
+                .and(key.zip(to_tags(&cache.tags, &b_field, field, config)))
+                .trace(config::Cache::trace_name().as_str())
+                .map(|(key, tags)| {
+                    b_field.map_expr(|expression| {
+                        Cache::wrap(cache, key.clone(), tags.clone(), expression)
+                    });
+                    b_field
+                })
+        },
+    )
+}
 
-            Valid::succeed(b_field)
+/// Evicts the tagged cache entries once the resolver of a mutation field
+/// succeeds, if `Field::cache_invalidate` is present for that field
+pub fn update_cache_invalidate(
+    operation_type: &GraphQLOperationType,
+) -> TryFold<'_, (&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String> {
+    TryFold::<(&ConfigModule, &Field, &config::Type, &str), FieldDefinition, String>::new(
+        move |(config, field, _, _name), mut b_field| {
+            let Some(cache_invalidate) = field.cache_invalidate.as_ref() else {
+                return Valid::succeed(b_field);
+            };
+
+            Valid::<(), String>::fail(
+                "@cacheInvalidate can only be used on mutation fields".to_string(),
+            )
+            .when(|| *operation_type != GraphQLOperationType::Mutation)
+            .and(
+                Valid::<(), String>::fail("No resolver has been found".to_string())
+                    .when(|| b_field.resolver.is_none()),
+            )
+            .and(to_tags(&cache_invalidate.tags, &b_field, field, config))
+            .trace(config::CacheInvalidate::trace_name().as_str())
+            .map(|tags| {
+                b_field.resolver = b_field.resolver.take().map(|expr| {
+                    Expression::CacheInvalidate(CacheInvalidate { tags, expr: Box::new(expr) })
+                });
+                b_field
+            })
         },
     )
 }
@@ -372,6 +449,13 @@ fn to_fields(
 ) -> Valid<Vec<FieldDefinition>, String> {
     let operation_type = if config_set.schema.mutation.as_deref().eq(&Some(object_name)) {
         GraphQLOperationType::Mutation
+    } else if config_set
+        .schema
+        .subscription
+        .as_deref()
+        .eq(&Some(object_name))
+    {
+        GraphQLOperationType::Subscription
     } else {
         GraphQLOperationType::Query
     };
@@ -395,6 +479,7 @@ fn to_fields(
             .and(update_modify().trace(config::Modify::trace_name().as_str()))
             .and(update_nested_resolvers())
             .and(update_cache_resolvers())
+            .and(update_cache_invalidate(&operation_type))
             .try_fold(
                 &(config_set, field, type_of, name),
                 FieldDefinition::default(),
@@ -74,12 +74,38 @@ fn to_json_schema<T>(field: &T, config: &Config) -> JsonSchema
 where
     T: TypeLike,
 {
-    let type_of = field.name();
     let list = field.list();
     let required = field.non_null();
-    let type_ = config.find_type(type_of);
-    let schema = match type_ {
-        Some(type_) => {
+    let schema = to_json_schema_for_type(field.name(), config);
+
+    if !required {
+        if list {
+            JsonSchema::Opt(Box::new(JsonSchema::Arr(Box::new(schema))))
+        } else {
+            JsonSchema::Opt(Box::new(schema))
+        }
+    } else if list {
+        JsonSchema::Arr(Box::new(schema))
+    } else {
+        schema
+    }
+}
+
+fn to_json_schema_for_type(type_of: &str, config: &Config) -> JsonSchema {
+    if let Some(union_) = config.find_union(type_of) {
+        let types = union_
+            .types
+            .iter()
+            .map(|name| (name.clone(), to_json_schema_for_type(name, config)))
+            .collect();
+        return JsonSchema::Union(types);
+    }
+
+    match config.find_type(type_of) {
+        Some(type_) if type_.variants.is_some() => {
+            JsonSchema::Enum(type_.variants.clone().unwrap_or_default())
+        }
+        Some(type_) if !type_.scalar => {
             let mut schema_fields = HashMap::new();
             for (name, field) in type_.fields.iter() {
                 if field.script.is_none() && field.http.is_none() {
@@ -88,25 +114,14 @@ where
             }
             JsonSchema::Obj(schema_fields)
         }
-        None => match type_of {
+        _ => match type_of {
             "String" => JsonSchema::Str {},
             "Int" => JsonSchema::Num {},
+            "Float" => JsonSchema::Num {},
             "Boolean" => JsonSchema::Bool {},
             "JSON" => JsonSchema::Obj(HashMap::new()),
             _ => JsonSchema::Str {},
         },
-    };
-
-    if !required {
-        if list {
-            JsonSchema::Opt(Box::new(JsonSchema::Arr(Box::new(schema))))
-        } else {
-            JsonSchema::Opt(Box::new(schema))
-        }
-    } else if list {
-        JsonSchema::Arr(Box::new(schema))
-    } else {
-        schema
     }
 }
 
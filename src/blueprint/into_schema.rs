@@ -1,12 +1,18 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::sync::Arc;
 
-use async_graphql::dynamic::{self, FieldFuture, FieldValue, SchemaBuilder};
+use async_graphql::dynamic::{
+    self, FieldFuture, FieldValue, SchemaBuilder, SubscriptionFieldFuture,
+};
+use async_graphql::ErrorExtensions;
 use async_graphql_value::ConstValue;
+use futures_util::future;
+use futures_util::stream::{self, StreamExt};
 
-use crate::blueprint::{Blueprint, Definition, Type};
+use crate::blueprint::{Blueprint, Definition, ObjectTypeDefinition, Type};
 use crate::http::RequestContext;
-use crate::lambda::{Concurrent, Eval, EvaluationContext};
+use crate::lambda::{Concurrent, Eval, EvaluationContext, EvaluationError, Expression};
 
 fn to_type_ref(type_of: &Type) -> dynamic::TypeRef {
     match type_of {
@@ -30,12 +36,95 @@ fn to_type_ref(type_of: &Type) -> dynamic::TypeRef {
     }
 }
 
-fn to_type(def: &Definition) -> dynamic::Type {
+/// Converts a resolved value, the objects of a union being given the type
+/// named by their `__typename`.
+fn to_field_value(value: ConstValue, is_union: bool) -> FieldValue<'static> {
+    match value {
+        ConstValue::List(list) => FieldValue::list(
+            list.into_iter()
+                .map(|value| to_field_value(value, is_union)),
+        ),
+        ConstValue::Object(object) if is_union => {
+            let type_name = match object.get("__typename") {
+                Some(ConstValue::String(type_name)) => Some(type_name.clone()),
+                _ => None,
+            };
+            let value = FieldValue::from(ConstValue::Object(object));
+            match type_name {
+                Some(type_name) => value.with_type(type_name),
+                None => value,
+            }
+        }
+        value => FieldValue::from(value),
+    }
+}
+
+/// Builds the root subscription type, where each field resolves to a stream
+/// of events.
+fn to_subscription(def: &ObjectTypeDefinition, unions: &HashSet<String>) -> dynamic::Subscription {
+    let mut subscription = dynamic::Subscription::new(def.name.clone());
+    for field in def.fields.iter() {
+        let field = field.clone();
+        let is_union = unions.contains(field.of_type.name());
+        let type_ref = to_type_ref(&field.of_type);
+        let field_name = &field.name.clone();
+        let mut dyn_schema_field =
+            dynamic::SubscriptionField::new(field_name, type_ref, move |ctx| {
+                let resolver = field.resolver.clone();
+                let field_name = field.name.clone();
+                SubscriptionFieldFuture::new(async move {
+                    let req_ctx = ctx.ctx.data::<Arc<RequestContext>>()?;
+                    let ctx = EvaluationContext::new(req_ctx, &ctx);
+                    let events = match &resolver {
+                        Some(expr) => expr.eval_stream(&ctx).await.map_err(to_error)?,
+                        None => {
+                            let value = ctx.path_value(&[&field_name]).map(|a| a.to_owned());
+                            stream::once(future::ready(Ok(value.unwrap_or_default()))).boxed()
+                        }
+                    };
+
+                    Ok(events.map(move |event| {
+                        event
+                            .map_err(to_error)
+                            .map(|value| to_field_value(value, is_union))
+                    }))
+                })
+            });
+        if let Some(description) = &field.description {
+            dyn_schema_field = dyn_schema_field.description(description);
+        }
+        for arg in field.args.iter() {
+            dyn_schema_field = dyn_schema_field.argument(dynamic::InputValue::new(
+                arg.name.clone(),
+                to_type_ref(&arg.of_type),
+            ));
+        }
+        subscription = subscription.field(dyn_schema_field);
+    }
+
+    subscription
+}
+
+fn to_error(error: anyhow::Error) -> async_graphql::Error {
+    match error.downcast::<EvaluationError>() {
+        Ok(error) => error.extend(),
+        Err(error) => async_graphql::Error::from(error),
+    }
+}
+
+fn to_type(def: &Definition, unions: &HashSet<String>) -> dynamic::Type {
     match def {
         Definition::ObjectTypeDefinition(def) => {
             let mut object = dynamic::Object::new(def.name.clone());
             for field in def.fields.iter() {
                 let field = field.clone();
+                let is_union = unions.contains(field.of_type.name());
+                // a status resolved to null makes the whole field null, instead of an
+                // object whose fields are all null
+                let has_null_status = field
+                    .resolver
+                    .as_ref()
+                    .is_some_and(Expression::has_null_status);
                 let type_ref = to_type_ref(&field.of_type);
                 let field_name = &field.name.clone();
                 let mut dyn_schema_field = dynamic::Field::new(field_name, type_ref, move |ctx| {
@@ -44,20 +133,22 @@ fn to_type(def: &Definition) -> dynamic::Type {
                     match &field.resolver {
                         None => {
                             let ctx = EvaluationContext::new(req_ctx, &ctx);
-                            FieldFuture::from_value(
-                                ctx.path_value(&[field_name]).map(|a| a.to_owned()),
-                            )
+                            let value = ctx.path_value(&[field_name]).map(|a| a.to_owned());
+                            FieldFuture::Value(value.map(|value| to_field_value(value, is_union)))
                         }
                         Some(expr) => {
                             let expr = expr.to_owned();
                             FieldFuture::new(async move {
                                 let ctx = EvaluationContext::new(req_ctx, &ctx);
 
-                                let const_value = expr.eval(&ctx, &Concurrent::Sequential).await?;
+                                let const_value = expr
+                                    .eval(&ctx, &Concurrent::Sequential)
+                                    .await
+                                    .map_err(to_error)?;
 
                                 let p = match const_value {
-                                    ConstValue::List(a) => FieldValue::list(a),
-                                    a => FieldValue::from(a),
+                                    ConstValue::Null if has_null_status => return Ok(None),
+                                    a => to_field_value(a, is_union),
                                 };
                                 Ok(Some(p))
                             })
@@ -131,10 +222,27 @@ impl From<&Blueprint> for SchemaBuilder {
     fn from(blueprint: &Blueprint) -> Self {
         let query = blueprint.query();
         let mutation = blueprint.mutation();
-        let mut schema = dynamic::Schema::build(query.as_str(), mutation.as_deref(), None);
+        let subscription = blueprint.subscription();
+        let mut schema =
+            dynamic::Schema::build(query.as_str(), mutation.as_deref(), subscription.as_deref());
+        let unions = blueprint
+            .definitions
+            .iter()
+            .filter_map(|def| match def {
+                Definition::UnionTypeDefinition(def) => Some(def.name.clone()),
+                _ => None,
+            })
+            .collect();
 
         for def in blueprint.definitions.iter() {
-            schema = schema.register(to_type(def));
+            schema = match def {
+                Definition::ObjectTypeDefinition(def)
+                    if subscription.as_deref() == Some(def.name.as_str()) =>
+                {
+                    schema.register(to_subscription(def, &unions))
+                }
+                def => schema.register(to_type(def, &unions)),
+            };
         }
 
         schema
@@ -1,6 +1,7 @@
 use super::{is_scalar, to_type, FieldDefinition, Type};
 use crate::config::{self, Config};
 use crate::lambda::{Expression, IO};
+use crate::mustache::Mustache;
 use crate::valid::{Valid, Validator};
 
 struct MustachePartsValidator<'a> {
@@ -101,6 +102,22 @@ impl<'a> MustachePartsValidator<'a> {
 }
 
 impl FieldDefinition {
+    /// Validates the expressions of a template rendered in the context of this
+    /// field, where missing and null values render as empty strings.
+    pub fn validate_template(
+        &self,
+        mustache: &Mustache,
+        type_of: &config::Type,
+        config: &Config,
+    ) -> Valid<(), String> {
+        let parts_validator = MustachePartsValidator::new(type_of, config, self);
+
+        Valid::from_iter(mustache.expression_segments(), |parts| {
+            parts_validator.validate(parts, true)
+        })
+        .unit()
+    }
+
     pub fn validate_field(&self, type_of: &config::Type, config: &Config) -> Valid<(), String> {
         // XXX we could use `Mustache`'s `render` method with a mock
         // struct implementing the `PathString` trait encapsulating `validation_map`
@@ -1,11 +1,13 @@
+use std::time::Duration;
+
 use prost_reflect::prost_types::FileDescriptorSet;
 use prost_reflect::FieldDescriptor;
 
 use crate::blueprint::{FieldDefinition, TypeLike};
 use crate::config::group_by::GroupBy;
-use crate::config::{Config, ConfigModule, Field, GraphQLOperationType, Grpc};
+use crate::config::{Config, ConfigModule, Field, GraphQLOperationType, Grpc, StatusAction};
 use crate::grpc::protobuf::{ProtobufOperation, ProtobufSet};
-use crate::grpc::request_template::RequestTemplate;
+use crate::grpc::request_template::{to_binary_metadata, RequestTemplate};
 use crate::json::JsonSchema;
 use crate::lambda::{Expression, IO};
 use crate::mustache::Mustache;
@@ -52,6 +54,35 @@ fn to_operation(
     })
 }
 
+fn to_batch_operation(
+    grpc: &Grpc,
+    operation: ProtobufOperation,
+) -> Valid<ProtobufOperation, String> {
+    match &grpc.batch_key {
+        Some(_) if grpc.group_by.is_empty() => {
+            Valid::fail("batchKey requires groupBy to be set".to_string()).trace("batchKey")
+        }
+        Some(batch_key) => Valid::from(
+            operation
+                .with_batch_key(batch_key)
+                .map_err(|e| ValidationError::new(e.to_string())),
+        )
+        .trace("batchKey"),
+        None => Valid::succeed(operation),
+    }
+}
+
+/// Checks that the constant values of binary `-bin` metadata are base64.
+fn validate_binary_metadata(grpc: &Grpc) -> Valid<(), String> {
+    Valid::from_iter(grpc.headers.iter(), |(key, value)| {
+        let is_const = Mustache::parse(value).map_or(false, |value| value.is_const());
+        Valid::<(), String>::fail("Binary metadata must be base64".to_string())
+            .when(|| key.ends_with("-bin") && is_const && to_binary_metadata(value).is_none())
+            .trace(key)
+    })
+    .unit()
+}
+
 fn json_schema_from_field(config: &Config, field: &Field) -> FieldSchema {
     let field_schema = crate::blueprint::to_json_schema_for_field(field, config);
     let args_schema = crate::blueprint::to_json_schema_for_args(&field.args, config);
@@ -64,6 +95,7 @@ pub struct FieldSchema {
 fn validate_schema(
     field_schema: FieldSchema,
     operation: &ProtobufOperation,
+    operation_type: &GraphQLOperationType,
     name: &str,
 ) -> Valid<(), String> {
     let input_type = &operation.input_type;
@@ -72,6 +104,15 @@ fn validate_schema(
     Valid::from(JsonSchema::try_from(input_type))
         .zip(Valid::from(JsonSchema::try_from(output_type)))
         .and_then(|(_input_schema, output_schema)| {
+            // the messages of a stream are collected into a list, unless they are
+            // emitted one by one as the events of a subscription
+            let output_schema = if operation.is_server_streaming()
+                && *operation_type != GraphQLOperationType::Subscription
+            {
+                JsonSchema::Arr(Box::new(output_schema))
+            } else {
+                output_schema
+            };
             // TODO: add validation for input schema - should compare result grpc.body to schema
             let fields = field_schema.field;
             let _args = field_schema.args;
@@ -160,16 +201,39 @@ pub fn compile_grpc(inputs: CompileGrpc) -> Valid<Expression, String> {
                 format!("File descriptor not found for proto id: {}", method.id),
             )
             .and_then(|file_descriptor_set| to_operation(&method, file_descriptor_set))
+            .and_then(|operation| {
+                Valid::<(), String>::fail(format!(
+                    "Client streaming method {} is not supported",
+                    operation.name()
+                ))
+                .when(|| operation.is_client_streaming())
+                .map_to(operation)
+            })
+            .and_then(|operation| to_batch_operation(grpc, operation))
             .fuse(to_url(grpc, &method, config_set))
-            .fuse(helpers::headers::to_mustache_headers(&grpc.headers))
+            .fuse(
+                validate_binary_metadata(grpc)
+                    .and(helpers::headers::to_mustache_headers(&grpc.headers)),
+            )
             .fuse(helpers::body::to_body(grpc.body.as_deref()))
             .into()
         })
         .and_then(|(operation, url, headers, body)| {
             let validation = if validate_with_schema {
-                let field_schema = json_schema_from_field(config_set, field);
+                let mut field_schema = json_schema_from_field(config_set, field);
+                // a status resolved to null makes the field nullable whatever the output
+                // message is
+                if grpc
+                    .on_status
+                    .iter()
+                    .any(|on_status| on_status.action == StatusAction::Null)
+                {
+                    if let JsonSchema::Opt(schema) = field_schema.field {
+                        field_schema.field = *schema;
+                    }
+                }
                 if grpc.group_by.is_empty() {
-                    validate_schema(field_schema, &operation, field.name()).unit()
+                    validate_schema(field_schema, &operation, operation_type, field.name()).unit()
                 } else {
                     validate_group_by(&field_schema, &operation, grpc.group_by.clone()).unit()
                 }
@@ -185,15 +249,31 @@ pub fn compile_grpc(inputs: CompileGrpc) -> Valid<Expression, String> {
                 operation,
                 body,
                 operation_type: operation_type.clone(),
+                compression: grpc
+                    .compression
+                    .or(config_set.upstream.grpc_compression)
+                    .unwrap_or_default(),
+                protocol: grpc.protocol,
+                timeout: Some(Duration::from_secs(
+                    grpc.timeout.unwrap_or(config_set.upstream.get_timeout()),
+                )),
             };
             if !grpc.group_by.is_empty() {
                 Expression::IO(IO::Grpc {
                     req_template,
                     group_by: Some(GroupBy::new(grpc.group_by.clone())),
                     dl_id: None,
+                    on_status: grpc.on_status.clone(),
+                    metadata: grpc.metadata.clone(),
                 })
             } else {
-                Expression::IO(IO::Grpc { req_template, group_by: None, dl_id: None })
+                Expression::IO(IO::Grpc {
+                    req_template,
+                    group_by: None,
+                    dl_id: None,
+                    on_status: grpc.on_status.clone(),
+                    metadata: grpc.metadata.clone(),
+                })
             }
         })
 }
@@ -8,6 +8,56 @@ use crate::try_fold::TryFold;
 use crate::valid::{Valid, ValidationError, Validator};
 use crate::{config, helpers};
 
+fn validate_paginate(http: &config::Http) -> Valid<(), String> {
+    let Some(paginate) = &http.paginate else {
+        return Valid::succeed(());
+    };
+
+    Valid::<(), String>::fail("Pagination is only supported for GET requests".to_string())
+        .when(|| http.method != Method::GET)
+        .and(
+            Valid::<(), String>::fail("Pagination can't be combined with groupBy".to_string())
+                .when(|| !http.group_by.is_empty()),
+        )
+        .and(
+            Valid::<(), String>::fail("cursorPath is required by the Cursor strategy".to_string())
+                .when(|| {
+                    paginate.strategy == config::PaginationStrategy::Cursor
+                        && paginate.cursor_path.is_empty()
+                })
+                .trace("cursorPath"),
+        )
+        .and(
+            Valid::<(), String>::fail("maxPages should be greater than 0".to_string())
+                .when(|| paginate.max_pages == Some(0))
+                .trace("maxPages"),
+        )
+}
+
+/// Combines the headers propagated from every upstream with the ones of the
+/// field, the merge rule of the field wins when a header is in both lists.
+fn to_propagate_headers(
+    config_set: &config::ConfigModule,
+    http: &config::Http,
+) -> Valid<Vec<config::PropagateHeader>, String> {
+    get_propagate_headers(&http.propagate_headers)
+        .trace("propagateHeaders")
+        .map(|field_headers| {
+            let mut headers: Vec<config::PropagateHeader> = config_set
+                .upstream
+                .propagate_headers
+                .iter()
+                .map(|header| config::PropagateHeader {
+                    name: header.name.to_lowercase(),
+                    merge: header.merge.clone(),
+                })
+                .filter(|header| field_headers.iter().all(|field| field.name != header.name))
+                .collect();
+            headers.extend(field_headers);
+            headers
+        })
+}
+
 pub fn compile_http(
     config_set: &config::ConfigModule,
     field: &config::Field,
@@ -24,6 +74,18 @@ pub fn compile_http(
                     && !http.group_by.is_empty()
             }),
         )
+        .and(
+            Valid::<(), String>::fail("Hedging is only supported for GET requests".to_string())
+                .when(|| http.hedge.is_some() && http.method != Method::GET),
+        )
+        .and(validate_paginate(http).trace("paginate"))
+        .and(
+            Valid::from_iter(http.on_status.iter(), |on_status| {
+                Valid::<(), String>::fail(format!("{} is not an error status", on_status.status))
+                    .when(|| !(400..600).contains(&on_status.status))
+            })
+            .trace("onStatus"),
+        )
         .and(Valid::from_option(
             http.base_url
                 .as_ref()
@@ -31,7 +93,8 @@ pub fn compile_http(
             "No base URL defined".to_string(),
         ))
         .zip(helpers::headers::to_mustache_headers(&http.headers))
-        .and_then(|(base_url, headers)| {
+        .zip(to_propagate_headers(config_set, http))
+        .and_then(|((base_url, headers), propagate_headers)| {
             let mut base_url = base_url.trim_end_matches('/').to_owned();
             base_url.push_str(http.path.clone().as_str());
 
@@ -53,19 +116,33 @@ pub fn compile_http(
                     .body(http.body.clone())
                     .encoding(http.encoding.clone()),
             )
-            .map(|req_tmpl| req_tmpl.headers(headers))
+            .map(|req_tmpl| (req_tmpl.headers(headers), propagate_headers))
             .map_err(|e| ValidationError::new(e.to_string()))
             .into()
         })
-        .map(|req_template| {
+        .map(|(req_template, propagate_headers)| {
             if !http.group_by.is_empty() && http.method == Method::GET {
                 Expression::IO(IO::Http {
                     req_template,
                     group_by: Some(GroupBy::new(http.group_by.clone())),
                     dl_id: None,
+                    hedge: http.hedge.clone(),
+                    response_format: http.response_format.clone(),
+                    paginate: http.paginate.clone(),
+                    on_status: http.on_status.clone(),
+                    propagate_headers,
                 })
             } else {
-                Expression::IO(IO::Http { req_template, group_by: None, dl_id: None })
+                Expression::IO(IO::Http {
+                    req_template,
+                    group_by: None,
+                    dl_id: None,
+                    hedge: http.hedge.clone(),
+                    response_format: http.response_format.clone(),
+                    paginate: http.paginate.clone(),
+                    on_status: http.on_status.clone(),
+                    propagate_headers,
+                })
             }
         })
 }
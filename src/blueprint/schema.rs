@@ -45,7 +45,11 @@ pub fn validate_field_has_resolver(
         .when(|| {
             if !field.has_resolver() {
                 let f_type = &field.type_of;
-                if let Some(ty) = types.get(f_type) {
+                // scalars and enums are leaves, resolved from their parent
+                if let Some(ty) = types
+                    .get(f_type)
+                    .filter(|ty| !ty.scalar && ty.variants.is_none())
+                {
                     let res = validate_type_has_resolvers(f_type, ty, types);
                     return !res.is_succeed();
                 } else {
@@ -93,10 +97,26 @@ fn validate_mutation(config: &Config) -> Valid<(), String> {
     }
 }
 
+fn validate_subscription(config: &Config) -> Valid<(), String> {
+    let subscription_type_name = config.schema.subscription.as_ref();
+
+    if let Some(subscription_type_name) = subscription_type_name {
+        let Some(subscription) = config.find_type(subscription_type_name) else {
+            return Valid::fail("Subscription type is not defined".to_owned())
+                .trace(subscription_type_name);
+        };
+
+        validate_type_has_resolvers(subscription_type_name, subscription, &config.types)
+    } else {
+        Valid::succeed(())
+    }
+}
+
 pub fn to_schema<'a>() -> TryFoldConfig<'a, SchemaDefinition> {
     TryFoldConfig::new(|config, _| {
         validate_query(config)
             .and(validate_mutation(config))
+            .and(validate_subscription(config))
             .and(Valid::from_option(
                 config.schema.query.as_ref(),
                 "Query root is missing".to_owned(),
@@ -105,6 +125,7 @@ pub fn to_schema<'a>() -> TryFoldConfig<'a, SchemaDefinition> {
             .map(|(query_type_name, directive)| SchemaDefinition {
                 query: query_type_name.to_owned(),
                 mutation: config.schema.mutation.clone(),
+                subscription: config.schema.subscription.clone(),
                 directives: vec![directive],
             })
     })
@@ -21,6 +21,7 @@ pub struct Server {
     pub enable_response_validation: bool,
     pub enable_batch_requests: bool,
     pub enable_showcase: bool,
+    pub enable_metrics: bool,
     pub global_response_timeout: i64,
     pub worker: usize,
     pub port: u16,
@@ -30,6 +31,7 @@ pub struct Server {
     pub http: Http,
     pub pipeline_flush: bool,
     pub script: Option<Script>,
+    pub cache: config::ServerCache,
 }
 
 /// Mimic of mini_v8::Script that's wasm compatible
@@ -107,7 +109,8 @@ impl TryFrom<crate::config::ConfigModule> for Server {
                 (config_server).get_response_headers().0,
             ))
             .fuse(to_script(&config_set))
-            .map(|(hostname, http, response_headers, script)| Server {
+            .fuse(validate_cache((config_server).get_cache()))
+            .map(|(hostname, http, response_headers, script, cache)| Server {
                 enable_apollo_tracing: (config_server).enable_apollo_tracing(),
                 enable_cache_control_header: (config_server).enable_cache_control(),
                 enable_graphiql: (config_server).enable_graphiql(),
@@ -116,6 +119,7 @@ impl TryFrom<crate::config::ConfigModule> for Server {
                 enable_response_validation: (config_server).enable_http_validation(),
                 enable_batch_requests: (config_server).enable_batch_requests(),
                 enable_showcase: (config_server).enable_showcase(),
+                enable_metrics: (config_server).enable_metrics(),
                 global_response_timeout: (config_server).get_global_response_timeout(),
                 http,
                 worker: (config_server).get_workers(),
@@ -125,6 +129,7 @@ impl TryFrom<crate::config::ConfigModule> for Server {
                 pipeline_flush: (config_server).get_pipeline_flush(),
                 response_headers,
                 script,
+                cache,
             })
             .to_result()
     }
@@ -160,6 +165,26 @@ fn validate_hostname(hostname: String) -> Valid<IpAddr, String> {
     }
 }
 
+fn validate_cache(cache: config::ServerCache) -> Valid<config::ServerCache, String> {
+    match &cache.redis {
+        Some(redis) => Valid::from(
+            url::Url::parse(&redis.url)
+                .map_err(|e| ValidationError::new(format!("Parsing failed because of {}", e))),
+        )
+        .and_then(|url| match url.scheme() {
+            "redis" | "rediss" | "redis+unix" | "unix" => Valid::succeed(()),
+            scheme => Valid::fail(format!("Unsupported scheme: {}", scheme)),
+        })
+        .trace("url")
+        .trace("redis")
+        .trace("cache")
+        .trace("@server")
+        .trace("schema")
+        .map_to(cache),
+        None => Valid::succeed(cache),
+    }
+}
+
 fn handle_response_headers(resp_headers: BTreeMap<String, String>) -> Valid<HeaderMap, String> {
     Valid::from_iter(resp_headers.iter(), |(k, v)| {
         let name = Valid::from(
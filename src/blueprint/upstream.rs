@@ -1,8 +1,8 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use derive_setters::Setters;
 
-use crate::config::{self, Batch};
+use crate::config::{self, Batch, PropagateHeader};
 use crate::valid::{Valid, ValidationError, Validator};
 
 #[derive(PartialEq, Eq, Clone, Debug, schemars::JsonSchema)]
@@ -27,6 +27,10 @@ pub struct Upstream {
     pub http_cache: bool,
     pub batch: Option<Batch>,
     pub http2_only: bool,
+    pub max_concurrent_requests: Option<usize>,
+    pub concurrency_limits: BTreeMap<String, usize>,
+    pub concurrency_queue_timeout: Option<u64>,
+    pub propagate_headers: Vec<PropagateHeader>,
 }
 
 impl Upstream {
@@ -53,23 +57,36 @@ impl TryFrom<crate::config::Upstream> for Upstream {
         get_batch(&config_upstream)
             .fuse(get_base_url(&config_upstream))
             .fuse(get_proxy(&config_upstream))
-            .map(|(batch, base_url, proxy)| Upstream {
-                pool_idle_timeout: (config_upstream).get_pool_idle_timeout(),
-                pool_max_idle_per_host: (config_upstream).get_pool_max_idle_per_host(),
-                keep_alive_interval: (config_upstream).get_keep_alive_interval(),
-                keep_alive_timeout: (config_upstream).get_keep_alive_timeout(),
-                keep_alive_while_idle: (config_upstream).get_keep_alive_while_idle(),
-                proxy,
-                connect_timeout: (config_upstream).get_connect_timeout(),
-                timeout: (config_upstream).get_timeout(),
-                tcp_keep_alive: (config_upstream).get_tcp_keep_alive(),
-                user_agent: (config_upstream).get_user_agent(),
-                allowed_headers: (config_upstream).get_allowed_headers(),
-                base_url,
-                http_cache: (config_upstream).get_enable_http_cache(),
-                batch,
-                http2_only: (config_upstream).get_http_2_only(),
-            })
+            .fuse(get_concurrency_limits(&config_upstream))
+            .fuse(
+                get_propagate_headers(&config_upstream.propagate_headers)
+                    .trace("propagateHeaders")
+                    .trace("@upstream")
+                    .trace("schema"),
+            )
+            .map(
+                |(batch, base_url, proxy, concurrency_limits, propagate_headers)| Upstream {
+                    pool_idle_timeout: (config_upstream).get_pool_idle_timeout(),
+                    pool_max_idle_per_host: (config_upstream).get_pool_max_idle_per_host(),
+                    keep_alive_interval: (config_upstream).get_keep_alive_interval(),
+                    keep_alive_timeout: (config_upstream).get_keep_alive_timeout(),
+                    keep_alive_while_idle: (config_upstream).get_keep_alive_while_idle(),
+                    proxy,
+                    connect_timeout: (config_upstream).get_connect_timeout(),
+                    timeout: (config_upstream).get_timeout(),
+                    tcp_keep_alive: (config_upstream).get_tcp_keep_alive(),
+                    user_agent: (config_upstream).get_user_agent(),
+                    allowed_headers: (config_upstream).get_allowed_headers(),
+                    base_url,
+                    http_cache: (config_upstream).get_enable_http_cache(),
+                    batch,
+                    http2_only: (config_upstream).get_http_2_only(),
+                    max_concurrent_requests: (config_upstream).max_concurrent_requests,
+                    concurrency_limits,
+                    concurrency_queue_timeout: (config_upstream).concurrency_queue_timeout,
+                    propagate_headers,
+                },
+            )
             .to_result()
     }
 }
@@ -103,3 +120,60 @@ fn get_proxy(upstream: &config::Upstream) -> Valid<Option<Proxy>, String> {
         Valid::succeed(None)
     }
 }
+
+fn get_concurrency_limits(upstream: &config::Upstream) -> Valid<BTreeMap<String, usize>, String> {
+    let max_concurrent_requests =
+        Valid::<(), String>::fail("maxConcurrentRequests should be greater than 0".to_string())
+            .trace("maxConcurrentRequests")
+            .when(|| upstream.max_concurrent_requests == Some(0));
+
+    let limits = Valid::from_iter(upstream.get_concurrency_limits(), |limit| {
+        if limit.max_concurrent_requests == 0 {
+            Valid::fail("maxConcurrentRequests should be greater than 0".to_string())
+                .trace(&limit.host)
+        } else {
+            Valid::succeed((limit.host, limit.max_concurrent_requests))
+        }
+    })
+    .trace("concurrencyLimits")
+    .map(BTreeMap::from_iter);
+
+    max_concurrent_requests
+        .and(limits)
+        .trace("@upstream")
+        .trace("schema")
+}
+
+/// The headers describing the connection to the upstream or the framing of its
+/// body, which don't apply to the response sent to the client.
+const UNPROPAGATED_HEADERS: &[&str] = &[
+    "connection",
+    "content-encoding",
+    "content-length",
+    "content-type",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Validates the header names, which are lowercased so they can be compared
+/// with the names of a [hyper::HeaderMap].
+pub fn get_propagate_headers(
+    propagate_headers: &[PropagateHeader],
+) -> Valid<Vec<PropagateHeader>, String> {
+    Valid::from_iter(propagate_headers.iter(), |header| {
+        let name = header.name.to_lowercase();
+        if UNPROPAGATED_HEADERS.contains(&name.as_str()) {
+            return Valid::fail(format!("{} can't be propagated", header.name));
+        }
+        match hyper::header::HeaderName::from_bytes(name.as_bytes()) {
+            Ok(_) => Valid::succeed(PropagateHeader { name, merge: header.merge.clone() }),
+            Err(_) => Valid::fail(format!("{} is not a valid header name", header.name)),
+        }
+    })
+}
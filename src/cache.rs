@@ -1,42 +1,329 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::num::NonZeroU64;
-use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use ttl_cache::TtlCache;
+use async_graphql_value::ConstValue;
 
-pub struct InMemoryCache<K: Hash + Eq, V> {
-    data: Arc<RwLock<TtlCache<K, V>>>,
+use crate::config::{EvictionPolicy, ServerCache};
+use crate::lambda::CachedValue;
+use crate::CacheEntry;
+
+/// Approximate number of bytes a value keeps alive, used to bound an
+/// [InMemoryCache] by `maxBytes`.
+pub trait EstimateSize {
+    fn estimate_size(&self) -> usize;
+}
+
+impl EstimateSize for String {
+    fn estimate_size(&self) -> usize {
+        std::mem::size_of::<String>() + self.len()
+    }
+}
+
+impl EstimateSize for ConstValue {
+    fn estimate_size(&self) -> usize {
+        let heap = match self {
+            ConstValue::String(value) => value.len(),
+            ConstValue::Binary(value) => value.len(),
+            ConstValue::Enum(name) => name.len(),
+            ConstValue::List(list) => list.iter().map(EstimateSize::estimate_size).sum(),
+            ConstValue::Object(object) => object
+                .iter()
+                .map(|(name, value)| name.len() + value.estimate_size())
+                .sum(),
+            ConstValue::Null | ConstValue::Number(_) | ConstValue::Boolean(_) => 0,
+        };
+        std::mem::size_of::<ConstValue>() + heap
+    }
+}
+
+impl EstimateSize for CachedValue {
+    fn estimate_size(&self) -> usize {
+        match self {
+            CachedValue::Value(value) => value.estimate_size(),
+            CachedValue::Null => std::mem::size_of::<CachedValue>(),
+            CachedValue::Error(error) => {
+                std::mem::size_of::<CachedValue>()
+                    + error.url.len()
+                    + error.body.len()
+                    + error.code.as_ref().map_or(0, String::len)
+            }
+        }
+    }
 }
 
-// TODO: take this from the user instead of hardcoding it
-const CACHE_CAPACITY: usize = 100000;
+/// Counters describing how effective an [InMemoryCache] is.
+#[derive(Default, Debug)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    entries: AtomicUsize,
+    bytes: AtomicUsize,
+}
 
-impl<K: Hash + Eq, V: Clone> Default for InMemoryCache<K, V> {
+/// A point in time copy of [CacheStats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheSnapshot {
+    /// Total number of lookups that found a live entry
+    pub hits: u64,
+    /// Total number of lookups that found no entry, or an expired one
+    pub misses: u64,
+    /// Total number of entries evicted to stay within `maxEntries` and
+    /// `maxBytes`
+    pub evictions: u64,
+    /// Entries currently held
+    pub entries: usize,
+    /// Approximate number of bytes currently held
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    pub fn snapshot(&self) -> CacheSnapshot {
+        CacheSnapshot {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Position of an entry in the eviction order, entries with the lowest rank
+/// are evicted first. The tick is unique, so it also identifies the entry.
+type Rank = (u64, u64);
+
+struct Entry<V> {
+    value: V,
+    stored_at: Instant,
+    expires_at: Instant,
+    tags: HashSet<String>,
+    size: usize,
+    hits: u64,
+    rank: Rank,
+}
+
+struct Store<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    by_rank: BTreeMap<Rank, K>,
+    by_expiry: BTreeMap<(Instant, u64), K>,
+    by_tag: HashMap<String, HashSet<K>>,
+    bytes: usize,
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Store<K, V> {
+    fn next_rank(&mut self, policy: EvictionPolicy, hits: u64) -> Rank {
+        self.tick += 1;
+        match policy {
+            EvictionPolicy::Lru => (0, self.tick),
+            EvictionPolicy::Lfu => (hits, self.tick),
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Option<Entry<V>> {
+        let entry = self.entries.remove(key)?;
+        self.by_rank.remove(&entry.rank);
+        self.by_expiry.remove(&(entry.expires_at, entry.rank.1));
+        for tag in entry.tags.iter() {
+            if let Some(keys) = self.by_tag.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.by_tag.remove(tag);
+                }
+            }
+        }
+        self.bytes -= entry.size;
+        Some(entry)
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        while let Some(entry) = self.by_expiry.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let key = entry.remove();
+            self.remove(&key);
+        }
+    }
+}
+
+/// An in-memory cache bounded by number of entries and, optionally, by the
+/// approximate size of its values. Expired entries are dropped first, then
+/// entries are evicted following the configured [EvictionPolicy].
+pub struct InMemoryCache<K: Hash + Eq, V> {
+    data: Arc<Mutex<Store<K, V>>>,
+    max_entries: usize,
+    max_bytes: Option<usize>,
+    policy: EvictionPolicy,
+    stats: Arc<CacheStats>,
+}
+
+impl<K: Hash + Eq, V> Default for InMemoryCache<K, V> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<K: Hash + Eq, V: Clone> InMemoryCache<K, V> {
+impl<K: Hash + Eq, V> InMemoryCache<K, V> {
     pub fn new() -> Self {
-        InMemoryCache { data: Arc::new(RwLock::new(TtlCache::new(CACHE_CAPACITY))) }
+        Self::init(&ServerCache::default())
+    }
+
+    pub fn init(config: &ServerCache) -> Self {
+        let store = Store {
+            entries: HashMap::new(),
+            by_rank: BTreeMap::new(),
+            by_expiry: BTreeMap::new(),
+            by_tag: HashMap::new(),
+            bytes: 0,
+            tick: 0,
+        };
+        InMemoryCache {
+            data: Arc::new(Mutex::new(store)),
+            max_entries: config.get_max_entries(),
+            max_bytes: config.max_bytes.map(|max_bytes| max_bytes.get()),
+            policy: config.get_eviction_policy(),
+            stats: Arc::new(CacheStats::default()),
+        }
+    }
+
+    fn update_stats(&self, store: &Store<K, V>) {
+        self.stats
+            .entries
+            .store(store.entries.len(), Ordering::Relaxed);
+        self.stats.bytes.store(store.bytes, Ordering::Relaxed);
+    }
+
+    fn is_full(&self, store: &Store<K, V>, size: usize) -> bool {
+        store.entries.len() >= self.max_entries
+            || self
+                .max_bytes
+                .is_some_and(|max_bytes| store.bytes + size > max_bytes)
     }
 }
 
 #[async_trait::async_trait]
-impl<K: Hash + Eq + Send + Sync, V: Clone + Send + Sync> crate::Cache for InMemoryCache<K, V> {
+impl<K: Hash + Eq + Clone + Send + Sync, V: EstimateSize + Clone + Send + Sync> crate::Cache
+    for InMemoryCache<K, V>
+{
     type Key = K;
     type Value = V;
     #[allow(clippy::too_many_arguments)]
     async fn set<'a>(&'a self, key: K, value: V, ttl: NonZeroU64) -> anyhow::Result<()> {
-        let ttl = Duration::from_millis(ttl.get());
-        self.data.write().unwrap().insert(key, value, ttl);
+        let size = std::mem::size_of::<K>() + value.estimate_size();
+        if self.max_bytes.is_some_and(|max_bytes| size > max_bytes) {
+            // an entry larger than the whole cache would evict everything
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut store = self.data.lock().unwrap();
+        store.remove(&key);
+        store.remove_expired(now);
+        while self.is_full(&store, size) {
+            let Some((_, victim)) = store.by_rank.pop_first() else {
+                break;
+            };
+            store.remove(&victim);
+            self.stats.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let rank = store.next_rank(self.policy, 0);
+        let expires_at = now + Duration::from_millis(ttl.get());
+        let entry = Entry {
+            value,
+            stored_at: now,
+            expires_at,
+            tags: HashSet::new(),
+            size,
+            hits: 0,
+            rank,
+        };
+        store.by_rank.insert(rank, key.clone());
+        store.by_expiry.insert((expires_at, rank.1), key.clone());
+        store.entries.insert(key, entry);
+        store.bytes += size;
+        self.update_stats(&store);
+        Ok(())
+    }
+
+    async fn get_entry<'a>(&'a self, key: &'a K) -> anyhow::Result<Option<CacheEntry<V>>> {
+        let now = Instant::now();
+        let mut store = self.data.lock().unwrap();
+        let hits = match store.entries.get(key) {
+            Some(entry) if entry.expires_at > now => entry.hits + 1,
+            Some(_) => {
+                store.remove(key);
+                self.update_stats(&store);
+                self.stats.misses.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+            None => {
+                self.stats.misses.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+        };
+
+        let rank = store.next_rank(self.policy, hits);
+        let store = &mut *store;
+        let Some(entry) = store.entries.get_mut(key) else {
+            return Ok(None);
+        };
+        store.by_rank.remove(&entry.rank);
+        store.by_expiry.remove(&(entry.expires_at, entry.rank.1));
+        entry.hits = hits;
+        entry.rank = rank;
+        store.by_rank.insert(rank, key.clone());
+        store
+            .by_expiry
+            .insert((entry.expires_at, rank.1), key.clone());
+        self.stats.hits.fetch_add(1, Ordering::Relaxed);
+
+        Ok(Some(CacheEntry {
+            value: entry.value.clone(),
+            age: entry.stored_at.elapsed(),
+        }))
+    }
+
+    /// The tags of an entry are dropped along with it, so `ttl` isn't needed.
+    async fn tag<'a>(&'a self, key: &'a K, tags: Vec<String>, _: NonZeroU64) -> anyhow::Result<()> {
+        let mut store = self.data.lock().unwrap();
+        let store = &mut *store;
+        if let Some(entry) = store.entries.get_mut(key) {
+            for tag in tags {
+                store
+                    .by_tag
+                    .entry(tag.clone())
+                    .or_default()
+                    .insert(key.clone());
+                entry.tags.insert(tag);
+            }
+        }
+        Ok(())
+    }
+
+    async fn invalidate<'a>(&'a self, tags: &'a [String]) -> anyhow::Result<()> {
+        let mut store = self.data.lock().unwrap();
+        let keys: HashSet<K> = tags
+            .iter()
+            .filter_map(|tag| store.by_tag.get(tag))
+            .flatten()
+            .cloned()
+            .collect();
+        for key in keys {
+            store.remove(&key);
+        }
+        self.update_stats(&store);
         Ok(())
     }
 
-    async fn get<'a>(&'a self, key: &'a K) -> anyhow::Result<Option<Self::Value>> {
-        Ok(self.data.read().unwrap().get(key).cloned())
+    fn stats(&self) -> Option<CacheSnapshot> {
+        Some(self.stats.snapshot())
     }
 }
 
@@ -45,6 +332,7 @@ mod tests {
     use std::num::NonZeroU64;
     use std::time::Duration;
 
+    use crate::cache::EstimateSize;
     use crate::Cache;
 
     #[tokio::test]
@@ -61,4 +349,159 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(ttl.get())).await;
         assert_eq!(cache.get(&10).await.ok(), Some(None));
     }
+
+    #[tokio::test]
+    async fn test_entry_age() {
+        let cache: crate::cache::InMemoryCache<u64, String> =
+            crate::cache::InMemoryCache::default();
+        cache
+            .set(10, "hello".into(), NonZeroU64::new(1000).unwrap())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let entry = cache.get_entry(&10).await.unwrap().unwrap();
+        assert_eq!(entry.value, "hello");
+        assert!(entry.age >= Duration::from_millis(20));
+        assert!(entry.age < Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_tags() {
+        let cache: crate::cache::InMemoryCache<u64, String> =
+            crate::cache::InMemoryCache::default();
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, "user 1".into(), ttl).await.unwrap();
+        cache.set(2, "user 2".into(), ttl).await.unwrap();
+        cache.set(3, "users".into(), ttl).await.unwrap();
+        cache.tag(&1, vec!["user:1".into()], ttl).await.unwrap();
+        cache.tag(&2, vec!["user:2".into()], ttl).await.unwrap();
+        cache
+            .tag(&3, vec!["user:1".into(), "user:2".into()], ttl)
+            .await
+            .unwrap();
+
+        cache.invalidate(&["user:1".into()]).await.unwrap();
+
+        assert_eq!(cache.get(&1).await.unwrap(), None);
+        assert_eq!(cache.get(&2).await.unwrap(), Some("user 2".into()));
+        assert_eq!(cache.get(&3).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_tags_follow_entries() {
+        let cache: crate::cache::InMemoryCache<u64, String> =
+            crate::cache::InMemoryCache::default();
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, "user 1".into(), ttl).await.unwrap();
+        cache.tag(&1, vec!["user:1".into()], ttl).await.unwrap();
+        cache.set(1, "user 1 again".into(), ttl).await.unwrap();
+
+        assert!(cache.data.lock().unwrap().by_tag.is_empty());
+        cache.invalidate(&["user:1".into()]).await.unwrap();
+        assert_eq!(cache.get(&1).await.unwrap(), Some("user 1 again".into()));
+    }
+
+    fn limited(
+        max_entries: usize,
+        max_bytes: Option<usize>,
+        eviction_policy: crate::config::EvictionPolicy,
+    ) -> crate::cache::InMemoryCache<u64, String> {
+        crate::cache::InMemoryCache::init(&crate::config::ServerCache {
+            max_entries: std::num::NonZeroUsize::new(max_entries),
+            max_bytes: max_bytes.and_then(std::num::NonZeroUsize::new),
+            eviction_policy: Some(eviction_policy),
+            redis: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        let cache = limited(2, None, crate::config::EvictionPolicy::Lru);
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, "a".into(), ttl).await.unwrap();
+        cache.set(2, "b".into(), ttl).await.unwrap();
+        cache.get(&1).await.unwrap();
+        cache.set(3, "c".into(), ttl).await.unwrap();
+
+        assert_eq!(cache.get(&1).await.unwrap(), Some("a".into()));
+        assert_eq!(cache.get(&2).await.unwrap(), None);
+        assert_eq!(cache.get(&3).await.unwrap(), Some("c".into()));
+    }
+
+    #[tokio::test]
+    async fn test_lfu_eviction() {
+        let cache = limited(2, None, crate::config::EvictionPolicy::Lfu);
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, "a".into(), ttl).await.unwrap();
+        cache.set(2, "b".into(), ttl).await.unwrap();
+        cache.get(&1).await.unwrap();
+        cache.get(&1).await.unwrap();
+        cache.get(&2).await.unwrap();
+        cache.set(3, "c".into(), ttl).await.unwrap();
+        cache.get(&3).await.unwrap();
+        cache.set(4, "d".into(), ttl).await.unwrap();
+
+        assert_eq!(cache.get(&1).await.unwrap(), Some("a".into()));
+        assert_eq!(cache.get(&2).await.unwrap(), None);
+        assert_eq!(cache.get(&3).await.unwrap(), None);
+        assert_eq!(cache.get(&4).await.unwrap(), Some("d".into()));
+    }
+
+    #[tokio::test]
+    async fn test_max_bytes() {
+        let entry_size = 8 + "0123456789".to_string().estimate_size();
+        let cache = limited(
+            100,
+            Some(entry_size * 2),
+            crate::config::EvictionPolicy::Lru,
+        );
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, "0123456789".into(), ttl).await.unwrap();
+        cache.set(2, "0123456789".into(), ttl).await.unwrap();
+        cache.set(3, "0123456789".into(), ttl).await.unwrap();
+        cache.set(4, "0".repeat(entry_size * 2), ttl).await.unwrap();
+
+        assert_eq!(cache.get(&1).await.unwrap(), None);
+        assert_eq!(cache.get(&4).await.unwrap(), None);
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.bytes, entry_size * 2);
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let cache = limited(1, None, crate::config::EvictionPolicy::Lru);
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, "a".into(), ttl).await.unwrap();
+        cache.get(&1).await.unwrap();
+        cache.get(&2).await.unwrap();
+        cache.set(2, "b".into(), ttl).await.unwrap();
+        cache.set(2, "c".into(), ttl).await.unwrap();
+
+        assert_eq!(
+            cache.stats().unwrap(),
+            crate::cache::CacheSnapshot {
+                hits: 1,
+                misses: 1,
+                evictions: 1,
+                entries: 1,
+                bytes: 8 + "c".to_string().estimate_size()
+            }
+        );
+    }
+
+    #[test]
+    fn test_const_value_size() {
+        let value = async_graphql_value::ConstValue::from_json(
+            serde_json::json!({"name": "Leanne", "ids": [1, 2]}),
+        )
+        .unwrap();
+        let size = std::mem::size_of::<async_graphql_value::ConstValue>();
+
+        assert_eq!(
+            value.estimate_size(),
+            size * 5 + "name".len() + "Leanne".len() + "ids".len()
+        );
+    }
 }
@@ -27,6 +27,14 @@ pub enum Command {
         /// Path for the configuration files or http(s) link to config files separated by spaces if more than one
         #[arg(required = true)]
         file_paths: Vec<String>,
+
+        /// Directory to record every upstream request and response into
+        #[arg(long)]
+        record: Option<String>,
+
+        /// Directory to replay upstream responses from, instead of calling the upstreams
+        #[arg(long, conflicts_with = "record")]
+        replay: Option<String>,
     },
 
     /// Validate a composition spec
@@ -65,4 +73,27 @@ pub enum Command {
         #[arg(default_value = ".")]
         folder_path: String,
     },
+
+    /// Generate a configuration from another kind of definition
+    Gen {
+        #[command(subcommand)]
+        command: GenCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GenCommand {
+    /// Generate the types and the @grpc fields of the services of a proto file
+    Proto {
+        /// Path of the proto file
+        file_path: String,
+
+        /// Base URL of the gRPC server
+        #[arg(long, default_value = "http://localhost:50051")]
+        base_url: String,
+
+        /// Format of the result. Accepted values: JSON|YML|GQL.
+        #[clap(short, long, default_value = "gql")]
+        format: Source,
+    },
 }
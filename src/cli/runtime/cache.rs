@@ -0,0 +1,526 @@
+use std::future::Future;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+
+use crate::cache::InMemoryCache;
+use crate::config::{self, CacheFormat};
+use crate::lambda::CachedValue;
+use crate::{Cache, CacheEntry};
+
+/// How long to wait for Redis before falling back to memory.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long to keep using memory before trying to reconnect.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// Adds the entry `ARGV[1]` to the tag sets `KEYS`, which expire with the
+/// entry that lives the longest. Unlike the conditions of `PEXPIRE`, this
+/// works before Redis 7.
+const TAG_SCRIPT: &str = r#"
+for _, key in ipairs(KEYS) do
+  redis.call('SADD', key, ARGV[1])
+  if redis.call('PTTL', key) < tonumber(ARGV[2]) then
+    redis.call('PEXPIRE', key, ARGV[2])
+  end
+end
+"#;
+
+/// Serializes the values stored in a [RedisCache].
+pub trait ValueCodec: Send + Sync {
+    fn encode(&self, value: &CachedValue) -> Result<Vec<u8>>;
+    fn decode(&self, bytes: &[u8]) -> Result<CachedValue>;
+}
+
+pub struct JsonCodec;
+
+impl ValueCodec for JsonCodec {
+    fn encode(&self, value: &CachedValue) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CachedValue> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+pub struct MessagePackCodec;
+
+impl ValueCodec for MessagePackCodec {
+    fn encode(&self, value: &CachedValue) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec(value)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CachedValue> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+impl From<CacheFormat> for Arc<dyn ValueCodec> {
+    fn from(format: CacheFormat) -> Self {
+        match format {
+            CacheFormat::Json => Arc::new(JsonCodec),
+            CacheFormat::MessagePack => Arc::new(MessagePackCodec),
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn is_connection_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<redis::RedisError>()
+        .is_some_and(|error| {
+            error.is_io_error()
+                || error.is_connection_dropped()
+                || error.is_connection_refusal()
+                || error.is_timeout()
+        })
+}
+
+fn entry_key(key: u64) -> String {
+    format!("tailcall:entry:{}", key)
+}
+
+fn tag_key(tag: &str) -> String {
+    format!("tailcall:tag:{}", tag)
+}
+
+/// A cache stored on a Redis server and shared by every replica. Entries are
+/// the time they were stored, as milliseconds since the epoch in 8 big endian
+/// bytes, followed by the encoded value. Tags are sets of entry keys.
+///
+/// While the server can't be reached, entries are read from and written into
+/// an [InMemoryCache] instead.
+pub struct RedisCache {
+    client: redis::Client,
+    connection: Mutex<Option<MultiplexedConnection>>,
+    retry_at: Mutex<Option<Instant>>,
+    codec: Arc<dyn ValueCodec>,
+    fallback: InMemoryCache<u64, CachedValue>,
+}
+
+impl RedisCache {
+    pub fn init(cache: &config::ServerCache, redis: &config::Redis) -> Result<Self> {
+        Ok(Self::new(
+            redis::Client::open(redis.url.as_str())?,
+            redis.format.into(),
+            InMemoryCache::init(cache),
+        ))
+    }
+
+    pub fn new(
+        client: redis::Client,
+        codec: Arc<dyn ValueCodec>,
+        fallback: InMemoryCache<u64, CachedValue>,
+    ) -> Self {
+        Self {
+            client,
+            connection: Mutex::new(None),
+            retry_at: Mutex::new(None),
+            codec,
+            fallback,
+        }
+    }
+
+    async fn connection(&self) -> Option<MultiplexedConnection> {
+        if let Some(connection) = self.connection.lock().unwrap().clone() {
+            return Some(connection);
+        }
+        if self
+            .retry_at
+            .lock()
+            .unwrap()
+            .is_some_and(|retry_at| Instant::now() < retry_at)
+        {
+            return None;
+        }
+
+        let connection = tokio::time::timeout(
+            COMMAND_TIMEOUT,
+            self.client.get_multiplexed_tokio_connection(),
+        )
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|connection| Ok(connection?));
+
+        match connection {
+            Ok(connection) => {
+                *self.connection.lock().unwrap() = Some(connection.clone());
+                Some(connection)
+            }
+            Err(error) => {
+                self.disconnect(error);
+                None
+            }
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CacheEntry<CachedValue>> {
+        if bytes.len() < 8 {
+            return Err(anyhow!("missing the time it was stored"));
+        }
+
+        let (stored_at, value) = bytes.split_at(8);
+        let stored_at = u64::from_be_bytes(stored_at.try_into()?);
+        Ok(CacheEntry {
+            value: self.codec.decode(value)?,
+            age: Duration::from_millis(now_millis().saturating_sub(stored_at)),
+        })
+    }
+
+    fn disconnect(&self, error: anyhow::Error) {
+        log::warn!("Redis cache unavailable, using memory instead: {}", error);
+        *self.connection.lock().unwrap() = None;
+        *self.retry_at.lock().unwrap() = Some(Instant::now() + RETRY_INTERVAL);
+    }
+
+    /// Runs `command` on Redis, returning `None` when it can't be reached or
+    /// fails. Only connection errors make the following commands use memory.
+    async fn execute<T, F, Fut>(&self, command: F) -> Option<T>
+    where
+        F: FnOnce(MultiplexedConnection) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let connection = self.connection().await?;
+        match tokio::time::timeout(COMMAND_TIMEOUT, command(connection)).await {
+            Ok(Ok(result)) => Some(result),
+            Ok(Err(error)) if is_connection_error(&error) => {
+                self.disconnect(error);
+                None
+            }
+            Ok(Err(error)) => {
+                log::error!("Redis cache command failed: {}", error);
+                None
+            }
+            Err(error) => {
+                self.disconnect(error.into());
+                None
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Cache for RedisCache {
+    type Key = u64;
+    type Value = CachedValue;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn set<'a>(&'a self, key: u64, value: CachedValue, ttl: NonZeroU64) -> Result<()> {
+        let mut bytes = now_millis().to_be_bytes().to_vec();
+        bytes.extend(self.codec.encode(&value)?);
+
+        let stored = self
+            .execute(|mut connection| async move {
+                Ok(connection.pset_ex(entry_key(key), bytes, ttl.get()).await?)
+            })
+            .await;
+
+        match stored {
+            Some(()) => Ok(()),
+            None => self.fallback.set(key, value, ttl).await,
+        }
+    }
+
+    async fn get_entry<'a>(&'a self, key: &'a u64) -> Result<Option<CacheEntry<CachedValue>>> {
+        let bytes = self
+            .execute(|mut connection| async move {
+                let bytes: Option<Vec<u8>> = connection.get(entry_key(*key)).await?;
+                Ok(bytes)
+            })
+            .await;
+
+        let Some(bytes) = bytes else {
+            return self.fallback.get_entry(key).await;
+        };
+        let Some(bytes) = bytes else {
+            return Ok(None);
+        };
+
+        // entries written in another format or truncated are misses
+        match self.decode(&bytes) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(error) => {
+                log::warn!("Ignoring malformed cache entry for key {}: {}", key, error);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn tag<'a>(&'a self, key: &'a u64, tags: Vec<String>, ttl: NonZeroU64) -> Result<()> {
+        let fallback_tags = tags.clone();
+        let tagged = self
+            .execute(|mut connection| async move {
+                redis::cmd("EVAL")
+                    .arg(TAG_SCRIPT)
+                    .arg(tags.len())
+                    .arg(tags.iter().map(|tag| tag_key(tag)).collect::<Vec<_>>())
+                    .arg(entry_key(*key))
+                    .arg(ttl.get())
+                    .query_async::<_, ()>(&mut connection)
+                    .await?;
+                Ok(())
+            })
+            .await;
+
+        match tagged {
+            Some(()) => Ok(()),
+            None => self.fallback.tag(key, fallback_tags, ttl).await,
+        }
+    }
+
+    async fn invalidate<'a>(&'a self, tags: &'a [String]) -> Result<()> {
+        // entries written while Redis was unavailable are still in memory
+        self.fallback.invalidate(tags).await?;
+        self.execute(|mut connection| async move {
+            for tag in tags {
+                let mut keys: Vec<String> = connection.smembers(tag_key(tag)).await?;
+                keys.push(tag_key(tag));
+                connection.del::<_, ()>(keys).await?;
+            }
+            Ok(())
+        })
+        .await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use async_graphql_value::ConstValue;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Data {
+        values: HashMap<Vec<u8>, Vec<u8>>,
+        sets: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+        expiries: HashMap<Vec<u8>, u64>,
+    }
+
+    fn bulk(bytes: &[u8]) -> Vec<u8> {
+        let mut reply = format!("${}\r\n", bytes.len()).into_bytes();
+        reply.extend(bytes);
+        reply.extend(b"\r\n");
+        reply
+    }
+
+    fn reply(data: &Mutex<Data>, command: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut data = data.lock().unwrap();
+        let name = String::from_utf8_lossy(&command[0]).to_uppercase();
+        match (name.as_str(), &command[1..]) {
+            ("PSETEX", [key, _, value]) => {
+                data.values.insert(key.clone(), value.clone());
+                b"+OK\r\n".to_vec()
+            }
+            ("GET", [key]) => match data.values.get(key) {
+                Some(value) => bulk(value),
+                None => b"$-1\r\n".to_vec(),
+            },
+            ("SADD", [key, member]) => {
+                let set = data.sets.entry(key.clone()).or_default();
+                format!(":{}\r\n", set.insert(member.clone()) as u8).into_bytes()
+            }
+            // runs TAG_SCRIPT, the only script sent by the cache
+            ("EVAL", [_, count, args @ ..]) => {
+                let count: usize = String::from_utf8_lossy(count).parse().unwrap();
+                let (keys, [member, ttl]) = args.split_at(count) else {
+                    return b"-ERR wrong number of arguments\r\n".to_vec();
+                };
+                let ttl: u64 = String::from_utf8_lossy(ttl).parse().unwrap();
+                for key in keys {
+                    data.sets
+                        .entry(key.clone())
+                        .or_default()
+                        .insert(member.clone());
+                    let expiry = data.expiries.entry(key.clone()).or_default();
+                    *expiry = ttl.max(*expiry);
+                }
+                b"$-1\r\n".to_vec()
+            }
+            ("PTTL", [key]) => {
+                let ttl = data.expiries.get(key).map_or(-1, |ttl| *ttl as i64);
+                format!(":{}\r\n", ttl).into_bytes()
+            }
+            ("SMEMBERS", [key]) => {
+                let members = data.sets.get(key).cloned().unwrap_or_default();
+                let mut reply = format!("*{}\r\n", members.len()).into_bytes();
+                members.iter().for_each(|member| reply.extend(bulk(member)));
+                reply
+            }
+            ("DEL", keys) => {
+                let deleted = keys
+                    .iter()
+                    .filter(|key| {
+                        data.values.remove(*key).is_some() || data.sets.remove(*key).is_some()
+                    })
+                    .count();
+                format!(":{}\r\n", deleted).into_bytes()
+            }
+            _ => b"-ERR unknown command\r\n".to_vec(),
+        }
+    }
+
+    async fn read_line(reader: &mut BufReader<TcpStream>) -> Option<String> {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        (!line.is_empty()).then(|| line.trim_end().to_string())
+    }
+
+    async fn serve(stream: TcpStream, data: Arc<Mutex<Data>>) -> Option<()> {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let count: usize = read_line(&mut reader)
+                .await?
+                .strip_prefix('*')?
+                .parse()
+                .ok()?;
+            let mut command = Vec::with_capacity(count);
+            for _ in 0..count {
+                let len: usize = read_line(&mut reader)
+                    .await?
+                    .strip_prefix('$')?
+                    .parse()
+                    .ok()?;
+                let mut arg = vec![0; len + 2];
+                reader.read_exact(&mut arg).await.ok()?;
+                arg.truncate(len);
+                command.push(arg);
+            }
+            let reply = reply(&data, command);
+            reader.get_mut().write_all(&reply).await.ok()?;
+        }
+    }
+
+    /// Starts a stand-in for redis-server that understands the commands used
+    /// by [RedisCache], and returns its url.
+    async fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let data = Arc::new(Mutex::new(Data::default()));
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, data.clone()));
+            }
+        });
+        url
+    }
+
+    fn redis_cache(url: &str, format: CacheFormat) -> RedisCache {
+        let redis = config::Redis { url: url.to_string(), format };
+        RedisCache::init(&config::ServerCache::default(), &redis).unwrap()
+    }
+
+    fn value() -> CachedValue {
+        let value = serde_json::json!({"id": 1, "name": "Leanne", "tags": ["a"]});
+        CachedValue::Value(ConstValue::from_json(value).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_shared_between_replicas() {
+        let url = start_server().await;
+        let ttl = NonZeroU64::new(1000).unwrap();
+        for format in [CacheFormat::Json, CacheFormat::MessagePack] {
+            let replica = redis_cache(&url, format);
+            let other_replica = redis_cache(&url, format);
+            replica.set(1, value(), ttl).await.unwrap();
+
+            let entry = other_replica.get_entry(&1).await.unwrap().unwrap();
+            assert_eq!(entry.value, value());
+            assert!(entry.age < Duration::from_millis(1000));
+            assert_eq!(other_replica.get(&2).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let cache = redis_cache(&start_server().await, CacheFormat::Json);
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, value(), ttl).await.unwrap();
+        cache.set(2, value(), ttl).await.unwrap();
+        cache.tag(&1, vec!["user:1".into()], ttl).await.unwrap();
+        cache.tag(&2, vec!["user:2".into()], ttl).await.unwrap();
+
+        cache.invalidate(&["user:1".into()]).await.unwrap();
+
+        assert_eq!(cache.get(&1).await.unwrap(), None);
+        assert_eq!(cache.get(&2).await.unwrap(), Some(value()));
+    }
+
+    #[tokio::test]
+    async fn test_tags_expire_with_the_longest_entry() {
+        let url = start_server().await;
+        let cache = redis_cache(&url, CacheFormat::Json);
+        for (key, ttl) in [(1, 1000), (2, 5000), (3, 2000)] {
+            let ttl = NonZeroU64::new(ttl).unwrap();
+            cache.set(key, value(), ttl).await.unwrap();
+            cache.tag(&key, vec!["users".into()], ttl).await.unwrap();
+        }
+
+        let mut connection = cache.connection().await.unwrap();
+        let ttl: i64 = connection.pttl(tag_key("users")).await.unwrap();
+        assert_eq!(ttl, 5000);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_entries_are_misses() {
+        let url = start_server().await;
+        let json = redis_cache(&url, CacheFormat::Json);
+        let message_pack = redis_cache(&url, CacheFormat::MessagePack);
+        let ttl = NonZeroU64::new(1000).unwrap();
+        json.set(1, value(), ttl).await.unwrap();
+        let mut connection = json.connection().await.unwrap();
+        connection
+            .pset_ex::<_, _, ()>(entry_key(2), b"abc".as_slice(), 1000)
+            .await
+            .unwrap();
+
+        assert_eq!(message_pack.get(&1).await.unwrap(), None);
+        assert_eq!(json.get(&2).await.unwrap(), None);
+        assert_eq!(json.get(&1).await.unwrap(), Some(value()));
+    }
+
+    #[tokio::test]
+    async fn test_error_replies_keep_the_connection() {
+        let cache = redis_cache(&start_server().await, CacheFormat::Json);
+        let result = cache
+            .execute(|mut connection| async move {
+                Ok(redis::cmd("UNKNOWN")
+                    .query_async::<_, ()>(&mut connection)
+                    .await?)
+            })
+            .await;
+
+        assert_eq!(result, None);
+        assert!(cache.connection.lock().unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fallback_to_memory() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let cache = redis_cache(&url, CacheFormat::Json);
+        let ttl = NonZeroU64::new(1000).unwrap();
+        cache.set(1, value(), ttl).await.unwrap();
+        cache.tag(&1, vec!["user:1".into()], ttl).await.unwrap();
+        assert_eq!(cache.get(&1).await.unwrap(), Some(value()));
+
+        cache.invalidate(&["user:1".into()]).await.unwrap();
+        assert_eq!(cache.get(&1).await.unwrap(), None);
+    }
+}
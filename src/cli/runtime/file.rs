@@ -22,13 +22,17 @@ impl FileIO for NativeFileIO {
     }
 
     async fn read<'a>(&'a self, path: &'a str) -> anyhow::Result<String> {
+        Ok(String::from_utf8(self.read_bytes(path).await?)?)
+    }
+
+    async fn read_bytes<'a>(&'a self, path: &'a str) -> anyhow::Result<Vec<u8>> {
         let mut file = tokio::fs::File::open(path).await?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)
             .await
             .map_err(CLIError::from)?;
         log::info!("File read: {} ... ok", path);
-        Ok(String::from_utf8(buffer)?)
+        Ok(buffer)
     }
 }
 
@@ -1,19 +1,26 @@
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use futures_channel::oneshot;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use futures_util::{future, FutureExt};
 use http_cache_reqwest::{Cache, CacheMode, HttpCache, HttpCacheOptions, MokaManager};
-use hyper::body::Bytes;
+use hyper::body::{Bytes, HttpBody};
+use hyper::client::HttpConnector;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use reqwest::header::{HeaderValue, CONTENT_TYPE, USER_AGENT};
 use reqwest::Client;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 
 use super::HttpIO;
 use crate::blueprint::Upstream;
-use crate::http::Response;
+use crate::http::{Response, StatusError, StreamResponse};
 
 #[derive(Clone)]
 pub struct NativeHttp {
     client: ClientWithMiddleware,
     http2_only: bool,
+    stream_client: Option<StreamClient>,
 }
 
 impl Default for NativeHttp {
@@ -21,10 +28,129 @@ impl Default for NativeHttp {
         Self {
             client: ClientBuilder::new(Client::new()).build(),
             http2_only: false,
+            stream_client: None,
         }
     }
 }
 
+/// Sends the requests over HTTP/2 with hyper directly, as reqwest drops the
+/// trailers that carry the status of gRPC calls. Streams are only bounded by
+/// the timeout of their request, as deadlines are enforced by gRPC services.
+#[derive(Clone)]
+struct StreamClient {
+    client: hyper::Client<HttpsConnector<HttpConnector>>,
+    user_agent: Option<HeaderValue>,
+    timeout: Duration,
+}
+
+impl StreamClient {
+    fn init(upstream: &Upstream) -> Option<Self> {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_keepalive(Some(Duration::from_secs(upstream.tcp_keep_alive)));
+        http.set_connect_timeout(Some(Duration::from_secs(upstream.connect_timeout)));
+        let https = match HttpsConnectorBuilder::new().with_native_roots() {
+            Ok(builder) => builder.https_or_http().enable_http2().wrap_connector(http),
+            Err(error) => {
+                log::warn!("Trailers of HTTP/2 streams can't be read: {}", error);
+                return None;
+            }
+        };
+
+        let client = hyper::Client::builder()
+            .http2_only(true)
+            .http2_keep_alive_interval(Some(Duration::from_secs(upstream.keep_alive_interval)))
+            .http2_keep_alive_timeout(Duration::from_secs(upstream.keep_alive_timeout))
+            .http2_keep_alive_while_idle(upstream.keep_alive_while_idle)
+            .pool_idle_timeout(Some(Duration::from_secs(upstream.pool_idle_timeout)))
+            .pool_max_idle_per_host(upstream.pool_max_idle_per_host)
+            .build(https);
+        let user_agent = HeaderValue::from_str(&upstream.user_agent).ok();
+
+        let timeout = Duration::from_secs(upstream.timeout);
+
+        Some(Self { client, user_agent, timeout })
+    }
+
+    /// Reads the whole response, its trailers being added to the headers.
+    async fn fetch(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+        let url = request.url().to_string();
+        let timeout = request.timeout().copied().unwrap_or(self.timeout);
+        let response = async {
+            let response = self.execute(request).await?;
+            let body: Vec<Bytes> = response.body.try_collect().await?;
+            let mut headers = response.headers;
+            headers.extend(response.trailers.await.unwrap_or_default());
+            Ok(Response {
+                status: response.status,
+                headers,
+                body: Bytes::from(body.concat()),
+            })
+        };
+
+        tokio::time::timeout(timeout, response)
+            .await
+            .map_err(|_| anyhow!("Request to {} timed out after {:?}", url, timeout))?
+    }
+
+    async fn execute(&self, request: reqwest::Request) -> Result<StreamResponse> {
+        let url = request.url().to_string();
+        let timeout = request.timeout().copied();
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(Bytes::copy_from_slice)
+            .unwrap_or_default();
+        let mut builder = hyper::Request::builder()
+            .method(request.method().clone())
+            .uri(url.as_str())
+            .version(reqwest::Version::HTTP_2);
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(request.headers().clone());
+            if let Some(user_agent) = &self.user_agent {
+                headers.entry(USER_AGENT).or_insert(user_agent.clone());
+            }
+        }
+
+        let response = self.client.request(builder.body(hyper::Body::from(body))?);
+        let response = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response)
+                .await
+                .map_err(|_| anyhow!("Request to {} timed out after {:?}", url, timeout))??,
+            None => response.await?,
+        };
+        let status = response.status();
+        let headers = response.headers().to_owned();
+        if status.is_client_error() || status.is_server_error() {
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            return Err(StatusError { status, url, body }.into());
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let body = stream::unfold(
+            (response.into_body(), sender),
+            |(mut body, sender)| async move {
+                match body.data().await {
+                    Some(chunk) => Some((chunk.map_err(Into::into), (body, sender))),
+                    None => {
+                        let trailers = body.trailers().await.ok().flatten();
+                        let _ = sender.send(trailers.unwrap_or_default());
+                        None
+                    }
+                }
+            },
+        );
+        let trailers = receiver.map(Result::ok);
+
+        Ok(StreamResponse {
+            status,
+            headers,
+            body: body.boxed(),
+            trailers: trailers.boxed(),
+        })
+    }
+}
+
 impl NativeHttp {
     pub fn init(upstream: &Upstream) -> Self {
         let mut builder = Client::builder()
@@ -60,7 +186,35 @@ impl NativeHttp {
                 options: HttpCacheOptions::default(),
             }))
         }
-        Self { client: client.build(), http2_only: upstream.http2_only }
+        // hyper has no support for proxies
+        let stream_client = if upstream.http2_only && upstream.proxy.is_none() {
+            StreamClient::init(upstream)
+        } else {
+            None
+        };
+
+        Self {
+            client: client.build(),
+            http2_only: upstream.http2_only,
+            stream_client,
+        }
+    }
+
+    /// The client reading the trailers of gRPC calls. The other requests, and
+    /// the ones whose body is streamed and can't be copied over to hyper, go
+    /// through reqwest and its middlewares.
+    fn stream_client(&self, request: &reqwest::Request) -> Option<&StreamClient> {
+        let is_grpc = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| {
+                value == "application/grpc" || value.starts_with("application/grpc+")
+            });
+        let buffered = request
+            .body()
+            .map_or(true, |body| body.as_bytes().is_some());
+        self.stream_client.as_ref().filter(|_| is_grpc && buffered)
     }
 }
 
@@ -77,14 +231,53 @@ impl HttpIO for NativeHttp {
             request.version()
         );
         log::debug!("request: {:?}", request);
+        if let Some(client) = self.stream_client(&request) {
+            return client.fetch(request).await;
+        }
         let response = self.client.execute(request).await;
         log::debug!("response: {:?}", response);
-        Ok(Response::from_reqwest(response?.error_for_status()?).await?)
+        Response::from_reqwest_checked(response?).await
+    }
+
+    async fn execute_stream(&self, mut request: reqwest::Request) -> Result<StreamResponse> {
+        if self.http2_only {
+            *request.version_mut() = reqwest::Version::HTTP_2;
+        }
+        log::info!(
+            "{} {} {:?} (stream)",
+            request.method(),
+            request.url(),
+            request.version()
+        );
+        if let Some(client) = self.stream_client(&request) {
+            return client.execute(request).await;
+        }
+
+        let response = self.client.execute(request).await?;
+        if response.status().is_client_error() || response.status().is_server_error() {
+            return Ok(Response::from_reqwest_checked(response).await?.into());
+        }
+
+        let status = response.status();
+        let headers = response.headers().to_owned();
+        let body = stream::try_unfold(response, |mut response| async move {
+            Ok(response.chunk().await?.map(|chunk| (chunk, response)))
+        });
+        Ok(StreamResponse {
+            status,
+            headers,
+            body: body.boxed(),
+            trailers: future::ready(None).boxed(),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::convert::Infallible;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use reqwest::header::{HeaderMap, HeaderName};
     use reqwest::Method;
     use tokio;
 
@@ -120,4 +313,53 @@ mod tests {
 
         header_serv.assert();
     }
+
+    #[tokio::test]
+    async fn test_http2_stream_trailers() {
+        let service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|_request| async {
+                let (mut sender, body) = hyper::Body::channel();
+                tokio::spawn(async move {
+                    sender.send_data(Bytes::from("message")).await.unwrap();
+                    let trailers = HeaderMap::from_iter([(
+                        HeaderName::from_static("grpc-status"),
+                        HeaderValue::from_static("5"),
+                    )]);
+                    sender.send_trailers(trailers).await.unwrap();
+                });
+                Ok::<_, Infallible>(hyper::Response::new(body))
+            }))
+        });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into())
+            .http2_only(true)
+            .serve(service);
+        let url = format!("http://{}/news", server.local_addr());
+        tokio::spawn(server);
+
+        let native_http = NativeHttp::init(&Upstream::default().http2_only(true));
+        let grpc_request = || {
+            let mut request = reqwest::Request::new(Method::POST, url.parse().unwrap());
+            request
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+            request
+        };
+        let response = native_http.execute_stream(grpc_request()).await.unwrap();
+
+        let body: Vec<_> = response.body.map(Result::unwrap).collect().await;
+        assert_eq!(body, vec![Bytes::from("message")]);
+        let trailers = response.trailers.await.unwrap();
+        assert_eq!(trailers["grpc-status"], "5");
+
+        // whole responses get their trailers as headers
+        let response = native_http.execute(grpc_request()).await.unwrap();
+        assert_eq!(response.body, Bytes::from("message"));
+        assert_eq!(response.headers["grpc-status"], "5");
+
+        // other requests go through reqwest, which drops the trailers
+        let request = reqwest::Request::new(Method::POST, url.parse().unwrap());
+        let response = native_http.execute_stream(request).await.unwrap();
+        let _: Vec<_> = response.body.collect().await;
+        assert!(response.trailers.await.is_none());
+    }
 }
@@ -1,3 +1,4 @@
+mod cache;
 mod env;
 mod file;
 mod http;
@@ -5,10 +6,14 @@ mod http;
 use std::hash::Hash;
 use std::sync::Arc;
 
+pub use cache::{JsonCodec, MessagePackCodec, RedisCache, ValueCodec};
+
 use crate::blueprint::Upstream;
 use crate::cache::InMemoryCache;
+use crate::config::ServerCache;
+use crate::http::{Bulkhead, BulkheadState, CassetteMode, Recorder, Replayer};
 use crate::runtime::TargetRuntime;
-use crate::{blueprint, EnvIO, FileIO, HttpIO};
+use crate::{blueprint, EntityCache, EnvIO, FileIO, HttpIO};
 
 // Provides access to env in native rust environment
 fn init_env() -> Arc<dyn EnvIO> {
@@ -33,26 +38,83 @@ fn init_hook_http(http: impl HttpIO, script: Option<blueprint::Script>) -> Arc<d
     Arc::new(http)
 }
 
+// Records the traffic into cassettes or replays it from them, if asked to
+fn init_cassette_http(
+    http: impl HttpIO,
+    script: Option<blueprint::Script>,
+    cassette: Option<&CassetteMode>,
+) -> Arc<dyn HttpIO> {
+    match cassette {
+        Some(CassetteMode::Record(dir)) => {
+            init_hook_http(Recorder::new(http, init_file(), dir.clone()), script)
+        }
+        Some(CassetteMode::Replay(dir)) => {
+            init_hook_http(Replayer::new(init_file(), dir.clone()), script)
+        }
+        None => init_hook_http(http, script),
+    }
+}
+
 // Provides access to http in native rust environment
-fn init_http(upstream: &Upstream, script: Option<blueprint::Script>) -> Arc<dyn HttpIO> {
+fn init_http(
+    upstream: &Upstream,
+    script: Option<blueprint::Script>,
+    cassette: Option<&CassetteMode>,
+    bulkhead: Arc<BulkheadState>,
+) -> Arc<dyn HttpIO> {
     let http_io = http::NativeHttp::init(upstream);
-    init_hook_http(http_io, script)
+    let http_io = Bulkhead::new(http_io, upstream).state(bulkhead);
+    init_cassette_http(http_io, script, cassette)
 }
 
 // Provides access to http in native rust environment
-fn init_http2_only(upstream: &Upstream, script: Option<blueprint::Script>) -> Arc<dyn HttpIO> {
+fn init_http2_only(
+    upstream: &Upstream,
+    script: Option<blueprint::Script>,
+    cassette: Option<&CassetteMode>,
+    bulkhead: Arc<BulkheadState>,
+) -> Arc<dyn HttpIO> {
     let http_io = http::NativeHttp::init(&upstream.clone().http2_only(true));
-    init_hook_http(http_io, script)
+    let http_io = Bulkhead::new(http_io, upstream).state(bulkhead);
+    init_cassette_http(http_io, script, cassette)
 }
 
-fn init_in_memory_cache<K: Hash + Eq, V: Clone>() -> InMemoryCache<K, V> {
+fn init_in_memory_cache<K: Hash + Eq, V>() -> InMemoryCache<K, V> {
     InMemoryCache::new()
 }
 
+/// Uses Redis when it's configured, and memory otherwise
+pub fn init_cache(cache: &ServerCache) -> Arc<EntityCache> {
+    let redis = cache
+        .redis
+        .as_ref()
+        .map(|redis| RedisCache::init(cache, redis));
+
+    match redis {
+        Some(Ok(redis)) => Arc::new(redis),
+        Some(Err(error)) => {
+            log::warn!("Redis cache unavailable, using memory instead: {}", error);
+            Arc::new(InMemoryCache::init(cache))
+        }
+        None => Arc::new(InMemoryCache::init(cache)),
+    }
+}
+
 pub fn init(upstream: &Upstream, script: Option<blueprint::Script>) -> TargetRuntime {
+    init_with_cassette(upstream, script, None, Arc::default())
+}
+
+/// Initializes the runtime, the limits of the upstreams being tracked in
+/// `bulkhead` for both the http and http2 clients.
+pub fn init_with_cassette(
+    upstream: &Upstream,
+    script: Option<blueprint::Script>,
+    cassette: Option<&CassetteMode>,
+    bulkhead: Arc<BulkheadState>,
+) -> TargetRuntime {
     TargetRuntime {
-        http: init_http(upstream, script.clone()),
-        http2_only: init_http2_only(upstream, script),
+        http: init_http(upstream, script.clone(), cassette, bulkhead.clone()),
+        http2_only: init_http2_only(upstream, script, cassette, bulkhead),
         env: init_env(),
         file: init_file(),
         cache: Arc::new(init_in_memory_cache()),
@@ -6,7 +6,6 @@ use tokio::sync::oneshot;
 use super::server_config::ServerConfig;
 use crate::async_graphql_hyper::{GraphQLBatchRequest, GraphQLRequest};
 use crate::cli::CLIError;
-use crate::http::handle_request;
 
 pub async fn start_http_1(
     sc: Arc<ServerConfig>,
@@ -17,7 +16,7 @@ pub async fn start_http_1(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLRequest>(req, state.app_ctx.clone())
+                super::handle::<GraphQLRequest>(req, state.clone())
             }))
         }
     });
@@ -26,7 +25,7 @@ pub async fn start_http_1(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLBatchRequest>(req, state.app_ctx.clone())
+                super::handle::<GraphQLBatchRequest>(req, state.clone())
             }))
         }
     });
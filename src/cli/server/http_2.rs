@@ -11,7 +11,6 @@ use tokio::sync::oneshot;
 use super::server_config::ServerConfig;
 use crate::async_graphql_hyper::{GraphQLBatchRequest, GraphQLRequest};
 use crate::cli::CLIError;
-use crate::http::handle_request;
 
 pub async fn start_http_2(
     sc: Arc<ServerConfig>,
@@ -29,7 +28,7 @@ pub async fn start_http_2(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLRequest>(req, state.app_ctx.clone())
+                super::handle::<GraphQLRequest>(req, state.clone())
             }))
         }
     });
@@ -38,7 +37,7 @@ pub async fn start_http_2(
         let state = Arc::clone(&sc);
         async move {
             Ok::<_, anyhow::Error>(service_fn(move |req| {
-                handle_request::<GraphQLBatchRequest>(req, state.app_ctx.clone())
+                super::handle::<GraphQLBatchRequest>(req, state.clone())
             }))
         }
     });
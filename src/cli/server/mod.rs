@@ -3,9 +3,22 @@ pub mod http_2;
 pub mod server;
 pub mod server_config;
 
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Method, Request, Response};
+use serde::de::DeserializeOwned;
 pub use server::Server;
 
 use self::server_config::ServerConfig;
+use crate::async_graphql_hyper::GraphQLRequestLike;
+use crate::cache::CacheSnapshot;
+use crate::http::{handle_request, BulkheadSnapshot};
+
+/// How often the statistics of the upstreams are reported
+const STATS_INTERVAL: Duration = Duration::from_secs(60);
 
 fn log_launch_and_open_browser(sc: &ServerConfig) {
     let addr = sc.addr().to_string();
@@ -21,3 +34,162 @@ fn log_launch_and_open_browser(sc: &ServerConfig) {
         let _ = webbrowser::open(url.as_str());
     }
 }
+
+/// Serves the statistics at `/metrics` when they are enabled, and every other
+/// request with [handle_request].
+async fn handle<T: DeserializeOwned + GraphQLRequestLike>(
+    req: Request<Body>,
+    sc: Arc<ServerConfig>,
+) -> anyhow::Result<Response<Body>> {
+    if sc.blueprint.server.enable_metrics
+        && req.method() == Method::GET
+        && req.uri().path() == "/metrics"
+    {
+        let metrics = to_metrics(sc.bulkhead.stats(), sc.app_ctx.runtime.cache.stats());
+        return Ok(Response::builder()
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(metrics))?);
+    }
+    handle_request::<T>(req, sc.app_ctx.clone()).await
+}
+
+/// Formats the statistics in the Prometheus text format. The cache is left
+/// out when it doesn't keep statistics.
+fn to_metrics(bulkhead: BulkheadSnapshot, cache: Option<CacheSnapshot>) -> String {
+    let mut metrics = vec![
+        (
+            "upstream_in_flight",
+            "gauge",
+            "Requests being sent to the upstreams",
+            bulkhead.in_flight as u64,
+        ),
+        (
+            "upstream_queued",
+            "gauge",
+            "Requests waiting for a free slot",
+            bulkhead.queued as u64,
+        ),
+        (
+            "upstream_saturated_total",
+            "counter",
+            "Requests that found their upstream saturated",
+            bulkhead.saturated,
+        ),
+        (
+            "upstream_timed_out_total",
+            "counter",
+            "Requests that failed waiting for a free slot",
+            bulkhead.timed_out,
+        ),
+    ];
+    if let Some(cache) = cache {
+        metrics.extend([
+            (
+                "cache_hits_total",
+                "counter",
+                "Cache lookups that found a live entry",
+                cache.hits,
+            ),
+            (
+                "cache_misses_total",
+                "counter",
+                "Cache lookups that found no live entry",
+                cache.misses,
+            ),
+            (
+                "cache_evictions_total",
+                "counter",
+                "Cache entries evicted to stay within the bounds",
+                cache.evictions,
+            ),
+            (
+                "cache_entries",
+                "gauge",
+                "Entries held by the cache",
+                cache.entries as u64,
+            ),
+            (
+                "cache_bytes",
+                "gauge",
+                "Approximate bytes held by the cache",
+                cache.bytes as u64,
+            ),
+        ]);
+    }
+
+    let mut text = String::new();
+    for (name, kind, help, value) in metrics {
+        let _ = write!(
+            text,
+            "# HELP tailcall_{0} {2}.\n# TYPE tailcall_{0} {1}\ntailcall_{0} {3}\n",
+            name, kind, help, value
+        );
+    }
+    text
+}
+
+/// Periodically logs how busy the upstreams are and how effective the cache
+/// is, whenever it changed since the last report.
+fn report_stats(sc: Arc<ServerConfig>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(STATS_INTERVAL);
+        let mut last_bulkhead = Default::default();
+        let mut last_cache = None;
+        loop {
+            interval.tick().await;
+
+            let bulkhead = sc.bulkhead.stats();
+            if bulkhead != last_bulkhead {
+                log::info!(
+                    "Upstreams: {} in flight, {} queued, {} saturated, {} timed out",
+                    bulkhead.in_flight,
+                    bulkhead.queued,
+                    bulkhead.saturated,
+                    bulkhead.timed_out
+                );
+                last_bulkhead = bulkhead;
+            }
+
+            let cache = sc.app_ctx.runtime.cache.stats();
+            if let Some(stats) = cache.filter(|_| cache != last_cache) {
+                log::info!(
+                    "Cache: {} hits, {} misses, {} evictions, {} entries, {} bytes",
+                    stats.hits,
+                    stats.misses,
+                    stats.evictions,
+                    stats.entries,
+                    stats.bytes
+                );
+                last_cache = cache;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_metrics;
+    use crate::cache::CacheSnapshot;
+    use crate::http::BulkheadSnapshot;
+
+    #[test]
+    fn test_metrics() {
+        let bulkhead = BulkheadSnapshot { in_flight: 2, queued: 1, saturated: 5, timed_out: 3 };
+        let metrics = to_metrics(bulkhead, None);
+
+        assert!(metrics.starts_with(
+            "# HELP tailcall_upstream_in_flight Requests being sent to the upstreams.\n# TYPE tailcall_upstream_in_flight gauge\ntailcall_upstream_in_flight 2\n"
+        ));
+        assert!(metrics.contains("\ntailcall_upstream_queued 1\n"));
+        assert!(metrics.contains("\ntailcall_upstream_saturated_total 5\n"));
+        assert!(metrics.contains("\ntailcall_upstream_timed_out_total 3\n"));
+        assert!(!metrics.contains("cache"));
+
+        let cache = CacheSnapshot { hits: 7, misses: 4, evictions: 1, entries: 6, bytes: 512 };
+        let metrics = to_metrics(bulkhead, Some(cache));
+
+        assert!(metrics
+            .contains("# TYPE tailcall_cache_hits_total counter\ntailcall_cache_hits_total 7\n"));
+        assert!(metrics.contains("\ntailcall_cache_bytes 512\n"));
+    }
+}
@@ -10,15 +10,23 @@ use super::server_config::ServerConfig;
 use crate::blueprint::{Blueprint, Http};
 use crate::cli::CLIError;
 use crate::config::ConfigModule;
+use crate::http::CassetteMode;
 
 pub struct Server {
     config_set: ConfigModule,
     server_up_sender: Option<oneshot::Sender<()>>,
+    cassette: Option<CassetteMode>,
 }
 
 impl Server {
     pub fn new(config_set: ConfigModule) -> Self {
-        Self { config_set, server_up_sender: None }
+        Self { config_set, server_up_sender: None, cassette: None }
+    }
+
+    /// Records the upstream traffic into, or replays it from, cassettes
+    pub fn cassette(mut self, cassette: Option<CassetteMode>) -> Self {
+        self.cassette = cassette;
+        self
     }
 
     pub fn server_up_receiver(&mut self) -> oneshot::Receiver<()> {
@@ -32,7 +40,8 @@ impl Server {
     /// Starts the server in the current Runtime
     pub async fn start(self) -> Result<()> {
         let blueprint = Blueprint::try_from(&self.config_set).map_err(CLIError::from)?;
-        let server_config = Arc::new(ServerConfig::new(blueprint.clone()));
+        let server_config = Arc::new(ServerConfig::new(blueprint.clone(), self.cassette.as_ref()));
+        super::report_stats(server_config.clone());
 
         match blueprint.server.http.clone() {
             Http::HTTP2 { cert, key } => {
@@ -2,21 +2,28 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
 use crate::blueprint::{Blueprint, Http};
-use crate::cli::runtime::init;
-use crate::http::AppContext;
+use crate::cli::runtime::{init_cache, init_with_cassette};
+use crate::http::{AppContext, BulkheadState, CassetteMode};
+use crate::runtime::TargetRuntime;
 
 pub struct ServerConfig {
     pub blueprint: Blueprint,
     pub app_ctx: Arc<AppContext>,
+    pub bulkhead: Arc<BulkheadState>,
 }
 
 impl ServerConfig {
-    pub fn new(blueprint: Blueprint) -> Self {
-        let server_context = Arc::new(AppContext::new(
-            blueprint.clone(),
-            init(&blueprint.upstream, blueprint.server.script.clone()),
-        ));
-        Self { app_ctx: server_context, blueprint }
+    pub fn new(blueprint: Blueprint, cassette: Option<&CassetteMode>) -> Self {
+        let bulkhead = Arc::new(BulkheadState::default());
+        let runtime = init_with_cassette(
+            &blueprint.upstream,
+            blueprint.server.script.clone(),
+            cassette,
+            bulkhead.clone(),
+        );
+        let runtime = TargetRuntime { cache: init_cache(&blueprint.server.cache), ..runtime };
+        let server_context = Arc::new(AppContext::new(blueprint.clone(), runtime));
+        Self { app_ctx: server_context, blueprint, bulkhead }
     }
 
     pub fn addr(&self) -> SocketAddr {
@@ -7,7 +7,7 @@ use env_logger::Env;
 use inquire::Confirm;
 use stripmargin::StripMargin;
 
-use super::command::{Cli, Command};
+use super::command::{Cli, Command, GenCommand};
 use super::update_checker;
 use crate::blueprint::{validate_operations, Blueprint, OperationQuery, Upstream};
 use crate::cli::fmt::Fmt;
@@ -15,8 +15,9 @@ use crate::cli::server::Server;
 use crate::cli::{self, CLIError};
 use crate::config::reader::ConfigReader;
 use crate::config::Config;
-use crate::print_schema;
+use crate::http::CassetteMode;
 use crate::valid::Validator;
+use crate::{generator, print_schema};
 
 const FILE_NAME: &str = ".tailcallrc.graphql";
 const YML_FILE_NAME: &str = ".graphqlrc.yml";
@@ -28,10 +29,18 @@ pub async fn run() -> Result<()> {
     let runtime = cli::runtime::init(&Upstream::default(), None);
     let config_reader = ConfigReader::init(runtime.clone());
     match cli.command {
-        Command::Start { file_paths } => {
+        Command::Start { file_paths, record, replay } => {
             let config_set = config_reader.read_all(&file_paths).await?;
             log::info!("N + 1: {}", config_set.n_plus_one().len().to_string());
-            let server = Server::new(config_set);
+            let cassette = match (record, replay) {
+                (Some(dir), _) => {
+                    fs::create_dir_all(&dir)?;
+                    Some(CassetteMode::Record(dir))
+                }
+                (None, Some(dir)) => Some(CassetteMode::Replay(dir)),
+                (None, None) => None,
+            };
+            let server = Server::new(config_set).cassette(cassette);
             server.fork_start().await?;
             Ok(())
         }
@@ -77,6 +86,12 @@ pub async fn run() -> Result<()> {
             Fmt::display(format.encode(&config)?);
             Ok(())
         }
+        Command::Gen { command: GenCommand::Proto { file_path, base_url, format } } => {
+            let descriptors = config_reader.read_descriptor_set(&file_path).await?;
+            let config = generator::from_proto(descriptors, &file_path, &base_url)?;
+            Fmt::display(format.encode(&config)?);
+            Ok(())
+        }
     }
 }
 
@@ -8,7 +8,7 @@ use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::{Expr, Link, Server, Upstream};
+use super::{Expr, Link, PropagateHeader, Server, Upstream};
 use crate::config::from_document::from_document;
 use crate::config::source::Source;
 use crate::config::KeyValues;
@@ -264,6 +264,31 @@ impl Type {
 pub struct Cache {
     /// Specifies the duration, in milliseconds, of how long the value has to be stored in the cache.
     pub max_age: NonZeroU64,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Specifies the duration, in milliseconds, after `maxAge` during which an expired value is still returned, while it is refreshed in the background.
+    pub stale_while_revalidate: Option<NonZeroU64>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Specifies the duration, in milliseconds, after `maxAge` during which an expired value is returned when the upstream fails.
+    pub stale_if_error: Option<NonZeroU64>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// A mustache template rendered into the key of the cached value, eg. `{{args.id}}-{{headers.x-tenant}}`. Fields rendering the same key share the cached value. By default the key is derived from the whole upstream request, including the forwarded headers.
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Mustache templates rendered into the tags of the cached value, which are evicted by the `@cacheInvalidate` operator. `value` refers to the cached value, or to each of its items when it's a list, eg. `["user:{{value.id}}"]`.
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// Specifies the duration, in milliseconds, of how long negative results are stored in the cache: `null` values, and the upstream errors with one of the `negativeStatuses`. By default they are cached like any other value, or not at all for errors.
+    pub negative_ttl: Option<NonZeroU64>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The error statuses of the upstream responses that are cached for `negativeTtl`, eg. `[404, 410]`.
+    pub negative_statuses: Vec<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Eq, schemars::JsonSchema)]
+/// The @cacheInvalidate operator evicts the values cached with any of the given tags once the mutation it is applied to succeeds.
+pub struct CacheInvalidate {
+    /// Mustache templates rendered into the tags to evict. `value` refers to the result of the mutation, eg. `["user:{{value.id}}"]` or `["user:{{args.id}}"]`.
+    pub tags: Vec<String>,
 }
 
 fn merge_types(
@@ -414,6 +439,16 @@ pub struct Field {
     /// Sets the cache configuration for a field
     ///
     pub cache: Option<Cache>,
+
+    ///
+    /// Evicts cached values once the mutation succeeds.
+    ///
+    #[serde(
+        rename = "cacheInvalidate",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    pub cache_invalidate: Option<CacheInvalidate>,
 }
 
 impl Field {
@@ -547,7 +582,7 @@ pub struct Http {
     pub body: Option<String>,
 
     #[serde(default, skip_serializing_if = "is_default")]
-    /// The `encoding` parameter specifies the encoding of the request body. It can be `ApplicationJson` or `ApplicationXWwwFormUrlEncoded`. @default `ApplicationJson`.
+    /// The `encoding` parameter specifies the encoding of the request body. It can be `ApplicationJson`, `ApplicationXWwwFormUrlEncoded`, `MultipartFormData`, `TextPlain` or `ApplicationXml`. With `MultipartFormData` every field of the body becomes a part of the form, and objects with a `filename`, a `content` and an optional `contentType` are sent as files, their `content` being decoded first when their `contentEncoding` is `base64`. With `ApplicationXml` a body that renders to an object is converted into XML elements, wrapped in a `root` element unless the object has a single field, any other body is sent as it is. @default `ApplicationJson`.
     pub encoding: Encoding,
 
     #[serde(rename = "groupBy", default, skip_serializing_if = "is_default")]
@@ -558,6 +593,10 @@ pub struct Http {
     /// The `headers` parameter allows you to customize the headers of the HTTP request made by the `@http` operator. It is used by specifying a key-value map of header names and their values.
     pub headers: KeyValues,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The `hedge` parameter sends duplicate requests to upstreams with long tail latency. When no response has arrived within `delayMs`, another request is sent, up to `maxExtra` times. The first response wins and the others are cancelled. Only supported for `GET` requests.
+    pub hedge: Option<Hedge>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// Schema of the input of the API call. It is automatically inferred in most cases.
     pub input: Option<JsonSchema>,
@@ -566,6 +605,10 @@ pub struct Http {
     /// This refers to the HTTP method of the API call. Commonly used methods include `GET`, `POST`, `PUT`, `DELETE` etc. @default `GET`.
     pub method: Method,
 
+    #[serde(rename = "onStatus", default, skip_serializing_if = "is_default")]
+    /// The `onStatus` parameter decides what happens when the upstream answers with an error status. With the `Null` action the field resolves to `null`, with the `Error` action the error is reported with the given `code` in its extensions. The status, url and an excerpt of the body of the response are always part of the extensions of the error.
+    pub on_status: Vec<OnStatus>,
+
     /// This refers to the API endpoint you're going to call. For instance `https://jsonplaceholder.typicode.com/users`.
     ///
     /// For dynamic segments in your API endpoint, use Mustache templates for variable substitution. For instance, to fetch a specific user, use `/users/{{args.id}}`.
@@ -575,9 +618,103 @@ pub struct Http {
     /// Schema of the output of the API call. It is automatically inferred in most cases.
     pub output: Option<JsonSchema>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The `paginate` parameter fetches every page of a paginated API, or up to `maxPages` pages, and concatenates the items of all the pages into the list returned by the field. Only supported for `GET` requests.
+    pub paginate: Option<Paginate>,
+
+    #[serde(
+        rename = "propagateHeaders",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    /// A list of response headers of this API copied to the response of the server, in addition to the `propagateHeaders` of the `@upstream` operator. A header listed in both places is merged as configured here.
+    pub propagate_headers: Vec<PropagateHeader>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// This represents the query parameters of your API call. You can pass it as a static object or use Mustache template for dynamic parameters. These parameters will be added to the URL.
     pub query: KeyValues,
+
+    #[serde(rename = "responseFormat", default, skip_serializing_if = "is_default")]
+    /// The `responseFormat` parameter specifies how the body of the response is decoded. It can be `Json`, `Xml`, `Csv` or `Text`. XML elements become objects whose attributes and children are fields, CSV rows become objects keyed by the header row. By default the format is detected from the `Content-Type` of the response, falling back to `Json`.
+    pub response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Hedge {
+    /// The time in milliseconds to wait for a response before sending a duplicate request.
+    pub delay_ms: u64,
+    /// The maximum number of duplicate requests sent in addition to the original one. @default `1`.
+    #[serde(default = "Hedge::default_max_extra")]
+    pub max_extra: usize,
+}
+
+impl Hedge {
+    fn default_max_extra() -> usize {
+        1
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, schemars::JsonSchema)]
+pub enum StatusAction {
+    /// Resolves the field to `null`.
+    Null,
+    /// Reports an error.
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+pub struct OnStatus {
+    /// The error status of the response, eg. `404`.
+    pub status: u16,
+    /// What to do when the upstream answers with `status`.
+    pub action: StatusAction,
+    /// The `code` put in the extensions of the error, only used with the `Error` action.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, schemars::JsonSchema)]
+pub enum PaginationStrategy {
+    /// Follows the `rel="next"` url of the `Link` response header.
+    LinkHeader,
+    /// Sends the value found at `cursorPath` in the response as the `param` query parameter of the next request.
+    Cursor,
+    /// Increments the `param` query parameter until a page without items is returned.
+    Page,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Paginate {
+    /// How the next page is requested.
+    pub strategy: PaginationStrategy,
+    /// Path of the cursor for the next page in the response, required by the `Cursor` strategy. Pagination stops once the cursor is missing or empty.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cursor_path: Vec<String>,
+    /// Path of the list of items in the response. @default the whole response.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub data_path: Vec<String>,
+    /// The query parameter carrying the cursor or the page number. @default `cursor` or `page`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub param: Option<String>,
+    /// The maximum number of pages fetched. @default `100`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub max_pages: Option<usize>,
+}
+
+impl Paginate {
+    pub fn get_max_pages(&self) -> usize {
+        self.max_pages.unwrap_or(100)
+    }
+
+    pub fn get_param(&self) -> &str {
+        match (&self.param, &self.strategy) {
+            (Some(param), _) => param,
+            (None, PaginationStrategy::Page) => "page",
+            (None, _) => "cursor",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
@@ -593,16 +730,186 @@ pub struct Grpc {
     /// This refers to the base URL of the API. If not specified, the default base URL is the one specified in the `@upstream` operator.
     pub base_url: Option<String>,
     #[serde(default, skip_serializing_if = "is_default")]
+    /// The repeated field of the request message holding the batched requests when `groupBy` is set, for instance `ids`. @default the first repeated field.
+    pub batch_key: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
     /// This refers to the arguments of your gRPC call. You can pass it as a static object or use Mustache template for dynamic parameters. These parameters will be added in the body in `protobuf` format.
     pub body: Option<String>,
     #[serde(default, skip_serializing_if = "is_default")]
+    /// The algorithm used to compress the request message. It overrides the `grpcCompression` of the `@upstream` operator. @default `Identity`.
+    pub compression: Option<GrpcCompression>,
+    #[serde(default, skip_serializing_if = "is_default")]
     /// The key path in the response which should be used to group multiple requests. For instance `["news","id"]`. For more details please refer out [n + 1 guide](https://tailcall.run/docs/guides/n+1#solving-using-batching).
     pub group_by: Vec<String>,
     #[serde(default, skip_serializing_if = "is_default")]
-    /// The `headers` parameter allows you to customize the headers of the HTTP request made by the `@grpc` operator. It is used by specifying a key-value map of header names and their values. Note: content-type is automatically set to application/grpc
+    /// The `headers` parameter allows you to customize the headers of the HTTP request made by the `@grpc` operator. It is used by specifying a key-value map of header names and their values. Note: content-type is automatically set to application/grpc. The values of binary `-bin` metadata are given in base64.
     pub headers: KeyValues,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The names of the metadata of the response forwarded in the `grpcMetadata` extension of the GraphQL response, for instance `["x-request-id"]`. The values of binary `-bin` metadata are forwarded in base64.
+    pub metadata: Vec<String>,
     /// This refers to the gRPC method you're going to call. For instance `GetAllNews`.
     pub method: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// What to do when the upstream answers with an error status, for instance `[{status: NOT_FOUND, action: Null}]`. Other errors are reported with their status, message and details in the extensions of the error.
+    pub on_status: Vec<GrpcOnStatus>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The transport used to reach the service. `GrpcWeb` and `GrpcWebText` call a gRPC-Web proxy over HTTP/1.1. @default `Grpc`.
+    pub protocol: GrpcProtocol,
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The maximum time in seconds to wait for the response, sent to the service as the deadline of the call in the `grpc-timeout` header. @default the `timeout` of the `@upstream` operator.
+    pub timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, schemars::JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+/// The status codes of gRPC, sent in the `grpc-status` header.
+pub enum GrpcCode {
+    Ok,
+    Cancelled,
+    Unknown,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+}
+
+impl GrpcCode {
+    const ALL: [GrpcCode; 17] = [
+        Self::Ok,
+        Self::Cancelled,
+        Self::Unknown,
+        Self::InvalidArgument,
+        Self::DeadlineExceeded,
+        Self::NotFound,
+        Self::AlreadyExists,
+        Self::PermissionDenied,
+        Self::ResourceExhausted,
+        Self::FailedPrecondition,
+        Self::Aborted,
+        Self::OutOfRange,
+        Self::Unimplemented,
+        Self::Internal,
+        Self::Unavailable,
+        Self::DataLoss,
+        Self::Unauthenticated,
+    ];
+
+    /// Unknown codes are mapped to `UNKNOWN`, as required by the gRPC spec.
+    pub fn from_code(code: i32) -> Self {
+        usize::try_from(code)
+            .ok()
+            .and_then(|code| Self::ALL.get(code))
+            .copied()
+            .unwrap_or(Self::Unknown)
+    }
+
+    pub fn code(&self) -> i32 {
+        *self as i32
+    }
+
+    /// The name of the code, eg. `NOT_FOUND`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Cancelled => "CANCELLED",
+            Self::Unknown => "UNKNOWN",
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::DeadlineExceeded => "DEADLINE_EXCEEDED",
+            Self::NotFound => "NOT_FOUND",
+            Self::AlreadyExists => "ALREADY_EXISTS",
+            Self::PermissionDenied => "PERMISSION_DENIED",
+            Self::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            Self::FailedPrecondition => "FAILED_PRECONDITION",
+            Self::Aborted => "ABORTED",
+            Self::OutOfRange => "OUT_OF_RANGE",
+            Self::Unimplemented => "UNIMPLEMENTED",
+            Self::Internal => "INTERNAL",
+            Self::Unavailable => "UNAVAILABLE",
+            Self::DataLoss => "DATA_LOSS",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+        }
+    }
+}
+
+impl Display for GrpcCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, schemars::JsonSchema)]
+pub struct GrpcOnStatus {
+    /// The error status of the response, eg. `NOT_FOUND`.
+    pub status: GrpcCode,
+    /// What to do when the upstream answers with `status`.
+    pub action: StatusAction,
+    /// The `code` put in the extensions of the error, only used with the `Error` action.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub code: Option<String>,
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, schemars::JsonSchema,
+)]
+/// The compression of the gRPC messages, sent in the `grpc-encoding` header. When a compression is set, the server is also allowed to compress its responses with gzip or deflate.
+pub enum GrpcCompression {
+    /// The messages are not compressed.
+    #[default]
+    Identity,
+    /// The messages are compressed with gzip.
+    Gzip,
+    /// The messages are compressed with deflate, in the zlib format.
+    Deflate,
+}
+
+impl GrpcCompression {
+    /// The name of the compression in the `grpc-encoding` header.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, schemars::JsonSchema,
+)]
+/// The transport of the gRPC calls.
+pub enum GrpcProtocol {
+    /// gRPC over HTTP/2.
+    #[default]
+    Grpc,
+    /// gRPC-Web with binary messages, sent as `application/grpc-web+proto`.
+    GrpcWeb,
+    /// gRPC-Web with messages encoded in base64, sent as `application/grpc-web-text+proto`.
+    GrpcWebText,
+}
+
+impl GrpcProtocol {
+    /// The content type of the requests and responses.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Grpc => "application/grpc",
+            Self::GrpcWeb => "application/grpc-web+proto",
+            Self::GrpcWebText => "application/grpc-web-text+proto",
+        }
+    }
+
+    pub fn is_web(&self) -> bool {
+        *self != Self::Grpc
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
@@ -636,6 +943,7 @@ pub enum GraphQLOperationType {
     #[default]
     Query,
     Mutation,
+    Subscription,
 }
 
 impl Display for GraphQLOperationType {
@@ -643,6 +951,7 @@ impl Display for GraphQLOperationType {
         f.write_str(match self {
             Self::Query => "query",
             Self::Mutation => "mutation",
+            Self::Subscription => "subscription",
         })
     }
 }
@@ -699,6 +1008,17 @@ pub enum Encoding {
     #[default]
     ApplicationJson,
     ApplicationXWwwFormUrlencoded,
+    MultipartFormData,
+    TextPlain,
+    ApplicationXml,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, schemars::JsonSchema)]
+pub enum ResponseFormat {
+    Json,
+    Xml,
+    Csv,
+    Text,
 }
 
 #[cfg(test)]
@@ -10,8 +10,8 @@ use async_graphql::Name;
 
 use super::JS;
 use crate::config::{
-    self, Cache, Config, Expr, GraphQL, Grpc, Link, Modify, Omit, RootSchema, Server, Union,
-    Upstream,
+    self, Cache, CacheInvalidate, Config, Expr, GraphQL, Grpc, Link, Modify, Omit, RootSchema,
+    Server, Union, Upstream,
 };
 use crate::directive::DirectiveCodec;
 use crate::valid::{Valid, Validator};
@@ -281,26 +281,30 @@ where
         .fuse(Omit::from_directives(directives.iter()))
         .fuse(Modify::from_directives(directives.iter()))
         .fuse(JS::from_directives(directives.iter()))
-        .map(|(http, graphql, cache, grpc, expr, omit, modify, script)| {
-            let const_field = to_const_field(directives);
-            config::Field {
-                type_of,
-                list,
-                required: !nullable,
-                list_type_required,
-                args,
-                doc,
-                modify,
-                omit,
-                http,
-                grpc,
-                script,
-                const_field,
-                graphql,
-                expr,
-                cache,
-            }
-        })
+        .fuse(CacheInvalidate::from_directives(directives.iter()))
+        .map(
+            |(http, graphql, cache, grpc, expr, omit, modify, script, cache_invalidate)| {
+                let const_field = to_const_field(directives);
+                config::Field {
+                    type_of,
+                    list,
+                    required: !nullable,
+                    list_type_required,
+                    args,
+                    doc,
+                    modify,
+                    omit,
+                    http,
+                    grpc,
+                    script,
+                    const_field,
+                    graphql,
+                    expr,
+                    cache,
+                    cache_invalidate,
+                }
+            },
+        )
 }
 
 fn to_type_of(type_: &Type) -> String {
@@ -98,6 +98,8 @@ fn config_document(config: &Config) -> ServiceDocument {
                     })
                     .collect(),
             })
+        } else if type_def.scalar {
+            TypeKind::Scalar
         } else if config.input_types().contains(type_name) {
             TypeKind::InputObject(InputObjectType {
                 fields: type_def
@@ -248,6 +250,10 @@ fn get_directives(field: &crate::config::Field) -> Vec<Positioned<ConstDirective
         field.grpc.as_ref().map(|d| pos(d.to_directive())),
         field.expr.as_ref().map(|d| pos(d.to_directive())),
         field.cache.as_ref().map(|d| pos(d.to_directive())),
+        field
+            .cache_invalidate
+            .as_ref()
+            .map(|d| pos(d.to_directive())),
     ];
 
     directives.into_iter().flatten().collect()
@@ -9,6 +9,8 @@ pub enum LinkType {
     #[default]
     Config,
     Protobuf,
+    ProtobufDescriptorSet,
+    GrpcReflection,
     Script,
     Cert,
     Key,
@@ -19,6 +21,8 @@ impl Display for LinkType {
         f.write_str(match self {
             LinkType::Config => "Config",
             LinkType::Protobuf => "Protobuf",
+            LinkType::ProtobufDescriptorSet => "ProtobufDescriptorSet",
+            LinkType::GrpcReflection => "GrpcReflection",
             LinkType::Script => "Script",
             LinkType::Cert => "Cert",
             LinkType::Key => "Key",
@@ -42,7 +46,7 @@ pub struct Link {
     #[serde(default, skip_serializing_if = "is_default")]
     pub src: String,
     ///
-    /// The type of the link. It can be `Config`, or `Protobuf`.
+    /// The type of the link. It can be `Config`, `Protobuf`, `ProtobufDescriptorSet` to load a binary `FileDescriptorSet` built with `protoc --include_imports`, or `GrpcReflection` to load the definitions of every service of the gRPC server at `src` using its reflection service.
     ///
     #[serde(default, skip_serializing_if = "is_default", rename = "type")]
     pub type_of: LinkType,
@@ -5,6 +5,7 @@ use anyhow::Context;
 use async_std::path::{Path, PathBuf};
 use futures_util::future::join_all;
 use futures_util::TryFutureExt;
+use prost::Message;
 use prost_reflect::prost_types::{FileDescriptorProto, FileDescriptorSet};
 use protox::file::{FileResolver, GoogleFileResolver};
 use rustls_pemfile;
@@ -15,6 +16,7 @@ use url::Url;
 
 use super::{ConfigModule, Content, Link, LinkType};
 use crate::config::{Config, Source};
+use crate::grpc::reflection;
 use crate::runtime::TargetRuntime;
 
 /// Reads the configuration from a file or from an HTTP URL and resolves all linked extensions to create a ConfigModule.
@@ -36,22 +38,26 @@ impl ConfigReader {
 
     /// Reads a file from the filesystem or from an HTTP URL
     async fn read_file<T: ToString>(&self, file: T) -> anyhow::Result<FileRead> {
+        let content = String::from_utf8(self.read_bytes(&file.to_string()).await?)?;
+
+        Ok(FileRead { content, path: file.to_string() })
+    }
+
+    /// Like [ConfigReader::read_file], without decoding the content as UTF-8
+    async fn read_bytes(&self, file: &str) -> anyhow::Result<Vec<u8>> {
         // Is an HTTP URL
-        let content = if let Ok(url) = Url::parse(&file.to_string()) {
+        if let Ok(url) = Url::parse(file) {
             let response = self
                 .runtime
                 .http
                 .execute(reqwest::Request::new(reqwest::Method::GET, url))
                 .await?;
 
-            String::from_utf8(response.body.to_vec())?
+            Ok(response.body.to_vec())
         } else {
             // Is a file path
-
-            self.runtime.file.read(&file.to_string()).await?
-        };
-
-        Ok(FileRead { content, path: file.to_string() })
+            self.runtime.file.read_bytes(file).await
+        }
     }
 
     /// Reads all the files in parallel
@@ -92,6 +98,16 @@ impl ConfigReader {
         }
 
         for config_link in links.iter() {
+            if config_link.type_of == LinkType::GrpcReflection {
+                let file_descriptor_set =
+                    reflection::resolve_descriptors(&self.runtime, &config_link.src).await?;
+                config_set
+                    .extensions
+                    .grpc_file_descriptors
+                    .push(Content { id: config_link.id.to_owned(), content: file_descriptor_set });
+                continue;
+            }
+
             let path = if Path::new(&config_link.src).is_absolute() {
                 config_link.src.clone()
             } else {
@@ -104,6 +120,17 @@ impl ConfigReader {
                     .to_string()
             };
 
+            if config_link.type_of == LinkType::ProtobufDescriptorSet {
+                let content = self.read_bytes(&path).await?;
+                let file_descriptor_set = FileDescriptorSet::decode(content.as_slice())
+                    .with_context(|| format!("Invalid FileDescriptorSet in {}", path))?;
+                config_set
+                    .extensions
+                    .grpc_file_descriptors
+                    .push(Content { id: config_link.id.to_owned(), content: file_descriptor_set });
+                continue;
+            }
+
             let source = self.read_file(&path).await?;
 
             let content = source.content;
@@ -123,20 +150,15 @@ impl ConfigReader {
                     }
                 }
                 LinkType::Protobuf => {
-                    let descriptors = self
-                        .resolve_descriptors(HashMap::new(), source.path)
-                        .await?;
-                    let mut file_descriptor_set = FileDescriptorSet::default();
-
-                    for (_, v) in descriptors {
-                        file_descriptor_set.file.push(v);
-                    }
+                    let file_descriptor_set = self.read_descriptor_set(&source.path).await?;
 
                     config_set.extensions.grpc_file_descriptors.push(Content {
                         id: config_link.id.to_owned(),
                         content: file_descriptor_set,
                     });
                 }
+                LinkType::ProtobufDescriptorSet => unreachable!("read as a binary file"),
+                LinkType::GrpcReflection => unreachable!("resolved without reading a file"),
                 LinkType::Script => {
                     config_set.extensions.script = Some(content);
                 }
@@ -231,6 +253,15 @@ impl ConfigReader {
         Ok(config_set)
     }
 
+    /// Reads the proto file at `path` along with every file it imports.
+    pub async fn read_descriptor_set(&self, path: &str) -> anyhow::Result<FileDescriptorSet> {
+        let descriptors = self
+            .resolve_descriptors(HashMap::new(), path.to_string())
+            .await?;
+
+        Ok(FileDescriptorSet { file: descriptors.into_values().collect() })
+    }
+
     /// Performs BFS to import all nested proto files
     async fn resolve_descriptors(
         &self,
@@ -351,10 +382,11 @@ mod test_proto_config {
 mod reader_tests {
     use anyhow::Context;
     use pretty_assertions::assert_eq;
+    use prost::Message;
     use tokio::io::AsyncReadExt;
 
     use crate::config::reader::ConfigReader;
-    use crate::config::{Config, Type};
+    use crate::config::{Config, Link, LinkType, Type};
 
     fn start_mock_server() -> httpmock::MockServer {
         httpmock::MockServer::start()
@@ -463,4 +495,42 @@ mod reader_tests {
 
         assert_eq!(content.unwrap(), config.extensions.script.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_descriptor_set_loader() {
+        let runtime = crate::runtime::test::init(None);
+        let reader = ConfigReader::init(runtime);
+
+        let descriptors = protox::compile(["news.proto"], ["src/grpc/tests"]).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        tokio::fs::write(file.path(), descriptors.encode_to_vec())
+            .await
+            .unwrap();
+        let link = |src: &str| Link {
+            id: Some("news".to_string()),
+            src: src.to_string(),
+            type_of: LinkType::ProtobufDescriptorSet,
+        };
+
+        let config = Config {
+            links: vec![link(file.path().to_str().unwrap())],
+            ..Default::default()
+        };
+        let config_module = reader.resolve(config, None).await.unwrap();
+        let content = config_module
+            .extensions
+            .get_file_descriptor("news")
+            .unwrap();
+        assert_eq!(content, &descriptors);
+
+        let config = Config {
+            links: vec![link("src/grpc/tests/news.proto")],
+            ..Default::default()
+        };
+        let error = reader.resolve(config, None).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid FileDescriptorSet in src/grpc/tests/news.proto"
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
 
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +18,10 @@ pub struct Server {
     /// `batchRequests` combines multiple requests into one, improving performance but potentially introducing latency and complicating debugging. Use judiciously. @default `false`.
     pub batch_requests: Option<bool>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `cache` bounds the in-memory cache used by `@cache`, by number of entries and by memory, and selects which entries get evicted once a bound is reached.
+    pub cache: Option<ServerCache>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `cacheControlHeader` sends `Cache-Control` headers in responses when activated. The `max-age` value is the least of the values received from upstream services. @default `false`.
     pub cache_control_header: Option<bool>,
@@ -37,6 +42,10 @@ pub struct Server {
     /// `introspection` allows clients to fetch schema information directly, aiding tools and applications in understanding available types, fields, and operations. @default `true`.
     pub introspection: Option<bool>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `metrics` serves the counters of the upstream concurrency limits and of the cache at `/metrics`, in the Prometheus text format. @default `false`.
+    pub metrics: Option<bool>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// `pipelineFlush` allows to control flushing behavior of the server pipeline.
     pub pipeline_flush: Option<bool>,
@@ -91,6 +100,74 @@ pub enum HttpVersion {
     HTTP2,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerCache {
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `maxEntries` is the maximum number of entries held by the cache. @default `100000`.
+    pub max_entries: Option<NonZeroUsize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `maxBytes` is the approximate maximum amount of memory, in bytes, used by the cached values. Unbounded by default.
+    pub max_bytes: Option<NonZeroUsize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `evictionPolicy` decides which entry is evicted when the cache is full: the least recently used (`Lru`) or the least frequently used (`Lfu`) one. @default `Lru`.
+    pub eviction_policy: Option<EvictionPolicy>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `redis` stores the cache on a Redis server, so that it's shared by every replica. The in-memory cache is used while the server can't be reached.
+    pub redis: Option<Redis>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Redis {
+    /// `url` of the Redis server, eg. `redis://127.0.0.1:6379/0`.
+    pub url: String,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// `format` used to serialize the cached values. @default `Json`.
+    pub format: CacheFormat,
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, schemars::JsonSchema,
+)]
+pub enum CacheFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, schemars::JsonSchema,
+)]
+pub enum EvictionPolicy {
+    #[default]
+    Lru,
+    Lfu,
+}
+
+impl ServerCache {
+    pub fn get_max_entries(&self) -> usize {
+        self.max_entries.map_or(100000, NonZeroUsize::get)
+    }
+
+    pub fn get_eviction_policy(&self) -> EvictionPolicy {
+        self.eviction_policy.unwrap_or_default()
+    }
+
+    pub fn merge_right(self, other: Self) -> Self {
+        Self {
+            max_entries: other.max_entries.or(self.max_entries),
+            max_bytes: other.max_bytes.or(self.max_bytes),
+            eviction_policy: other.eviction_policy.or(self.eviction_policy),
+            redis: other.redis.or(self.redis),
+        }
+    }
+}
+
 impl Server {
     pub fn enable_apollo_tracing(&self) -> bool {
         self.apollo_tracing.unwrap_or(false)
@@ -127,6 +204,9 @@ impl Server {
     pub fn enable_showcase(&self) -> bool {
         self.showcase.unwrap_or(false)
     }
+    pub fn enable_metrics(&self) -> bool {
+        self.metrics.unwrap_or(false)
+    }
 
     pub fn get_hostname(&self) -> String {
         self.hostname.clone().unwrap_or("127.0.0.1".to_string())
@@ -144,6 +224,10 @@ impl Server {
         self.version.unwrap_or(HttpVersion::HTTP1)
     }
 
+    pub fn get_cache(&self) -> ServerCache {
+        self.cache.clone().unwrap_or_default()
+    }
+
     pub fn get_pipeline_flush(&self) -> bool {
         self.pipeline_flush.unwrap_or(true)
     }
@@ -156,10 +240,15 @@ impl Server {
         self.query_validation = other.query_validation.or(self.query_validation);
         self.response_validation = other.response_validation.or(self.response_validation);
         self.batch_requests = other.batch_requests.or(self.batch_requests);
+        self.cache = match (self.cache, other.cache) {
+            (Some(cache), Some(other)) => Some(cache.merge_right(other)),
+            (cache, other) => other.or(cache),
+        };
         self.global_response_timeout = other
             .global_response_timeout
             .or(self.global_response_timeout);
         self.showcase = other.showcase.or(self.showcase);
+        self.metrics = other.metrics.or(self.metrics);
         self.workers = other.workers.or(self.workers);
         self.port = other.port.or(self.port);
         self.hostname = other.hostname.or(self.hostname);
@@ -175,3 +264,21 @@ impl Server {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_cache_keeps_eviction_policy() {
+        let cache = ServerCache {
+            eviction_policy: Some(EvictionPolicy::Lfu),
+            ..Default::default()
+        };
+        let other = ServerCache { max_entries: NonZeroUsize::new(10), ..Default::default() };
+        let merged = cache.merge_right(other);
+
+        assert_eq!(merged.get_eviction_policy(), EvictionPolicy::Lfu);
+        assert_eq!(merged.get_max_entries(), 10);
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::BTreeSet;
 use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
 
+use super::GrpcCompression;
 use crate::is_default;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Setters, schemars::JsonSchema)]
@@ -23,6 +24,41 @@ pub struct Proxy {
     pub url: String,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyLimit {
+    /// The upstream host the limit applies to, for instance `jsonplaceholder.typicode.com`.
+    pub host: String,
+    /// The maximum number of requests that can be in flight to the host at the same time.
+    pub max_concurrent_requests: usize,
+}
+
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default, Hash, schemars::JsonSchema,
+)]
+pub enum HeaderMerge {
+    /// Keeps the values of every upstream.
+    #[default]
+    Append,
+    /// Keeps the value of the first upstream that responded.
+    First,
+    /// Keeps the value of the last upstream that responded.
+    Last,
+    /// Keeps the smallest numeric value, for instance for `X-RateLimit-Remaining`.
+    Min,
+    /// Keeps the largest numeric value.
+    Max,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, schemars::JsonSchema)]
+pub struct PropagateHeader {
+    /// The name of the upstream response header copied to the response of the server.
+    pub name: String,
+    /// How the values are merged when several upstreams return the header. @default `Append`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub merge: HeaderMerge,
+}
+
 #[derive(
     Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Setters, Default, schemars::JsonSchema,
 )]
@@ -41,10 +77,22 @@ pub struct Upstream {
     /// An object that specifies the batch settings, including `maxSize` (the maximum size of the batch), `delay` (the delay in milliseconds between each batch), and `headers` (an array of HTTP headers to be included in the batch).
     pub batch: Option<Batch>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// A list of per host concurrency limits, for instance `[{host: "legacy.example.com", maxConcurrentRequests: 5}]`. It overrides `maxConcurrentRequests` for the listed hosts. Each host is the concurrency profile of the `@http` and `@grpc` fields calling it: they share its slots, whichever field sends the request, so a field is given a profile of its own through the host of its `baseURL`. The counters of the limits are served at `/metrics` with the `metrics` option of the `@server` operator.
+    pub concurrency_limits: Vec<ConcurrencyLimit>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The time in milliseconds a request waits for a free slot when the concurrency limit of its upstream is reached. Once elapsed the request fails instead of being sent. If not set, requests wait until a slot is released.
+    pub concurrency_queue_timeout: Option<u64>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The time in seconds that the connection will wait for a response before timing out.
     pub connect_timeout: Option<u64>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The algorithm used to compress the messages of every `@grpc` request, either `Identity`, `Gzip` or `Deflate`. It can be overridden per field with the `compression` of [@grpc](#grpc). @default `Identity`.
+    pub grpc_compression: Option<GrpcCompression>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// Activating this enables Tailcall's HTTP caching, adhering to the [HTTP Caching RFC](https://tools.ietf.org/html/rfc7234), to enhance performance by minimizing redundant data fetches. Defaults to `false` if unspecified.
     pub http_cache: Option<bool>,
@@ -66,6 +114,10 @@ pub struct Upstream {
     /// A boolean value that determines whether keep-alive messages should be sent while the connection is idle.
     pub keep_alive_while_idle: Option<bool>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// The maximum number of requests that can be in flight to a single upstream host at the same time. Additional requests are queued until a slot is released. If not set, the number of concurrent requests is not limited.
+    pub max_concurrent_requests: Option<usize>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The maximum number of idle connections that will be maintained per host.
     pub pool_max_idle_per_host: Option<usize>,
@@ -78,6 +130,10 @@ pub struct Upstream {
     /// The `proxy` setting defines an intermediary server through which the upstream requests will be routed before reaching their intended endpoint. By specifying a proxy URL, you introduce an additional layer, enabling custom routing and security policies.
     pub proxy: Option<Proxy>,
 
+    #[serde(default, skip_serializing_if = "is_default")]
+    /// A list of upstream response headers copied to the response of the server, for instance `[{name: "X-RateLimit-Remaining", merge: Min}]`. It applies to every upstream and can be extended per field with the `propagateHeaders` of [@http](#http). A header already set by the server is never overwritten, and the headers about the connection or the body, such as `Content-Length`, can't be propagated.
+    pub propagate_headers: Vec<PropagateHeader>,
+
     #[serde(default, skip_serializing_if = "is_default")]
     /// The time in seconds between each TCP keep-alive message sent to maintain the connection.
     pub tcp_keep_alive: Option<u64>,
@@ -139,6 +195,10 @@ impl Upstream {
         self.http2_only.unwrap_or(false)
     }
 
+    pub fn get_concurrency_limits(&self) -> Vec<ConcurrencyLimit> {
+        self.concurrency_limits.clone()
+    }
+
     // TODO: add unit tests for merge
     pub fn merge_right(mut self, other: Self) -> Self {
         self.allowed_headers = other.allowed_headers.map(|other| {
@@ -150,14 +210,23 @@ impl Upstream {
             }
         });
         self.base_url = other.base_url.or(self.base_url);
+        self.concurrency_limits.extend(other.concurrency_limits);
+        self.concurrency_queue_timeout = other
+            .concurrency_queue_timeout
+            .or(self.concurrency_queue_timeout);
         self.connect_timeout = other.connect_timeout.or(self.connect_timeout);
+        self.grpc_compression = other.grpc_compression.or(self.grpc_compression);
         self.http_cache = other.http_cache.or(self.http_cache);
         self.keep_alive_interval = other.keep_alive_interval.or(self.keep_alive_interval);
         self.keep_alive_timeout = other.keep_alive_timeout.or(self.keep_alive_timeout);
         self.keep_alive_while_idle = other.keep_alive_while_idle.or(self.keep_alive_while_idle);
+        self.max_concurrent_requests = other
+            .max_concurrent_requests
+            .or(self.max_concurrent_requests);
         self.pool_idle_timeout = other.pool_idle_timeout.or(self.pool_idle_timeout);
         self.pool_max_idle_per_host = other.pool_max_idle_per_host.or(self.pool_max_idle_per_host);
         self.proxy = other.proxy.or(self.proxy);
+        self.propagate_headers.extend(other.propagate_headers);
         self.tcp_keep_alive = other.tcp_keep_alive.or(self.tcp_keep_alive);
         self.timeout = other.timeout.or(self.timeout);
         self.user_agent = other.user_agent.or(self.user_agent);
@@ -0,0 +1,517 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{bail, Context, Result};
+use prost_reflect::prost_types::FileDescriptorSet;
+use prost_reflect::{
+    DescriptorPool, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor,
+    ServiceDescriptor,
+};
+
+use crate::config::{Arg, Config, Field, Grpc, Link, LinkType, Type, Union};
+use crate::grpc::protobuf::{is_object, oneof_json_name, oneofs, type_name};
+
+/// The prefixes of the names of the methods exposed as queries, the other
+/// ones are exposed as mutations.
+const QUERY_PREFIXES: [&str; 6] = ["Get", "List", "Find", "Search", "Fetch", "Stream"];
+
+/// Generates a config exposing the methods of the services defined in the file
+/// `path` of `descriptors`, called at `base_url`. The messages are mapped to
+/// object types in responses and to input types in arguments.
+pub fn from_proto(descriptors: FileDescriptorSet, path: &str, base_url: &str) -> Result<Config> {
+    let pool = DescriptorPool::from_file_descriptor_set(descriptors)?;
+    let file = pool
+        .get_file_by_name(path)
+        .with_context(|| format!("File {} not found in the descriptors", path))?;
+
+    // @grpc methods are referenced as <package>.<service>.<method>
+    let package = file.package_name();
+    if package.is_empty() || package.contains('.') {
+        bail!(
+            "The package of {} must be a single name to be referenced by @grpc, found: {:?}",
+            path,
+            package
+        );
+    }
+
+    let mut generator = Generator::default();
+    for service in file.services() {
+        for method in service.methods() {
+            if !method.is_client_streaming() {
+                generator.add_method(&service, &method)?;
+            }
+        }
+    }
+
+    let mut config = generator.config;
+    config.upstream.base_url = Some(base_url.to_string());
+    config.links.push(Link {
+        id: Some(package.to_string()),
+        src: path.to_string(),
+        type_of: LinkType::Protobuf,
+    });
+
+    Ok(config)
+}
+
+#[derive(Default)]
+struct Generator {
+    config: Config,
+    /// The full names of the messages and enums behind the generated types.
+    sources: HashMap<String, String>,
+}
+
+impl Generator {
+    fn add_method(&mut self, service: &ServiceDescriptor, method: &MethodDescriptor) -> Result<()> {
+        let output = self.output_type(&method.output())?;
+        // the messages of a stream are collected into a list
+        let mut field = Field::default()
+            .type_of(output)
+            .list(method.is_server_streaming())
+            .list_type_required(method.is_server_streaming())
+            .required(true);
+
+        let mut grpc = Grpc {
+            method: format!("{}.{}", service.full_name(), method.name()),
+            ..Default::default()
+        };
+        let input = method.input();
+        if input.fields().len() > 0 {
+            let name = lower_first(input.name());
+            let arg = Arg {
+                type_of: self.input_type(&input)?,
+                list: false,
+                required: true,
+                doc: None,
+                modify: None,
+                default_value: None,
+            };
+            grpc.body = Some(format!("{{{{args.{}}}}}", name));
+            field.args.insert(name, arg);
+        }
+        field.grpc = Some(grpc);
+
+        let is_query = method.is_server_streaming()
+            || QUERY_PREFIXES
+                .iter()
+                .any(|prefix| method.name().starts_with(prefix));
+        let root = if is_query {
+            self.config.schema.query.get_or_insert("Query".to_string())
+        } else {
+            self.config
+                .schema
+                .mutation
+                .get_or_insert("Mutation".to_string())
+        };
+
+        let root = self.config.types.entry(root.clone()).or_default();
+        let mut name = lower_first(method.name());
+        if root.fields.contains_key(&name) {
+            name = format!("{}{}", lower_first(service.name()), method.name());
+        }
+        root.fields.insert(name, field);
+
+        Ok(())
+    }
+
+    /// Adds the object type of `message`. Messages without fields can't be
+    /// object types and are exposed as `JSON`.
+    fn output_type(&mut self, message: &MessageDescriptor) -> Result<String> {
+        if let Some(name) = self.well_known_type(message) {
+            return Ok(name);
+        }
+        if message.fields().len() == 0 {
+            return Ok(self.json());
+        }
+        let name = type_name(message.full_name(), message.package_name());
+        self.add_message(message, name, false)
+    }
+
+    fn input_type(&mut self, message: &MessageDescriptor) -> Result<String> {
+        if let Some(name) = self.well_known_type(message) {
+            return Ok(name);
+        }
+        if message.fields().len() == 0 {
+            return Ok(self.json());
+        }
+        let name = type_name(message.full_name(), message.package_name()) + "Input";
+        self.add_message(message, name, true)
+    }
+
+    /// Claims the type `name` for the message or enum `full_name`. Returns
+    /// whether the type is new, and fails when a definition from another
+    /// package already took the name.
+    fn claim(&mut self, name: &str, full_name: &str) -> Result<bool> {
+        match self.sources.get(name) {
+            Some(source) if source == full_name => Ok(false),
+            Some(source) => bail!(
+                "{} and {} would both be exposed as the type {}",
+                source,
+                full_name,
+                name
+            ),
+            None => {
+                self.sources.insert(name.to_string(), full_name.to_string());
+                Ok(true)
+            }
+        }
+    }
+
+    fn add_message(
+        &mut self,
+        message: &MessageDescriptor,
+        name: String,
+        input: bool,
+    ) -> Result<String> {
+        if !self.claim(&name, message.full_name())? {
+            return Ok(name);
+        }
+        // inserted first so that recursive messages reference it
+        self.config.types.insert(name.clone(), Type::default());
+
+        let mut fields = BTreeMap::new();
+        for field in message.fields() {
+            fields.insert(field.json_name().to_string(), self.field(&field, input)?);
+        }
+
+        // the oneof groups of messages are exposed as unions in responses
+        if !input {
+            for oneof in oneofs(message) {
+                let members: Option<Vec<_>> = oneof
+                    .fields()
+                    .map(|field| match field.kind() {
+                        Kind::Message(message)
+                            if is_object(&message) && message.fields().len() > 0 =>
+                        {
+                            Some((field.json_name().to_string(), message))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let Some(members) = members else {
+                    continue;
+                };
+
+                let json_name = oneof_json_name(&oneof);
+                let union_name = format!("{}{}", name, upper_first(&json_name));
+                let mut types = BTreeSet::new();
+                for (field_name, message) in members {
+                    fields.remove(&field_name);
+                    types.insert(self.output_type(&message)?);
+                }
+                self.config
+                    .unions
+                    .insert(union_name.clone(), Union { types, doc: None });
+                fields.insert(json_name, Field::default().type_of(union_name));
+            }
+        }
+
+        self.config
+            .types
+            .insert(name.clone(), Type { fields, ..Default::default() });
+
+        Ok(name)
+    }
+
+    /// Maps the well-known types to the scalars matching their JSON
+    /// representation, the wrappers being nullable scalars.
+    fn well_known_type(&mut self, message: &MessageDescriptor) -> Option<String> {
+        let name = match message.full_name().strip_prefix("google.protobuf.")? {
+            "Timestamp" => self.scalar("DateTime"),
+            "Duration" => self.scalar("Duration"),
+            "Struct" | "Value" | "ListValue" | "Any" => self.json(),
+            "FieldMask" | "StringValue" | "BytesValue" => "String".to_string(),
+            // 64-bit integers are serialized as strings to keep their precision
+            "Int64Value" | "UInt64Value" => "String".to_string(),
+            "DoubleValue" | "FloatValue" => "Float".to_string(),
+            "Int32Value" | "UInt32Value" => "Int".to_string(),
+            "BoolValue" => "Boolean".to_string(),
+            _ => return None,
+        };
+
+        Some(name)
+    }
+
+    /// Declares the `JSON` scalar, used for maps and empty messages.
+    fn json(&mut self) -> String {
+        self.scalar("JSON")
+    }
+
+    fn scalar(&mut self, name: &str) -> String {
+        self.config
+            .types
+            .entry(name.to_string())
+            .or_insert_with(|| Type { scalar: true, ..Default::default() });
+
+        name.to_string()
+    }
+
+    fn add_enum(&mut self, descriptor: &EnumDescriptor) -> Result<String> {
+        let name = type_name(descriptor.full_name(), descriptor.package_name());
+        if !self.claim(&name, descriptor.full_name())? {
+            return Ok(name);
+        }
+        self.config.types.insert(
+            name.clone(),
+            Type {
+                variants: Some(
+                    descriptor
+                        .values()
+                        .map(|value| value.name().to_string())
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+        );
+
+        Ok(name)
+    }
+
+    fn field(&mut self, field: &FieldDescriptor, input: bool) -> Result<Field> {
+        let type_of = match field.kind() {
+            Kind::Message(_) if field.is_map() => self.json(),
+            Kind::Message(message) if input => self.input_type(&message)?,
+            Kind::Message(message) => self.output_type(&message)?,
+            Kind::Enum(descriptor) => self.add_enum(&descriptor)?,
+            Kind::Double | Kind::Float => "Float".to_string(),
+            Kind::Bool => "Boolean".to_string(),
+            Kind::String | Kind::Bytes => "String".to_string(),
+            Kind::Int64 | Kind::Uint64 | Kind::Sint64 | Kind::Fixed64 | Kind::Sfixed64 => {
+                "String".to_string()
+            }
+            _ => "Int".to_string(),
+        };
+
+        // repeated fields and maps are always sent, while the others are optional
+        let required = !input && field.cardinality() == prost_reflect::Cardinality::Repeated;
+        Ok(Field::default()
+            .type_of(type_of)
+            .list(field.is_list())
+            .required(required))
+    }
+}
+
+fn lower_first(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn upper_first(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::blueprint::Blueprint;
+    use crate::config::{ConfigModule, Content, Source};
+
+    fn descriptors() -> FileDescriptorSet {
+        protox::compile(["news.proto"], ["src/grpc/tests"]).unwrap()
+    }
+
+    #[test]
+    fn test_from_proto() {
+        let config = from_proto(descriptors(), "news.proto", "http://localhost:50051").unwrap();
+        let sdl = Source::GraphQL.encode(&config).unwrap();
+
+        assert_eq!(
+            sdl,
+            r#"schema @server @upstream(baseURL: "http://localhost:50051") @link(id: "news", src: "news.proto", type: Protobuf) {
+  query: Query
+  mutation: Mutation
+}
+
+scalar JSON
+
+input MultipleNewsIdInput {
+  ids: [NewsIdInput]
+}
+
+input NewsIdInput {
+  id: Int
+}
+
+input NewsInput {
+  body: String
+  id: Int
+  postImage: String
+  title: String
+}
+
+type Mutation {
+  addNews(news: NewsInput!): News! @grpc(body: "{{args.news}}", method: "news.NewsService.AddNews")
+  deleteNews(newsId: NewsIdInput!): JSON! @grpc(body: "{{args.newsId}}", method: "news.NewsService.DeleteNews")
+  editNews(news: NewsInput!): News! @grpc(body: "{{args.news}}", method: "news.NewsService.EditNews")
+}
+
+type News {
+  body: String
+  id: Int
+  postImage: String
+  title: String
+}
+
+type NewsList {
+  news: [News]!
+}
+
+type Query {
+  getAllNews: NewsList! @grpc(method: "news.NewsService.GetAllNews")
+  getMultipleNews(multipleNewsId: MultipleNewsIdInput!): NewsList! @grpc(body: "{{args.multipleNewsId}}", method: "news.NewsService.GetMultipleNews")
+  getNews(newsId: NewsIdInput!): News! @grpc(body: "{{args.newsId}}", method: "news.NewsService.GetNews")
+  streamNews: [News!]! @grpc(method: "news.NewsService.StreamNews")
+}"#
+        );
+    }
+
+    #[test]
+    fn test_from_proto_is_valid() {
+        let config = from_proto(descriptors(), "news.proto", "http://localhost:50051").unwrap();
+        let mut config_module = ConfigModule::from(config);
+        config_module
+            .extensions
+            .grpc_file_descriptors
+            .push(Content { id: Some("news".to_string()), content: descriptors() });
+
+        Blueprint::try_from(&config_module).unwrap();
+    }
+
+    #[test]
+    fn test_well_known_types() {
+        let descriptors = protox::compile(["well_known.proto"], ["src/grpc/tests"]).unwrap();
+        let config = from_proto(descriptors, "well_known.proto", "http://localhost:50051").unwrap();
+        let sdl = Source::GraphQL.encode(&config).unwrap();
+
+        assert_eq!(
+            sdl,
+            r#"schema @server @upstream(baseURL: "http://localhost:50051") @link(id: "events", src: "well_known.proto", type: Protobuf) {
+  query: Query
+}
+
+scalar DateTime
+
+scalar Duration
+
+scalar JSON
+
+input EventInput {
+  active: Boolean
+  createdAt: DateTime
+  detail: JSON
+  duration: Duration
+  id: Int
+  likes: String
+  mask: String
+  metadata: JSON
+  note: String
+  payload: JSON
+  priority: Int
+  views: String
+}
+
+type Event {
+  active: Boolean
+  createdAt: DateTime
+  detail: JSON
+  duration: Duration
+  id: Int
+  likes: String
+  mask: String
+  metadata: JSON
+  note: String
+  payload: JSON
+  priority: Int
+  views: String
+}
+
+type Query {
+  getCreatedAt(event: EventInput!): DateTime! @grpc(body: "{{args.event}}", method: "events.EventService.GetCreatedAt")
+  getEvent(event: EventInput!): Event! @grpc(body: "{{args.event}}", method: "events.EventService.GetEvent")
+}"#
+        );
+    }
+
+    #[test]
+    fn test_oneof() {
+        let descriptors = protox::compile(["oneof.proto"], ["src/grpc/tests"]).unwrap();
+        let config = from_proto(descriptors, "oneof.proto", "http://localhost:50051").unwrap();
+        let sdl = Source::GraphQL.encode(&config).unwrap();
+
+        assert_eq!(
+            sdl,
+            r#"schema @server @upstream(baseURL: "http://localhost:50051") @link(id: "posts", src: "oneof.proto", type: Protobuf) {
+  query: Query
+}
+
+input PostIdInput {
+  id: Int
+  status: Status
+}
+
+union PostContent = Image | Text
+
+enum Status {
+  DRAFT
+  PUBLISHED
+}
+
+type Image {
+  url: String
+  width: Int
+}
+
+type Post {
+  content: PostContent
+  id: Int
+  status: Status
+  title: String
+}
+
+type Query {
+  getPost(postId: PostIdInput!): Post! @grpc(body: "{{args.postId}}", method: "posts.PostService.GetPost")
+}
+
+type Text {
+  body: String
+}"#
+        );
+
+        let descriptors = protox::compile(["oneof.proto"], ["src/grpc/tests"]).unwrap();
+        let mut config_module = ConfigModule::from(config);
+        config_module
+            .extensions
+            .grpc_file_descriptors
+            .push(Content { id: Some("posts".to_string()), content: descriptors });
+        Blueprint::try_from(&config_module).unwrap();
+    }
+
+    #[test]
+    fn test_name_collision() {
+        let path = "src/grpc/tests/collision.proto";
+        let descriptors = protox::compile([path], ["."]).unwrap();
+        let error = from_proto(descriptors, path, "http://localhost:50051").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "authors.News and news.News would both be exposed as the type News"
+        );
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let error =
+            from_proto(descriptors(), "missing.proto", "http://localhost:50051").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "File missing.proto not found in the descriptors"
+        );
+    }
+}
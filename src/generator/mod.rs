@@ -0,0 +1,3 @@
+mod from_proto;
+
+pub use from_proto::from_proto;
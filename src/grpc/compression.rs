@@ -0,0 +1,77 @@
+use std::io::{Read, Write};
+
+use anyhow::{bail, Result};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use hyper::HeaderMap;
+
+use crate::config::GrpcCompression;
+
+/// The header holding the compression of the messages.
+pub const GRPC_ENCODING: &str = "grpc-encoding";
+
+/// The header listing the compressions accepted for the response messages.
+pub const GRPC_ACCEPT_ENCODING: &str = "grpc-accept-encoding";
+
+/// The compressions that can be decoded from the responses.
+pub const ACCEPTED_ENCODINGS: &str = "gzip, deflate";
+
+/// Returns the compression of the messages set in the `grpc-encoding` header.
+pub fn grpc_encoding(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(GRPC_ENCODING)
+        .and_then(|value| value.to_str().ok())
+}
+
+pub fn compress(compression: GrpcCompression, bytes: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        GrpcCompression::Identity => Ok(bytes.to_vec()),
+        GrpcCompression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes)?;
+            Ok(encoder.finish()?)
+        }
+        GrpcCompression::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+/// Decompresses a message with the compression named by `encoding`.
+pub fn decompress(encoding: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut message = Vec::new();
+    match encoding {
+        "gzip" => GzDecoder::new(bytes).read_to_end(&mut message)?,
+        "deflate" => ZlibDecoder::new(bytes).read_to_end(&mut message)?,
+        "identity" => bail!("Compressed gRPC message received with the identity encoding"),
+        encoding => bail!("Unsupported gRPC message encoding: {}", encoding),
+    };
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let message = b"message".repeat(10);
+        for compression in [GrpcCompression::Gzip, GrpcCompression::Deflate] {
+            let compressed = compress(compression, &message).unwrap();
+            assert!(compressed.len() < message.len());
+            assert_eq!(
+                decompress(compression.encoding(), &compressed).unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsupported_encoding() {
+        let error = decompress("zstd", b"message").unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported gRPC message encoding: zstd");
+    }
+}
@@ -10,6 +10,7 @@ use async_graphql_value::ConstValue;
 use super::data_loader_request::DataLoaderRequest;
 use super::protobuf::ProtobufOperation;
 use super::request::execute_grpc_request;
+use super::web::encode_body;
 use crate::config::group_by::GroupBy;
 use crate::config::Batch;
 use crate::data_loader::{DataLoader, Loader};
@@ -38,7 +39,10 @@ impl GrpcDataLoader {
     ) -> anyhow::Result<HashMap<DataLoaderRequest, Response<async_graphql::Value>>> {
         let results = keys.iter().map(|key| async {
             let result = match key.to_request() {
-                Ok(req) => execute_grpc_request(&self.runtime, &self.operation, req).await,
+                Ok(req) => {
+                    execute_grpc_request(&self.runtime, &self.operation, key.template.protocol, req)
+                        .await
+                }
                 Err(error) => Err(error),
             };
 
@@ -62,20 +66,25 @@ impl GrpcDataLoader {
         group_by: &GroupBy,
         keys: &[DataLoaderRequest],
     ) -> Result<HashMap<DataLoaderRequest, Response<async_graphql::Value>>> {
-        let inputs = keys.iter().map(|key| key.template.body.as_str());
-        let (multiple_body, grouped_keys) = self
-            .operation
-            .convert_multiple_inputs(inputs, group_by.key())?;
-
         let first_request = keys[0].clone();
-        let multiple_request = create_grpc_request(
+        let inputs = keys.iter().map(|key| key.template.body.as_str());
+        let (multiple_body, grouped_keys) = self.operation.convert_multiple_inputs(
+            inputs,
+            group_by.key(),
+            first_request.template.compression,
+        )?;
+
+        let protocol = first_request.template.protocol;
+        let mut multiple_request = create_grpc_request(
             first_request.template.url,
             first_request.template.headers,
-            multiple_body,
+            encode_body(protocol, multiple_body),
         );
+        *multiple_request.timeout_mut() = first_request.template.timeout;
 
         let response =
-            execute_grpc_request(&self.runtime, &self.operation, multiple_request).await?;
+            execute_grpc_request(&self.runtime, &self.operation, protocol, multiple_request)
+                .await?;
 
         let path = &group_by.path();
         let response_body = response.body.group_by(path);
@@ -62,7 +62,7 @@ mod tests {
 
     use super::DataLoaderRequest;
     use crate::config::reader::ConfigReader;
-    use crate::config::{Config, Field, Grpc, Link, LinkType, Type};
+    use crate::config::{Config, Field, Grpc, GrpcCompression, GrpcProtocol, Link, LinkType, Type};
     use crate::grpc::protobuf::{ProtobufOperation, ProtobufSet};
     use crate::grpc::request_template::RenderedRequestTemplate;
 
@@ -116,6 +116,9 @@ mod tests {
             headers: HeaderMap::new(),
             operation: get_protobuf_op().await,
             body: "{}".to_owned(),
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
         };
 
         let dl_req_1 = DataLoaderRequest::new(tmpl.clone(), batch_headers.clone());
@@ -135,6 +138,9 @@ mod tests {
             )]),
             operation: get_protobuf_op().await,
             body: "{}".to_owned(),
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
         };
         let tmpl_2 = tmpl_1.clone();
 
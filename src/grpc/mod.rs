@@ -1,8 +1,12 @@
+pub mod compression;
 pub mod data_loader;
 pub mod data_loader_request;
 pub mod protobuf;
+pub mod reflection;
 pub mod request;
 pub mod request_template;
+pub mod status;
+pub mod web;
 
 pub use data_loader_request::DataLoaderRequest;
 pub use request::execute_grpc_request;
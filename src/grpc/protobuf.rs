@@ -1,15 +1,23 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
 
 use anyhow::{anyhow, bail, Context, Result};
 use async_graphql::Value;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{StreamExt, TryStreamExt};
+use hyper::body::Bytes;
 use prost::bytes::BufMut;
 use prost::Message;
 use prost_reflect::prost_types::FileDescriptorSet;
 use prost_reflect::{
-    DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor, ServiceDescriptor,
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor,
+    OneofDescriptor, ReflectMessage, ServiceDescriptor,
 };
 use serde_json::Deserializer;
 
+use super::compression::{compress, decompress};
+use crate::config::GrpcCompression;
+
 fn to_message(descriptor: &MessageDescriptor, input: &str) -> Result<DynamicMessage> {
     let mut deserializer = Deserializer::from_str(input);
     let message =
@@ -24,18 +32,86 @@ fn to_message(descriptor: &MessageDescriptor, input: &str) -> Result<DynamicMess
     Ok(message)
 }
 
-fn message_to_bytes(message: DynamicMessage) -> Result<Vec<u8>> {
-    let mut buf: Vec<u8> = Vec::with_capacity(message.encoded_len() + 5);
+fn message_to_bytes(message: DynamicMessage, compression: GrpcCompression) -> Result<Vec<u8>> {
+    let (flag, bytes) = match compression {
+        GrpcCompression::Identity => (0, message.encode_to_vec()),
+        compression => (1, compress(compression, &message.encode_to_vec())?),
+    };
+    let mut buf: Vec<u8> = Vec::with_capacity(bytes.len() + 5);
     // set compression flag
-    buf.put_u8(0);
+    buf.put_u8(flag);
     // next 4 bytes should encode message length
-    buf.put_u32(message.encoded_len() as u32);
-    // encode the message itself
-    message.encode(&mut buf)?;
+    buf.put_u32(bytes.len() as u32);
+    // the message itself
+    buf.extend(bytes);
 
     Ok(buf)
 }
 
+/// Returns the length of the Length-Prefixed Message at the start of
+/// `bytes`, or `None` if it isn't complete yet.
+pub(crate) fn frame_length(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 5 {
+        return None;
+    }
+    let end = 5 + u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as usize;
+    (bytes.len() >= end).then_some(end)
+}
+
+/// Returns the message of a complete Length-Prefixed Message, decompressed
+/// with `encoding` if the compressed flag is set.
+fn frame_message<'a>(frame: &'a [u8], encoding: Option<&str>) -> Result<Cow<'a, [u8]>> {
+    let message = &frame[5..];
+    if frame[0] == 0 {
+        return Ok(Cow::Borrowed(message));
+    }
+    let encoding = encoding.context("Compressed gRPC message received without a grpc-encoding")?;
+    Ok(Cow::Owned(decompress(encoding, message)?))
+}
+
+/// Splits a body made of Length-Prefixed Messages into the messages. The
+/// compressed ones are decoded with `encoding`, the value of the
+/// `grpc-encoding` header.
+pub fn split_frames<'a>(mut bytes: &'a [u8], encoding: Option<&str>) -> Result<Vec<Cow<'a, [u8]>>> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        if bytes.len() < 5 {
+            bail!("Malformed gRPC message");
+        }
+        let end = frame_length(bytes).context("Truncated gRPC message")?;
+        frames.push(frame_message(&bytes[..end], encoding)?);
+        bytes = &bytes[end..];
+    }
+
+    Ok(frames)
+}
+
+/// Like [split_frames], but yields the messages as soon as they are received
+/// from a body that arrives in chunks.
+pub fn stream_frames(
+    body: BoxStream<'static, Result<Bytes>>,
+    encoding: Option<String>,
+) -> BoxStream<'static, Result<Vec<u8>>> {
+    stream::try_unfold((body, Vec::new()), move |(mut body, mut buffer)| {
+        let encoding = encoding.clone();
+        async move {
+            loop {
+                if let Some(end) = frame_length(&buffer) {
+                    let message = frame_message(&buffer[..end], encoding.as_deref())?.into_owned();
+                    buffer.drain(..end);
+                    return Ok(Some((message, (body, buffer))));
+                }
+                match body.try_next().await? {
+                    Some(chunk) => buffer.extend_from_slice(&chunk),
+                    None if buffer.is_empty() => return Ok(None),
+                    None => bail!("Truncated gRPC message"),
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
 pub fn protobuf_value_as_str(value: &prost_reflect::Value) -> String {
     use prost_reflect::Value;
 
@@ -57,19 +133,123 @@ pub fn get_field_value_as_str(message: &DynamicMessage, field_name: &str) -> Res
         .get_field_by_name(field_name)
         .ok_or(anyhow!("Unable to find key"))?;
 
+    // enums are sent in GraphQL with the names of their values
+    if let prost_reflect::Value::EnumNumber(number) = field.as_ref() {
+        let descriptor = message.descriptor().get_field_by_name(field_name);
+        if let Some(Kind::Enum(descriptor)) = descriptor.map(|field| field.kind()) {
+            if let Some(value) = descriptor.get_value(*number) {
+                return Ok(value.name().to_string());
+            }
+        }
+    }
+
     Ok(protobuf_value_as_str(&field))
 }
 
+/// The name of the GraphQL type of a message or an enum: its name without the
+/// package, nested definitions being prefixed with the names of their parents.
+pub fn type_name(full_name: &str, package: &str) -> String {
+    full_name
+        .strip_prefix(package)
+        .unwrap_or(full_name)
+        .split('.')
+        .collect()
+}
+
+/// The `oneof` groups declared in `message`, without the ones generated for
+/// proto3 `optional` fields.
+pub fn oneofs(message: &MessageDescriptor) -> impl Iterator<Item = OneofDescriptor> + '_ {
+    message.oneofs().filter(|oneof| {
+        !oneof
+            .fields()
+            .any(|field| field.field_descriptor_proto().proto3_optional())
+    })
+}
+
+/// The name of a `oneof` group in JSON, converted to lowerCamelCase like the
+/// names of the fields.
+pub fn oneof_json_name(oneof: &OneofDescriptor) -> String {
+    let mut name = String::new();
+    let mut upper = false;
+    for c in oneof.name().chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            name.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            name.push(c);
+        }
+    }
+
+    name
+}
+
+/// The messages that can be exposed as GraphQL objects, the well-known types
+/// having their own JSON representation.
+pub fn is_object(message: &MessageDescriptor) -> bool {
+    message.package_name() != "google.protobuf"
+}
+
+/// Adds the `oneof` groups of `message` that hold a message to its JSON
+/// representation, under the name of the group. The message is tagged with
+/// its `__typename` so that the group can be resolved as a GraphQL union.
+fn add_oneofs(message: &DynamicMessage, json: &mut serde_json::Value) {
+    let serde_json::Value::Object(object) = json else {
+        return;
+    };
+
+    for field in message.descriptor().fields() {
+        if !matches!(field.kind(), Kind::Message(_)) || field.is_map() {
+            continue;
+        }
+        let Some(json) = object.get_mut(field.json_name()) else {
+            continue;
+        };
+        match (message.get_field(&field).as_ref(), json) {
+            (prost_reflect::Value::Message(message), json) => add_oneofs(message, json),
+            (prost_reflect::Value::List(list), serde_json::Value::Array(items)) => {
+                for (item, json) in list.iter().zip(items.iter_mut()) {
+                    if let prost_reflect::Value::Message(message) = item {
+                        add_oneofs(message, json);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for oneof in oneofs(&message.descriptor()) {
+        let Some(field) = oneof.fields().find(|field| message.has_field(field)) else {
+            continue;
+        };
+        let Kind::Message(descriptor) = field.kind() else {
+            continue;
+        };
+        let Some(serde_json::Value::Object(member)) = object.get(field.json_name()) else {
+            continue;
+        };
+        if !is_object(&descriptor) {
+            continue;
+        }
+
+        let mut member = member.clone();
+        member.insert(
+            "__typename".to_string(),
+            type_name(descriptor.full_name(), descriptor.package_name()).into(),
+        );
+        object.insert(oneof_json_name(&oneof), serde_json::Value::Object(member));
+    }
+}
+
 #[derive(Debug)]
 pub struct ProtobufSet {
     descriptor_pool: DescriptorPool,
 }
 
-// TODO: support for reflection
 impl ProtobufSet {
-    // TODO: load definitions from proto file for now, but in future
-    // it could be more convenient to load FileDescriptorSet instead
-    // either from file or server reflection
+    /// Loads the definitions resolved from a `.proto` file or from the
+    /// reflection service of a gRPC server
     pub fn from_proto_file(file_descriptor_set: &FileDescriptorSet) -> Result<Self> {
         let descriptor_pool =
             DescriptorPool::from_file_descriptor_set(file_descriptor_set.clone())?;
@@ -102,7 +282,7 @@ impl ProtobufService {
         let input_type = method.input();
         let output_type = method.output();
 
-        Ok(ProtobufOperation { method, input_type, output_type })
+        Ok(ProtobufOperation { method, input_type, output_type, batch_field: None })
     }
 }
 
@@ -111,9 +291,10 @@ pub struct ProtobufOperation {
     method: MethodDescriptor,
     pub input_type: MessageDescriptor,
     pub output_type: MessageDescriptor,
+    /// The field of the input message holding the batched requests.
+    batch_field: Option<FieldDescriptor>,
 }
 
-// TODO: support compression
 impl ProtobufOperation {
     pub fn name(&self) -> &str {
         self.method.name()
@@ -123,23 +304,68 @@ impl ProtobufOperation {
         self.method.parent_service().name()
     }
 
-    pub fn convert_input(&self, input: &str) -> Result<Vec<u8>> {
+    /// The definitions the method was loaded from.
+    pub fn descriptor_pool(&self) -> &DescriptorPool {
+        self.method.parent_pool()
+    }
+
+    /// The server answers with a stream of messages.
+    pub fn is_server_streaming(&self) -> bool {
+        self.method.is_server_streaming()
+    }
+
+    /// The client sends a stream of messages.
+    pub fn is_client_streaming(&self) -> bool {
+        self.method.is_client_streaming()
+    }
+
+    /// Batches the requests in the field `key` of the input message, which has
+    /// to be a repeated message.
+    pub fn with_batch_key(self, key: &str) -> Result<Self> {
+        let field = self
+            .input_type
+            .get_field_by_name(key)
+            .or_else(|| self.input_type.get_field_by_json_name(key))
+            .with_context(|| {
+                format!(
+                    "Couldn't find field {} in {}",
+                    key,
+                    self.input_type.full_name()
+                )
+            })?;
+        if !field.is_list() || field.kind().as_message().is_none() {
+            bail!(
+                "Field {} of {} must be a repeated message to batch requests",
+                key,
+                self.input_type.full_name()
+            );
+        }
+
+        Ok(Self { batch_field: Some(field), ..self })
+    }
+
+    pub fn convert_input(&self, input: &str, compression: GrpcCompression) -> Result<Vec<u8>> {
         let message = to_message(&self.input_type, input)?;
 
-        message_to_bytes(message)
+        message_to_bytes(message, compression)
     }
 
     pub fn convert_multiple_inputs<'a>(
         &self,
         child_inputs: impl Iterator<Item = &'a str>,
         id: &str,
+        compression: GrpcCompression,
     ) -> Result<(Vec<u8>, Vec<String>)> {
-        // Find the field of list type that should hold child messages
-        let field_descriptor = self
-            .input_type
-            .fields()
-            .find(|field| field.is_list())
-            .ok_or(anyhow!("Unable to find list field on type"))?;
+        // Find the field of list type that should hold child messages, unless
+        // it's set by the batch key
+        let field_descriptor = match &self.batch_field {
+            Some(field) => field.clone(),
+            None => self
+                .input_type
+                .fields()
+                .find(|field| field.is_list())
+                .ok_or(anyhow!("Unable to find list field on type"))?,
+        };
 
         let field_kind = field_descriptor.kind();
         let child_message_descriptor = field_kind
@@ -166,26 +392,38 @@ impl ProtobufOperation {
             ),
         );
 
-        message_to_bytes(message).map(|result| (result, ids))
+        message_to_bytes(message, compression).map(|result| (result, ids))
     }
 
-    pub fn convert_output(&self, bytes: &[u8]) -> Result<Value> {
-        if bytes.len() < 5 {
-            bail!("Empty response");
-        }
-        // ignore 5 first bytes as they are part of Length-Prefixed Message Framing
+    /// Decodes the messages of the response, decompressing them with
+    /// `encoding` if needed. The messages of server-streaming methods are
+    /// collected into a list.
+    pub fn convert_output(&self, bytes: &[u8], encoding: Option<&str>) -> Result<Value> {
         // see https://www.oreilly.com/library/view/grpc-up-and/9781492058328/ch04.html#:~:text=Length%2DPrefixed%20Message%20Framing
-        // 1st byte - compression flag
-        // 2-4th bytes - length of the message
+        let frames = split_frames(bytes, encoding)?;
+        if self.is_server_streaming() {
+            return frames
+                .iter()
+                .map(|frame| self.convert_message(frame))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::List);
+        }
+
+        self.convert_message(frames.first().context("Empty response")?)
+    }
+
+    /// Decodes a single message, without its Length-Prefixed Message framing.
+    pub fn convert_message(&self, message: &[u8]) -> Result<Value> {
         let message =
-            DynamicMessage::decode(self.output_type.clone(), &bytes[5..]).with_context(|| {
+            DynamicMessage::decode(self.output_type.clone(), message).with_context(|| {
                 format!(
                     "Failed to parse response for type {}",
                     self.output_type.full_name()
                 )
             })?;
 
-        let json = serde_json::to_value(message)?;
+        let mut json = serde_json::to_value(&message)?;
+        add_oneofs(&message, &mut json);
 
         Ok(async_graphql::Value::from_json(json)?)
     }
@@ -325,7 +563,7 @@ mod tests {
 
         let output = b"\0\0\0\0\x0e\n\x0ctest message";
 
-        let parsed = operation.convert_output(output)?;
+        let parsed = operation.convert_output(output, None)?;
 
         assert_eq!(
             serde_json::to_value(parsed)?,
@@ -343,13 +581,13 @@ mod tests {
         let service = file.find_service("news.NewsService")?;
         let operation = service.find_operation("GetNews")?;
 
-        let input = operation.convert_input(r#"{ "id": 1 }"#)?;
+        let input = operation.convert_input(r#"{ "id": 1 }"#, GrpcCompression::Identity)?;
 
         assert_eq!(input, b"\0\0\0\0\x02\x08\x01");
 
-        let output = b"\0\0\0\x005\x08\x01\x12\x06Note 1\x1a\tContent 1\"\x0cPost image 1";
+        let output = b"\0\0\0\0#\x08\x01\x12\x06Note 1\x1a\tContent 1\"\x0cPost image 1";
 
-        let parsed = operation.convert_output(output)?;
+        let parsed = operation.convert_output(output, None)?;
 
         assert_eq!(
             serde_json::to_value(parsed)?,
@@ -361,6 +599,186 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn news_proto_file_compressed() -> Result<()> {
+        let file = ProtobufSet::from_proto_file(&get_proto_file("news.proto").await?)?;
+        let service = file.find_service("news.NewsService")?;
+        let operation = service.find_operation("GetNews")?;
+
+        let input = operation.convert_input(r#"{ "id": 1 }"#, GrpcCompression::Gzip)?;
+
+        assert_eq!(input[0], 1);
+        let frames = split_frames(&input, Some("gzip"))?;
+        assert_eq!(frames, vec![b"\x08\x01".as_slice()]);
+
+        let message = b"\x08\x01\x12\x06Note 1";
+        let compressed = compress(GrpcCompression::Deflate, message)?;
+        let mut output = vec![1];
+        output.extend((compressed.len() as u32).to_be_bytes());
+        output.extend(compressed);
+
+        let parsed = operation.convert_output(&output, Some("deflate"))?;
+        assert_eq!(
+            serde_json::to_value(parsed)?,
+            json!({ "id": 1, "title": "Note 1" })
+        );
+
+        let error = operation.convert_output(&output, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Compressed gRPC message received without a grpc-encoding"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_frames_across_chunks() -> Result<()> {
+        let body = b"\0\0\0\0\x02\x08\x01\0\0\0\0\x02\x08\x02";
+        let chunks = [&body[..3], &body[3..9], &body[9..]]
+            .into_iter()
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+
+        let messages: Vec<_> = stream_frames(stream::iter(chunks).boxed(), None)
+            .try_collect()
+            .await?;
+        assert_eq!(messages, vec![b"\x08\x01".to_vec(), b"\x08\x02".to_vec()]);
+
+        let truncated = stream::iter([Ok(Bytes::from_static(b"\0\0\0\0\x02\x08"))]).boxed();
+        let error = stream_frames(truncated, None)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Truncated gRPC message");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn news_proto_file_server_streaming() -> Result<()> {
+        let file = ProtobufSet::from_proto_file(&get_proto_file("news.proto").await?)?;
+        let service = file.find_service("news.NewsService")?;
+        let operation = service.find_operation("StreamNews")?;
+        assert!(operation.is_server_streaming());
+        assert!(!operation.is_client_streaming());
+
+        let output = b"\0\0\0\0\x02\x08\x01\0\0\0\0\x02\x08\x02";
+        let parsed = operation.convert_output(output, None)?;
+
+        assert_eq!(
+            serde_json::to_value(parsed)?,
+            json!([{ "id": 1 }, { "id": 2 }])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn well_known_types() -> Result<()> {
+        let file = ProtobufSet::from_proto_file(&get_proto_file("well_known.proto").await?)?;
+        let service = file.find_service("events.EventService")?;
+        let operation = service.find_operation("GetEvent")?;
+
+        let event = json!({
+            "id": 1,
+            "createdAt": "2024-01-02T03:04:05Z",
+            "duration": "1.500s",
+            "note": "hello",
+            "priority": 3,
+            "active": false,
+            "metadata": { "tags": ["a", 1.0] },
+            "payload": "text",
+            "mask": "id,createdAt",
+            "detail": { "@type": "type.googleapis.com/events.Event", "id": 2 },
+            "views": "9007199254740993",
+            "likes": "-9007199254740993"
+        });
+        let input = operation.convert_input(&event.to_string(), GrpcCompression::Identity)?;
+        let output = operation.convert_output(&input, None)?;
+        assert_eq!(serde_json::to_value(output)?, event);
+
+        // unset wrappers are null rather than their default value
+        let input =
+            operation.convert_input(r#"{ "id": 1, "note": null }"#, GrpcCompression::Identity)?;
+        let output = operation.convert_output(&input, None)?;
+        assert_eq!(serde_json::to_value(output)?, json!({ "id": 1 }));
+
+        let operation = service.find_operation("GetCreatedAt")?;
+        let output = operation.convert_output(b"\0\0\0\0\x02\x08\x01", None)?;
+        assert_eq!(serde_json::to_value(output)?, json!("1970-01-01T00:00:01Z"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn oneof_and_enums() -> Result<()> {
+        let file = ProtobufSet::from_proto_file(&get_proto_file("oneof.proto").await?)?;
+        let service = file.find_service("posts.PostService")?;
+        let operation = service.find_operation("GetPost")?;
+
+        // the oneof group holds the message set in it, tagged with its type
+        let post = to_message(
+            &operation.output_type,
+            r#"{ "id": 1, "status": "PUBLISHED", "image": { "url": "a.png" }, "title": "A" }"#,
+        )?;
+        let output = message_to_bytes(post, GrpcCompression::Identity)?;
+        let output = operation.convert_output(&output, None)?;
+        assert_eq!(
+            serde_json::to_value(output)?,
+            json!({
+                "id": 1,
+                "status": "PUBLISHED",
+                "image": { "url": "a.png" },
+                "content": { "url": "a.png", "__typename": "Image" },
+                "title": "A"
+            })
+        );
+
+        // batched by the names of the enum values
+        let message = to_message(&operation.input_type, r#"{ "status": "PUBLISHED" }"#)?;
+        assert_eq!(get_field_value_as_str(&message, "status")?, "PUBLISHED");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batch_key() -> Result<()> {
+        let file = ProtobufSet::from_proto_file(&get_proto_file("news.proto").await?)?;
+        let service = file.find_service("news.NewsService")?;
+
+        let operation = service
+            .find_operation("GetMultipleNews")?
+            .with_batch_key("ids")?;
+        let (message, ids) = operation.convert_multiple_inputs(
+            [r#"{ "id": 3 }"#, r#"{ "id": 5 }"#].into_iter(),
+            "id",
+            GrpcCompression::Identity,
+        )?;
+        assert_eq!(message, b"\0\0\0\0\x08\n\x02\x08\x03\n\x02\x08\x05");
+        assert_eq!(ids, vec!["3".to_owned(), "5".to_owned()]);
+
+        let error = service
+            .find_operation("GetMultipleNews")?
+            .with_batch_key("news")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Couldn't find field news in news.MultipleNewsId"
+        );
+
+        let error = service
+            .find_operation("GetNews")?
+            .with_batch_key("id")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Field id of news.NewsId must be a repeated message to batch requests"
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn news_proto_file_multiple_messages() -> Result<()> {
         let file = ProtobufSet::from_proto_file(&get_proto_file("news.proto").await?)?;
@@ -369,8 +787,11 @@ mod tests {
 
         let child_messages = vec![r#"{ "id": 3 }"#, r#"{ "id": 5 }"#, r#"{ "id": 1 }"#];
 
-        let (multiple_message, grouped) =
-            multiple_operation.convert_multiple_inputs(child_messages.into_iter(), "id")?;
+        let (multiple_message, grouped) = multiple_operation.convert_multiple_inputs(
+            child_messages.into_iter(),
+            "id",
+            GrpcCompression::Identity,
+        )?;
 
         assert_eq!(
             multiple_message,
@@ -383,7 +804,7 @@ mod tests {
 
         let output = b"\0\0\0\0o\n#\x08\x01\x12\x06Note 1\x1a\tContent 1\"\x0cPost image 1\n#\x08\x03\x12\x06Note 3\x1a\tContent 3\"\x0cPost image 3\n#\x08\x05\x12\x06Note 5\x1a\tContent 5\"\x0cPost image 5";
 
-        let parsed = multiple_operation.convert_output(output)?;
+        let parsed = multiple_operation.convert_output(output, None)?;
 
         assert_eq!(
             serde_json::to_value(parsed)?,
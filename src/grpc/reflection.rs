@@ -0,0 +1,373 @@
+// the code generated for the `prost::Oneof` messages takes many arguments
+#![allow(clippy::too_many_arguments)]
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Context, Result};
+use hyper::header::{CONTENT_TYPE, TE};
+use hyper::HeaderMap;
+use indexmap::IndexMap;
+use prost::Message;
+use prost_reflect::prost_types::{FileDescriptorProto, FileDescriptorSet};
+use prost_reflect::DescriptorPool;
+use reqwest::header::HeaderValue;
+use url::Url;
+
+use super::compression::grpc_encoding;
+use super::protobuf::split_frames;
+use super::request::create_grpc_request;
+use super::status::GrpcStatus;
+use crate::runtime::TargetRuntime;
+
+/// The versions of the reflection service, tried in order.
+const REFLECTION_SERVICES: [&str; 2] = [
+    "grpc.reflection.v1.ServerReflection",
+    "grpc.reflection.v1alpha.ServerReflection",
+];
+
+// Messages of `grpc/reflection/v1/reflection.proto`, which are the same in
+// v1alpha. Only the requests and responses used to fetch the files are kept.
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionRequest {
+    #[prost(string, tag = "1")]
+    host: String,
+    #[prost(oneof = "MessageRequest", tags = "3, 4, 7")]
+    message_request: Option<MessageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageRequest {
+    #[prost(string, tag = "3")]
+    FileByFilename(String),
+    #[prost(string, tag = "4")]
+    FileContainingSymbol(String),
+    #[prost(string, tag = "7")]
+    ListServices(String),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionResponse {
+    #[prost(oneof = "MessageResponse", tags = "4, 6, 7")]
+    message_response: Option<MessageResponse>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageResponse {
+    #[prost(message, tag = "4")]
+    FileDescriptor(FileDescriptorResponse),
+    #[prost(message, tag = "6")]
+    ListServices(ListServiceResponse),
+    #[prost(message, tag = "7")]
+    Error(ErrorResponse),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorResponse {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    file_descriptor_proto: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ListServiceResponse {
+    #[prost(message, repeated, tag = "1")]
+    service: Vec<ServiceResponse>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceResponse {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ErrorResponse {
+    #[prost(int32, tag = "1")]
+    error_code: i32,
+    #[prost(string, tag = "2")]
+    error_message: String,
+}
+
+/// Client of the gRPC server reflection service of a single server.
+struct ReflectionClient<'a> {
+    runtime: &'a TargetRuntime,
+    url: Url,
+}
+
+impl<'a> ReflectionClient<'a> {
+    /// Sends the requests as a single stream and returns the responses.
+    async fn execute(&self, requests: Vec<MessageRequest>) -> Result<Vec<MessageResponse>> {
+        let mut body = Vec::new();
+        for message_request in requests {
+            let request = ServerReflectionRequest {
+                host: String::new(),
+                message_request: Some(message_request),
+            };
+            body.push(0);
+            body.extend((request.encoded_len() as u32).to_be_bytes());
+            request.encode(&mut body)?;
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        headers.insert(TE, HeaderValue::from_static("trailers"));
+        let request = create_grpc_request(self.url.clone(), headers, body);
+        let response = self.runtime.http2_only.execute(request).await?;
+        // servers without the service answer with an UNIMPLEMENTED status
+        if let Some(status) = GrpcStatus::from_headers(&response.headers, &DescriptorPool::new())? {
+            return Err(status.into());
+        }
+
+        split_frames(&response.body, grpc_encoding(&response.headers))?
+            .into_iter()
+            .map(
+                |frame| match ServerReflectionResponse::decode(frame.as_ref())?.message_response {
+                    Some(MessageResponse::Error(error)) => bail!(
+                        "Server reflection failed with code {}: {}",
+                        error.error_code,
+                        error.error_message
+                    ),
+                    Some(response) => Ok(response),
+                    None => bail!("Empty server reflection response"),
+                },
+            )
+            .collect()
+    }
+
+    async fn list_services(&self) -> Result<Vec<String>> {
+        let mut services = Vec::new();
+        for response in self
+            .execute(vec![MessageRequest::ListServices(String::new())])
+            .await?
+        {
+            if let MessageResponse::ListServices(response) = response {
+                services.extend(response.service.into_iter().map(|service| service.name));
+            }
+        }
+        if services.is_empty() {
+            bail!("No services listed");
+        }
+        Ok(services)
+    }
+
+    async fn files(&self, requests: Vec<MessageRequest>) -> Result<Vec<FileDescriptorProto>> {
+        let mut files = Vec::new();
+        for response in self.execute(requests).await? {
+            if let MessageResponse::FileDescriptor(response) = response {
+                for file in response.file_descriptor_proto {
+                    files.push(FileDescriptorProto::decode(file.as_slice())?);
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Builds the [FileDescriptorSet] of every service exposed by the server at
+/// `src`, along with their dependencies, using its reflection service.
+pub async fn resolve_descriptors(runtime: &TargetRuntime, src: &str) -> Result<FileDescriptorSet> {
+    let mut errors = Vec::new();
+    for service in REFLECTION_SERVICES {
+        let url = Url::parse(&format!(
+            "{}/{}/ServerReflectionInfo",
+            src.trim_end_matches('/'),
+            service
+        ))?;
+        match resolve_with(ReflectionClient { runtime, url }).await {
+            Ok(file_descriptor_set) => return Ok(file_descriptor_set),
+            Err(error) => errors.push(format!("{}: {}", service, error)),
+        }
+    }
+
+    Err(anyhow!(errors.join(", "))).with_context(|| format!("Server reflection failed for {}", src))
+}
+
+async fn resolve_with(client: ReflectionClient<'_>) -> Result<FileDescriptorSet> {
+    let symbols = client
+        .list_services()
+        .await?
+        .into_iter()
+        .filter(|service| !service.starts_with("grpc.reflection."))
+        .map(MessageRequest::FileContainingSymbol)
+        .collect::<Vec<_>>();
+
+    let mut files: IndexMap<String, FileDescriptorProto> = IndexMap::new();
+    let mut requests = symbols;
+    let mut requested = HashSet::new();
+    while !requests.is_empty() {
+        for file in client.files(requests).await? {
+            files.entry(file.name().to_string()).or_insert(file);
+        }
+
+        // servers usually send the dependencies along, but they don't have to
+        requests = files
+            .values()
+            .flat_map(|file| file.dependency.iter())
+            .filter(|dependency| !files.contains_key(*dependency))
+            .filter(|dependency| requested.insert(dependency.to_string()))
+            .map(|dependency| MessageRequest::FileByFilename(dependency.clone()))
+            .collect();
+    }
+
+    Ok(FileDescriptorSet { file: files.into_values().collect() })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hyper::body::Bytes;
+
+    use super::*;
+    use crate::http::Response;
+    use crate::HttpIO;
+
+    /// Serves the v1alpha reflection service, without sending dependencies
+    /// along with the requested files.
+    struct ReflectionServer {
+        files: FileDescriptorSet,
+    }
+
+    impl ReflectionServer {
+        fn file(&self, name: &str) -> Vec<u8> {
+            let file = self.files.file.iter().find(|file| file.name() == name);
+            file.unwrap().encode_to_vec()
+        }
+
+        fn respond(&self, request: MessageRequest) -> MessageResponse {
+            let file = match request {
+                MessageRequest::ListServices(_) => {
+                    let service = [
+                        "news.NewsService",
+                        "grpc.reflection.v1alpha.ServerReflection",
+                    ]
+                    .map(|name| ServiceResponse { name: name.to_string() })
+                    .to_vec();
+                    return MessageResponse::ListServices(ListServiceResponse { service });
+                }
+                MessageRequest::FileContainingSymbol(symbol) => {
+                    assert_eq!(symbol, "news.NewsService");
+                    self.file("news.proto")
+                }
+                MessageRequest::FileByFilename(name) => self.file(&name),
+            };
+            MessageResponse::FileDescriptor(FileDescriptorResponse {
+                file_descriptor_proto: vec![file],
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for ReflectionServer {
+        async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+            // gRPC servers answer unknown services with UNIMPLEMENTED
+            if !request.url().path().contains("v1alpha") {
+                let mut response = Response::empty();
+                response
+                    .headers
+                    .insert("grpc-status", HeaderValue::from_static("12"));
+                return Ok(response);
+            }
+
+            let body = request.body().and_then(|body| body.as_bytes()).unwrap();
+            let mut response = Vec::new();
+            for frame in split_frames(body, None)? {
+                let request = ServerReflectionRequest::decode(frame.as_ref())?;
+                let message_response = request.message_request.map(|request| self.respond(request));
+                let message = ServerReflectionResponse { message_response }.encode_to_vec();
+                response.push(0);
+                response.extend((message.len() as u32).to_be_bytes());
+                response.extend(message);
+            }
+            Ok(Response::empty().body(Bytes::from(response)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_descriptors() {
+        let files = protox::compile(["news.proto"], ["src/grpc/tests"]).unwrap();
+        let mut runtime = crate::runtime::test::init(None);
+        runtime.http2_only = Arc::new(ReflectionServer { files });
+
+        let file_descriptor_set = resolve_descriptors(&runtime, "http://localhost:50051/")
+            .await
+            .unwrap();
+
+        let names: Vec<&str> = file_descriptor_set
+            .file
+            .iter()
+            .map(|file| file.name())
+            .collect();
+        assert_eq!(names, vec!["news.proto", "google/protobuf/empty.proto"]);
+        let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set).unwrap();
+        assert!(pool.get_service_by_name("news.NewsService").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reflection_unavailable() {
+        let mut runtime = crate::runtime::test::init(None);
+        runtime.http2_only = Arc::new(Unavailable);
+
+        let error = resolve_descriptors(&runtime, "http://localhost:50051")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Server reflection failed for http://localhost:50051"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_services() {
+        let mut runtime = crate::runtime::test::init(None);
+        runtime.http2_only = Arc::new(NoServices);
+
+        let error = resolve_descriptors(&runtime, "http://localhost:50051")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Server reflection failed for http://localhost:50051: \
+             grpc.reflection.v1.ServerReflection: gRPC status UNIMPLEMENTED: , \
+             grpc.reflection.v1alpha.ServerReflection: No services listed"
+        );
+    }
+
+    /// Serves only the v1alpha reflection service, which lists no services.
+    struct NoServices;
+
+    #[async_trait::async_trait]
+    impl HttpIO for NoServices {
+        async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+            let status = if request.url().path().contains("v1alpha") {
+                "0"
+            } else {
+                "12"
+            };
+            let mut response = Response::empty();
+            response
+                .headers
+                .insert("grpc-status", HeaderValue::from_static(status));
+            Ok(response)
+        }
+    }
+
+    struct Unavailable;
+
+    #[async_trait::async_trait]
+    impl HttpIO for Unavailable {
+        async fn execute(&self, _: reqwest::Request) -> Result<Response<Bytes>> {
+            bail!("Connection refused")
+        }
+    }
+
+    #[test]
+    fn test_split_frames() {
+        assert_eq!(
+            split_frames(b"\0\0\0\0\x01a\0\0\0\0\0", None).unwrap(),
+            vec![b"a".as_slice(), b"".as_slice()]
+        );
+        assert!(split_frames(b"\0\0\0\0\x02a", None).is_err());
+        assert!(split_frames(b"\x01\0\0\0\0", None).is_err());
+    }
+}
@@ -1,10 +1,17 @@
-use anyhow::{bail, Result};
+use anyhow::Result;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{future, StreamExt, TryStreamExt};
+use hyper::body::Bytes;
 use hyper::{HeaderMap, Method};
 use reqwest::Request;
 use url::Url;
 
-use super::protobuf::ProtobufOperation;
-use crate::http::Response;
+use super::compression::grpc_encoding;
+use super::protobuf::{stream_frames, ProtobufOperation};
+use super::status::GrpcStatus;
+use super::web::decode_response;
+use crate::config::GrpcProtocol;
+use crate::http::{Response, StatusError, StreamResponse};
 use crate::runtime::TargetRuntime;
 
 pub fn create_grpc_request(url: Url, headers: HeaderMap, body: Vec<u8>) -> Request {
@@ -15,16 +22,83 @@ pub fn create_grpc_request(url: Url, headers: HeaderMap, body: Vec<u8>) -> Reque
     req
 }
 
+/// Sends the request over HTTP/2, or over the regular HTTP client for
+/// gRPC-Web. The status of the call is read from the headers, where clients
+/// add the trailers of whole responses.
+async fn execute(
+    runtime: &TargetRuntime,
+    protocol: GrpcProtocol,
+    request: Request,
+) -> Result<Response<Bytes>> {
+    if protocol.is_web() {
+        decode_response(protocol, runtime.http.execute(request).await?)
+    } else {
+        runtime.http2_only.execute(request).await
+    }
+}
+
 pub async fn execute_grpc_request(
     runtime: &TargetRuntime,
     operation: &ProtobufOperation,
+    protocol: GrpcProtocol,
     request: Request,
 ) -> Result<Response<async_graphql::Value>> {
-    let response = runtime.http2_only.execute(request).await?;
+    let url = request.url().to_string();
+    let response = execute(runtime, protocol, request).await?;
+    if let Some(status) = GrpcStatus::from_headers(&response.headers, operation.descriptor_pool())?
+    {
+        return Err(status.into());
+    }
+
+    if !response.status.is_success() {
+        return Err(StatusError { status: response.status, url, body: response.body }.into());
+    }
 
-    if response.status.is_success() {
-        return response.to_grpc_value(operation);
+    response.to_grpc_value(operation)
+}
+
+/// Executes a server-streaming request and decodes the messages as they
+/// arrive. With gRPC-Web the whole body is read before the messages are
+/// decoded, as the status is only known from the trailers at its end. With
+/// HTTP/2 an error status sent in the trailers ends the stream with an error.
+pub async fn execute_grpc_stream(
+    runtime: &TargetRuntime,
+    operation: &ProtobufOperation,
+    protocol: GrpcProtocol,
+    request: Request,
+) -> Result<BoxStream<'static, Result<async_graphql::Value>>> {
+    let url = request.url().to_string();
+    let response = if protocol.is_web() {
+        StreamResponse::from(execute(runtime, protocol, request).await?)
+    } else {
+        runtime.http2_only.execute_stream(request).await?
+    };
+    if let Some(status) = GrpcStatus::from_headers(&response.headers, operation.descriptor_pool())?
+    {
+        return Err(status.into());
+    }
+
+    if !response.status.is_success() {
+        let body: Vec<Bytes> = response.body.try_collect().await?;
+        let body = Bytes::from(body.concat());
+        return Err(StatusError { status: response.status, url, body }.into());
     }
 
-    bail!("Failed to execute request")
+    let operation = operation.clone();
+    let pool = operation.descriptor_pool().clone();
+    let encoding = grpc_encoding(&response.headers).map(str::to_string);
+    let headers = response.headers;
+    let trailers = response.trailers;
+    let status = stream::once(async move {
+        match GrpcStatus::from_trailers(&headers, trailers.await.as_ref(), &pool)? {
+            Some(status) => Err(status.into()),
+            None => Ok(()),
+        }
+    })
+    .filter_map(|result: Result<()>| future::ready(result.err().map(Err)));
+
+    Ok(stream_frames(response.body, encoding)
+        .map(move |message| operation.convert_message(&message?))
+        .chain(status)
+        .boxed())
 }
@@ -1,15 +1,21 @@
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::time::Duration;
 
 use anyhow::Result;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD_NO_PAD};
+use base64::engine::DecodePaddingMode;
+use base64::{alphabet, Engine};
 use derive_setters::Setters;
 use hyper::header::CONTENT_TYPE;
 use hyper::{HeaderMap, Method};
 use reqwest::header::HeaderValue;
 use url::Url;
 
+use super::compression::{ACCEPTED_ENCODINGS, GRPC_ACCEPT_ENCODING, GRPC_ENCODING};
 use super::request::create_grpc_request;
-use crate::config::GraphQLOperationType;
+use super::web::{encode_body, X_GRPC_WEB};
+use crate::config::{GraphQLOperationType, GrpcCompression, GrpcProtocol};
 use crate::grpc::protobuf::ProtobufOperation;
 use crate::has_headers::HasHeaders;
 use crate::helpers::headers::MustacheHeaders;
@@ -17,8 +23,6 @@ use crate::lambda::CacheKey;
 use crate::mustache::Mustache;
 use crate::path::PathString;
 
-static GRPC_MIME_TYPE: HeaderValue = HeaderValue::from_static("application/grpc");
-
 #[derive(Setters, Debug, Clone)]
 pub struct RequestTemplate {
     pub url: Mustache,
@@ -26,6 +30,9 @@ pub struct RequestTemplate {
     pub body: Option<Mustache>,
     pub operation: ProtobufOperation,
     pub operation_type: GraphQLOperationType,
+    pub compression: GrpcCompression,
+    pub protocol: GrpcProtocol,
+    pub timeout: Option<Duration>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -34,6 +41,41 @@ pub struct RenderedRequestTemplate {
     pub headers: HeaderMap,
     pub body: String,
     pub operation: ProtobufOperation,
+    pub compression: GrpcCompression,
+    pub protocol: GrpcProtocol,
+    pub timeout: Option<Duration>,
+}
+
+/// The header telling the service the deadline of the call.
+const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/// Formats a timeout as the value of `grpc-timeout`: an integer of at most 8
+/// digits followed by its unit.
+fn to_grpc_timeout(timeout: Duration) -> String {
+    let millis = timeout.as_millis();
+    if millis % 1000 != 0 && millis < 100_000_000 {
+        format!("{}m", millis)
+    } else if timeout.as_secs() < 100_000_000 {
+        format!("{}S", timeout.as_secs())
+    } else {
+        format!("{}H", timeout.as_secs() / 3600)
+    }
+}
+
+/// Reads the values of binary metadata, configured in base64 with or without
+/// padding.
+const BINARY_METADATA: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Normalises the base64 value of a binary `-bin` metadata to the unpadded
+/// form sent to the service, or returns `None` when it isn't base64.
+pub fn to_binary_metadata(value: &str) -> Option<String> {
+    BINARY_METADATA
+        .decode(value)
+        .ok()
+        .map(|bytes| STANDARD_NO_PAD.encode(bytes))
 }
 
 impl Hash for RenderedRequestTemplate {
@@ -53,10 +95,40 @@ impl RequestTemplate {
     fn create_headers<C: PathString>(&self, ctx: &C) -> HeaderMap {
         let mut header_map = HeaderMap::new();
 
-        header_map.insert(CONTENT_TYPE, GRPC_MIME_TYPE.to_owned());
+        header_map.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(self.protocol.content_type()),
+        );
+        if self.protocol.is_web() {
+            header_map.insert(X_GRPC_WEB, HeaderValue::from_static("1"));
+        }
+        if let Some(timeout) = self.timeout {
+            if let Ok(value) = HeaderValue::from_str(&to_grpc_timeout(timeout)) {
+                header_map.insert(GRPC_TIMEOUT, value);
+            }
+        }
+        if self.compression != GrpcCompression::Identity {
+            header_map.insert(
+                GRPC_ENCODING,
+                HeaderValue::from_static(self.compression.encoding()),
+            );
+            header_map.insert(
+                GRPC_ACCEPT_ENCODING,
+                HeaderValue::from_static(ACCEPTED_ENCODINGS),
+            );
+        }
 
         for (k, v) in &self.headers {
-            if let Ok(header_value) = HeaderValue::from_str(&v.render(ctx)) {
+            let value = v.render(ctx);
+            let value = if k.as_str().ends_with("-bin") {
+                match to_binary_metadata(&value) {
+                    Some(value) => value,
+                    None => continue,
+                }
+            } else {
+                value
+            };
+            if let Ok(header_value) = HeaderValue::from_str(&value) {
                 header_map.insert(k, header_value);
             }
         }
@@ -68,7 +140,15 @@ impl RequestTemplate {
         let url = self.create_url(ctx)?;
         let headers = self.render_headers(ctx);
         let body = self.render_body(ctx);
-        Ok(RenderedRequestTemplate { url, headers, body, operation: self.operation.clone() })
+        Ok(RenderedRequestTemplate {
+            url,
+            headers,
+            body,
+            operation: self.operation.clone(),
+            compression: self.compression,
+            protocol: self.protocol,
+            timeout: self.timeout,
+        })
     }
 
     fn render_body<C: PathString + HasHeaders>(&self, ctx: &C) -> String {
@@ -98,11 +178,22 @@ impl RenderedRequestTemplate {
         let mut req = reqwest::Request::new(Method::POST, self.url.clone());
         req.headers_mut().extend(self.headers.clone());
 
-        Ok(create_grpc_request(
+        let mut req = create_grpc_request(
             self.url.clone(),
             self.headers.clone(),
-            self.operation.convert_input(self.body.as_str())?,
-        ))
+            encode_body(
+                self.protocol,
+                self.operation
+                    .convert_input(self.body.as_str(), self.compression)?,
+            ),
+        );
+        // The deadline of a server stream is left to the service, so a
+        // subscription isn't cut off by the client while messages arrive.
+        if !self.operation.is_server_streaming() {
+            *req.timeout_mut() = self.timeout;
+        }
+
+        Ok(req)
     }
 }
 
@@ -120,26 +211,34 @@ mod tests {
     use std::borrow::Cow;
     use std::collections::HashSet;
     use std::path::PathBuf;
+    use std::time::Duration;
 
     use derive_setters::Setters;
     use hyper::header::{HeaderName, HeaderValue};
     use hyper::{HeaderMap, Method};
     use pretty_assertions::assert_eq;
 
-    use super::RequestTemplate;
+    use super::{to_binary_metadata, to_grpc_timeout, RequestTemplate};
     use crate::config::reader::ConfigReader;
-    use crate::config::{Config, Field, GraphQLOperationType, Grpc, Link, LinkType, Type};
+    use crate::config::{
+        Config, Field, GraphQLOperationType, Grpc, GrpcCompression, GrpcProtocol, Link, LinkType,
+        Type,
+    };
     use crate::grpc::protobuf::{ProtobufOperation, ProtobufSet};
     use crate::lambda::CacheKey;
     use crate::mustache::Mustache;
 
     async fn get_protobuf_op() -> ProtobufOperation {
+        get_operation("greetings.proto", "Greeter", "SayHello").await
+    }
+
+    async fn get_operation(file: &str, service: &str, method: &str) -> ProtobufOperation {
         let root_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         let mut test_file = root_dir.join(file!());
 
         test_file.pop();
         test_file.push("tests");
-        test_file.push("greetings.proto");
+        test_file.push(file);
 
         let id = "greetings".to_string();
 
@@ -170,9 +269,9 @@ mod tests {
         )
         .unwrap();
 
-        let service = protobuf_set.find_service("Greeter").unwrap();
+        let service = protobuf_set.find_service(service).unwrap();
 
-        service.find_operation("SayHello").unwrap()
+        service.find_operation(method).unwrap()
     }
 
     #[derive(Setters)]
@@ -210,6 +309,9 @@ mod tests {
             operation: get_protobuf_op().await,
             body: None,
             operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
         };
         let ctx = Context::default();
         let rendered = tmpl.render(&ctx).unwrap();
@@ -244,6 +346,9 @@ mod tests {
             operation: get_protobuf_op().await,
             body: Some(Mustache::parse(r#"{ "name": "test" }"#).unwrap()),
             operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
         };
         let ctx = Context::default();
         let rendered = tmpl.render(&ctx).unwrap();
@@ -254,6 +359,135 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn request_with_compression() {
+        let tmpl = RequestTemplate {
+            url: Mustache::parse("http://localhost:3000/").unwrap(),
+            headers: vec![],
+            operation: get_protobuf_op().await,
+            body: Some(Mustache::parse(r#"{ "name": "test" }"#).unwrap()),
+            operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Gzip,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
+        };
+        let ctx = Context::default();
+        let rendered = tmpl.render(&ctx).unwrap();
+        let req = rendered.to_request().unwrap();
+
+        assert_eq!(req.headers()["grpc-encoding"], "gzip");
+        assert_eq!(req.headers()["grpc-accept-encoding"], "gzip, deflate");
+
+        let body = req.body().and_then(|body| body.as_bytes()).unwrap();
+        let frames = crate::grpc::protobuf::split_frames(body, Some("gzip")).unwrap();
+        assert_eq!(body[0], 1);
+        assert_eq!(frames, vec![b"\n\x04test".as_slice()]);
+    }
+
+    #[tokio::test]
+    async fn request_with_grpc_web_text() {
+        let tmpl = RequestTemplate {
+            url: Mustache::parse("http://localhost:3000/").unwrap(),
+            headers: vec![],
+            operation: get_protobuf_op().await,
+            body: Some(Mustache::parse(r#"{ "name": "test" }"#).unwrap()),
+            operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::GrpcWebText,
+            timeout: None,
+        };
+        let ctx = Context::default();
+        let rendered = tmpl.render(&ctx).unwrap();
+        let req = rendered.to_request().unwrap();
+
+        assert_eq!(
+            req.headers()["content-type"],
+            "application/grpc-web-text+proto"
+        );
+        assert_eq!(req.headers()["x-grpc-web"], "1");
+
+        let body = req.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_eq!(body, b"AAAAAAYKBHRlc3Q=");
+    }
+
+    #[tokio::test]
+    async fn request_with_timeout_and_binary_metadata() {
+        let tmpl = RequestTemplate {
+            url: Mustache::parse("http://localhost:3000/").unwrap(),
+            headers: vec![(
+                HeaderName::from_static("trace-bin"),
+                Mustache::parse("dHJhY2U=").unwrap(),
+            )],
+            operation: get_protobuf_op().await,
+            body: None,
+            operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: Some(Duration::from_secs(5)),
+        };
+        let ctx = Context::default();
+        let rendered = tmpl.render(&ctx).unwrap();
+        let req = rendered.to_request().unwrap();
+
+        assert_eq!(req.headers()["grpc-timeout"], "5S");
+        assert_eq!(req.headers()["trace-bin"], "dHJhY2U");
+        assert_eq!(req.timeout(), Some(&Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn streaming_request_keeps_only_the_deadline() {
+        let tmpl = RequestTemplate {
+            url: Mustache::parse("http://localhost:3000/").unwrap(),
+            headers: vec![],
+            operation: get_operation("news.proto", "news.NewsService", "StreamNews").await,
+            body: None,
+            operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: Some(Duration::from_secs(5)),
+        };
+        let ctx = Context::default();
+        let req = tmpl.render(&ctx).unwrap().to_request().unwrap();
+
+        assert_eq!(req.headers()["grpc-timeout"], "5S");
+        assert_eq!(req.timeout(), None);
+    }
+
+    #[tokio::test]
+    async fn request_without_invalid_binary_metadata() {
+        let tmpl = RequestTemplate {
+            url: Mustache::parse("http://localhost:3000/").unwrap(),
+            headers: vec![(
+                HeaderName::from_static("trace-bin"),
+                Mustache::parse("{{trace}}").unwrap(),
+            )],
+            operation: get_protobuf_op().await,
+            body: None,
+            operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
+        };
+        let ctx = Context::default().value(serde_json::json!({ "trace": "not base64!" }));
+        let req = tmpl.render(&ctx).unwrap().to_request().unwrap();
+
+        assert!(!req.headers().contains_key("trace-bin"));
+    }
+
+    #[test]
+    fn binary_metadata() {
+        assert_eq!(to_binary_metadata("AP8=").as_deref(), Some("AP8"));
+        assert_eq!(to_binary_metadata("AP8").as_deref(), Some("AP8"));
+        assert_eq!(to_binary_metadata("trace"), None);
+    }
+
+    #[test]
+    fn grpc_timeout() {
+        assert_eq!(to_grpc_timeout(Duration::from_secs(10)), "10S");
+        assert_eq!(to_grpc_timeout(Duration::from_millis(1500)), "1500m");
+        assert_eq!(to_grpc_timeout(Duration::from_secs(200_000_000)), "55555H");
+    }
+
     async fn request_template_with_body(body_str: &str) -> RequestTemplate {
         RequestTemplate {
             url: Mustache::parse("http://localhost:3000/").unwrap(),
@@ -261,6 +495,9 @@ mod tests {
             operation: get_protobuf_op().await,
             body: Some(Mustache::parse(body_str).unwrap()),
             operation_type: GraphQLOperationType::Query,
+            compression: GrpcCompression::Identity,
+            protocol: GrpcProtocol::Grpc,
+            timeout: None,
         }
     }
 
@@ -0,0 +1,234 @@
+use anyhow::{bail, Context, Result};
+use async_graphql_value::ConstValue;
+use base64::alphabet::STANDARD;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use hyper::HeaderMap;
+use prost::Message;
+use prost_reflect::prost_types::Any;
+use prost_reflect::{DescriptorPool, DynamicMessage};
+
+use crate::config::GrpcCode;
+
+const GRPC_STATUS: &str = "grpc-status";
+const GRPC_MESSAGE: &str = "grpc-message";
+const GRPC_STATUS_DETAILS: &str = "grpc-status-details-bin";
+
+/// Base64 as sent in binary headers and gRPC-Web bodies, with or without
+/// padding.
+pub(crate) const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// The `google.rpc.Status` message sent in `grpc-status-details-bin`.
+#[derive(Clone, PartialEq, Message)]
+struct Status {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<Any>,
+}
+
+/// The upstream answered with a gRPC error status.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("gRPC status {status}: {message}")]
+pub struct GrpcStatus {
+    pub status: GrpcCode,
+    pub message: String,
+    /// The details of `google.rpc.Status`, decoded as JSON.
+    pub details: Vec<ConstValue>,
+}
+
+impl GrpcStatus {
+    /// Reads the error status from the headers of a Trailers-Only response,
+    /// the way servers answer failed calls, or of a whole response whose
+    /// trailers were added to its headers. The types of the details are
+    /// looked up in `pool`.
+    pub fn from_headers(headers: &HeaderMap, pool: &DescriptorPool) -> Result<Option<Self>> {
+        let Some(code) = headers.get(GRPC_STATUS) else {
+            return Ok(None);
+        };
+        let status = GrpcCode::from_code(code.to_str()?.trim().parse()?);
+        if status == GrpcCode::Ok {
+            return Ok(None);
+        }
+
+        let message = headers
+            .get(GRPC_MESSAGE)
+            .map(|message| percent_encoding::percent_decode(message.as_bytes()))
+            .map(|message| message.decode_utf8_lossy().into_owned())
+            .unwrap_or_default();
+
+        let details = match headers.get(GRPC_STATUS_DETAILS) {
+            Some(details) => {
+                let details = BASE64
+                    .decode(details.as_bytes())
+                    .context("Invalid grpc-status-details-bin")?;
+                Status::decode(details.as_slice())?
+                    .details
+                    .iter()
+                    .map(|any| decode_any(any, pool))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Some(GrpcStatus { status, message, details }))
+    }
+
+    /// Reads the error status from the trailers sent after the messages.
+    /// Unless the status was already sent in the `headers`, a response
+    /// without one is an error. Without `trailers`, as when the client
+    /// couldn't read them, the status is assumed to be OK.
+    pub fn from_trailers(
+        headers: &HeaderMap,
+        trailers: Option<&HeaderMap>,
+        pool: &DescriptorPool,
+    ) -> Result<Option<Self>> {
+        match trailers {
+            Some(trailers) if trailers.contains_key(GRPC_STATUS) => {
+                Self::from_headers(trailers, pool)
+            }
+            Some(_) if !headers.contains_key(GRPC_STATUS) => {
+                bail!("The upstream didn't send a gRPC status")
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Decodes the message with its type from `pool`, or keeps its bytes encoded
+/// in base64 when the type is unknown.
+fn decode_any(any: &Any, pool: &DescriptorPool) -> ConstValue {
+    let type_name = any.type_url.rsplit('/').next().unwrap_or_default();
+    let message = pool
+        .get_message_by_name(type_name)
+        .and_then(|descriptor| DynamicMessage::decode(descriptor, any.value.as_slice()).ok())
+        .and_then(|message| serde_json::to_value(message).ok());
+
+    let mut value = serde_json::Map::new();
+    value.insert("@type".to_string(), any.type_url.clone().into());
+    match message {
+        Some(serde_json::Value::Object(fields)) => value.extend(fields),
+        _ => {
+            value.insert("value".to_string(), BASE64.encode(&any.value).into());
+        }
+    }
+
+    ConstValue::from_json(value.into()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::STANDARD_NO_PAD;
+    use hyper::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn pool() -> DescriptorPool {
+        let files = protox::compile(["news.proto"], ["src/grpc/tests"]).unwrap();
+        DescriptorPool::from_file_descriptor_set(files).unwrap()
+    }
+
+    #[test]
+    fn test_ok_status() {
+        let headers =
+            HeaderMap::from_iter([(GRPC_STATUS.parse().unwrap(), HeaderValue::from_static("0"))]);
+        assert!(GrpcStatus::from_headers(&headers, &pool())
+            .unwrap()
+            .is_none());
+        assert!(GrpcStatus::from_headers(&HeaderMap::new(), &pool())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_error_status() {
+        let news = DynamicMessage::decode(
+            pool().get_message_by_name("news.News").unwrap(),
+            b"\x08\x01\x12\x06Note 1".as_slice(),
+        )
+        .unwrap();
+        let details = Status {
+            code: 5,
+            message: "news not found".to_string(),
+            details: vec![
+                Any {
+                    type_url: "type.googleapis.com/news.News".to_string(),
+                    value: news.encode_to_vec(),
+                },
+                Any {
+                    type_url: "type.googleapis.com/google.rpc.ErrorInfo".to_string(),
+                    value: b"\x0a\x01a".to_vec(),
+                },
+            ],
+        };
+        let headers = HeaderMap::from_iter([
+            (GRPC_STATUS.parse().unwrap(), HeaderValue::from_static("5")),
+            (
+                GRPC_MESSAGE.parse().unwrap(),
+                HeaderValue::from_static("news%20not%20found"),
+            ),
+            (
+                GRPC_STATUS_DETAILS.parse().unwrap(),
+                HeaderValue::from_str(&STANDARD_NO_PAD.encode(details.encode_to_vec())).unwrap(),
+            ),
+        ]);
+
+        let status = GrpcStatus::from_headers(&headers, &pool())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(status.status, GrpcCode::NotFound);
+        assert_eq!(status.to_string(), "gRPC status NOT_FOUND: news not found");
+        assert_eq!(
+            ConstValue::List(status.details).into_json().unwrap(),
+            json!([
+                {"@type": "type.googleapis.com/news.News", "id": 1, "title": "Note 1"},
+                {"@type": "type.googleapis.com/google.rpc.ErrorInfo", "value": "CgFh"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_trailers() {
+        let ok =
+            HeaderMap::from_iter([(GRPC_STATUS.parse().unwrap(), HeaderValue::from_static("0"))]);
+        let not_found =
+            HeaderMap::from_iter([(GRPC_STATUS.parse().unwrap(), HeaderValue::from_static("5"))]);
+        let empty = HeaderMap::new();
+
+        let status = GrpcStatus::from_trailers(&empty, Some(&not_found), &pool())
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.status, GrpcCode::NotFound);
+        assert!(GrpcStatus::from_trailers(&empty, Some(&ok), &pool())
+            .unwrap()
+            .is_none());
+        // Trailers-Only responses carry the status in their headers
+        assert!(GrpcStatus::from_trailers(&ok, Some(&empty), &pool())
+            .unwrap()
+            .is_none());
+        assert!(GrpcStatus::from_trailers(&empty, None, &pool())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            GrpcStatus::from_trailers(&empty, Some(&empty), &pool())
+                .unwrap_err()
+                .to_string(),
+            "The upstream didn't send a gRPC status"
+        );
+    }
+
+    #[test]
+    fn test_unknown_code() {
+        assert_eq!(GrpcCode::from_code(5), GrpcCode::NotFound);
+        assert_eq!(GrpcCode::from_code(42), GrpcCode::Unknown);
+        assert_eq!(GrpcCode::from_code(-1), GrpcCode::Unknown);
+    }
+}
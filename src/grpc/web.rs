@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use hyper::body::Bytes;
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::HeaderMap;
+
+use super::protobuf::frame_length;
+use super::status::BASE64;
+use crate::config::GrpcProtocol;
+use crate::http::Response;
+
+/// The header telling a proxy that the request comes from a gRPC-Web client.
+pub const X_GRPC_WEB: &str = "x-grpc-web";
+
+/// The flag of the frame holding the trailers at the end of a gRPC-Web body.
+const TRAILERS_FLAG: u8 = 0x80;
+
+/// Encodes the Length-Prefixed Messages of a request for `protocol`.
+pub fn encode_body(protocol: GrpcProtocol, body: Vec<u8>) -> Vec<u8> {
+    match protocol {
+        GrpcProtocol::GrpcWebText => BASE64.encode(body).into_bytes(),
+        GrpcProtocol::Grpc | GrpcProtocol::GrpcWeb => body,
+    }
+}
+
+/// Turns a gRPC-Web response into a gRPC one: the body only keeps the
+/// messages, and the trailers sent at its end are added to the headers.
+pub fn decode_response(
+    protocol: GrpcProtocol,
+    response: Response<Bytes>,
+) -> Result<Response<Bytes>> {
+    let body = match protocol {
+        GrpcProtocol::Grpc => return Ok(response),
+        GrpcProtocol::GrpcWeb => response.body.to_vec(),
+        GrpcProtocol::GrpcWebText => decode_text(&response.body)?,
+    };
+
+    let mut headers = response.headers;
+    let mut messages = Vec::new();
+    let mut bytes = body.as_slice();
+    while !bytes.is_empty() {
+        let end = frame_length(bytes).context("Truncated gRPC-Web message")?;
+        let (frame, rest) = bytes.split_at(end);
+        if frame[0] & TRAILERS_FLAG != 0 {
+            headers.extend(parse_trailers(&frame[5..])?);
+        } else {
+            messages.extend_from_slice(frame);
+        }
+        bytes = rest;
+    }
+
+    Ok(Response {
+        status: response.status,
+        headers,
+        body: Bytes::from(messages),
+    })
+}
+
+/// Decodes a base64 body. The chunks of a stream are encoded one by one, so
+/// padding may appear in the middle of the body.
+fn decode_text(body: &[u8]) -> Result<Vec<u8>> {
+    let body: Vec<u8> = body
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+
+    let mut bytes = Vec::new();
+    let mut start = 0;
+    for (i, byte) in body.iter().enumerate() {
+        if *byte == b'=' && body.get(i + 1) != Some(&b'=') {
+            bytes.extend(BASE64.decode(&body[start..=i])?);
+            start = i + 1;
+        }
+    }
+    bytes.extend(BASE64.decode(&body[start..])?);
+
+    Ok(bytes)
+}
+
+/// Parses the trailers frame, made of `name: value` lines like HTTP/1.1
+/// headers.
+fn parse_trailers(bytes: &[u8]) -> Result<HeaderMap> {
+    let mut trailers = HeaderMap::new();
+    for line in String::from_utf8_lossy(bytes).split("\r\n") {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        trailers.append(
+            HeaderName::from_bytes(name.trim().to_lowercase().as_bytes())?,
+            HeaderValue::from_str(value.trim())?,
+        );
+    }
+
+    Ok(trailers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &[u8]) -> Response<Bytes> {
+        Response::empty().body(Bytes::copy_from_slice(body))
+    }
+
+    #[test]
+    fn test_decode_binary() {
+        let body = b"\0\0\0\0\x02\x08\x01\x80\0\0\0\x21grpc-status:0\r\ngrpc-message: ok\r\n";
+        let response = decode_response(GrpcProtocol::GrpcWeb, response(body)).unwrap();
+
+        assert_eq!(response.body.as_ref(), b"\0\0\0\0\x02\x08\x01");
+        assert_eq!(response.headers.get("grpc-status").unwrap(), "0");
+        assert_eq!(response.headers.get("grpc-message").unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_decode_text() {
+        let message = BASE64.encode(b"\0\0\0\0\x02\x08\x01");
+        let trailers = BASE64.encode(b"\x80\0\0\0\x0fgrpc-status:5\r\n");
+        let body = format!("{}{}", message, trailers);
+        let response =
+            decode_response(GrpcProtocol::GrpcWebText, response(body.as_bytes())).unwrap();
+
+        assert_eq!(response.body.as_ref(), b"\0\0\0\0\x02\x08\x01");
+        assert_eq!(response.headers.get("grpc-status").unwrap(), "5");
+    }
+
+    #[test]
+    fn test_encode_text() {
+        assert_eq!(
+            encode_body(GrpcProtocol::GrpcWebText, b"\0\0\0\0\x02\x08\x01".to_vec()),
+            b"AAAAAAIIAQ==".to_vec()
+        );
+        assert_eq!(
+            encode_body(GrpcProtocol::GrpcWeb, b"\0\0\0\0\x02\x08\x01".to_vec()),
+            b"\0\0\0\0\x02\x08\x01".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let error =
+            decode_response(GrpcProtocol::GrpcWeb, response(b"\0\0\0\0\x05\x08")).unwrap_err();
+        assert_eq!(error.to_string(), "Truncated gRPC-Web message");
+    }
+}
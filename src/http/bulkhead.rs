@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use futures_util::future::{select, Either};
+use futures_util::{pin_mut, StreamExt};
+use hyper::body::Bytes;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::{Response, StreamResponse};
+use crate::blueprint::Upstream;
+use crate::HttpIO;
+
+/// Counters describing how busy the upstreams guarded by a [Bulkhead] are.
+#[derive(Default, Debug)]
+pub struct BulkheadStats {
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    saturated: AtomicU64,
+    timed_out: AtomicU64,
+}
+
+/// A point in time copy of [BulkheadStats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BulkheadSnapshot {
+    /// Requests currently being executed
+    pub in_flight: usize,
+    /// Requests currently waiting for a free slot
+    pub queued: usize,
+    /// Total number of requests that found their upstream saturated
+    pub saturated: u64,
+    /// Total number of requests that failed waiting for a free slot
+    pub timed_out: u64,
+}
+
+impl BulkheadStats {
+    pub fn snapshot(&self) -> BulkheadSnapshot {
+        BulkheadSnapshot {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            saturated: self.saturated.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Keeps a gauge of [BulkheadStats] incremented while it is alive, so that
+/// it's restored even when the request future is dropped.
+struct Gauge {
+    stats: Arc<BulkheadState>,
+    gauge: fn(&BulkheadStats) -> &AtomicUsize,
+}
+
+impl Gauge {
+    fn new(stats: Arc<BulkheadState>, gauge: fn(&BulkheadStats) -> &AtomicUsize) -> Self {
+        gauge(&stats.stats).fetch_add(1, Ordering::Relaxed);
+        Self { stats, gauge }
+    }
+}
+
+impl Drop for Gauge {
+    fn drop(&mut self) {
+        (self.gauge)(&self.stats.stats).fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The slots of every upstream host and their counters. Clients sharing the
+/// same state share the limits of the upstreams.
+#[derive(Default, Debug)]
+pub struct BulkheadState {
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    stats: BulkheadStats,
+}
+
+impl BulkheadState {
+    pub fn stats(&self) -> BulkheadSnapshot {
+        self.stats.snapshot()
+    }
+}
+
+/// Wraps an [HttpIO] and limits the number of requests that can be in flight
+/// to every upstream host. Requests beyond the limit wait in a queue until a
+/// slot is released, or fail once the queue timeout elapses.
+pub struct Bulkhead<Http> {
+    http: Http,
+    max_concurrent_requests: Option<usize>,
+    concurrency_limits: BTreeMap<String, usize>,
+    queue_timeout: Option<Duration>,
+    state: Arc<BulkheadState>,
+}
+
+impl<Http: HttpIO> Bulkhead<Http> {
+    pub fn new(http: Http, upstream: &Upstream) -> Self {
+        Self {
+            http,
+            max_concurrent_requests: upstream.max_concurrent_requests,
+            concurrency_limits: upstream.concurrency_limits.clone(),
+            queue_timeout: upstream
+                .concurrency_queue_timeout
+                .map(Duration::from_millis),
+            state: Arc::new(BulkheadState::default()),
+        }
+    }
+
+    /// Shares the slots and counters of `state` with other clients.
+    pub fn state(mut self, state: Arc<BulkheadState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn stats(&self) -> BulkheadSnapshot {
+        self.state.stats()
+    }
+
+    fn semaphore(&self, host: &str) -> Option<Arc<Semaphore>> {
+        let limit = self
+            .concurrency_limits
+            .get(host)
+            .copied()
+            .or(self.max_concurrent_requests)?;
+
+        let mut semaphores = self.state.semaphores.lock().unwrap();
+        let semaphore = semaphores
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)));
+
+        Some(semaphore.clone())
+    }
+
+    async fn acquire(&self, host: &str, semaphore: Arc<Semaphore>) -> Result<OwnedSemaphorePermit> {
+        let stats = &self.state.stats;
+        if semaphore.available_permits() == 0 {
+            stats.saturated.fetch_add(1, Ordering::Relaxed);
+            log::warn!(
+                "Upstream {} is saturated, {} request(s) waiting for a free slot",
+                host,
+                stats.queued.load(Ordering::Relaxed) + 1
+            );
+        }
+
+        let queued = Gauge::new(self.state.clone(), |stats| &stats.queued);
+        let permit = semaphore.acquire_owned();
+        let permit = match self.queue_timeout {
+            Some(timeout) => {
+                let delay = futures_timer::Delay::new(timeout);
+                pin_mut!(permit);
+                match select(permit, delay).await {
+                    Either::Left((permit, _)) => Some(permit),
+                    Either::Right(_) => None,
+                }
+            }
+            None => Some(permit.await),
+        };
+        drop(queued);
+
+        match permit {
+            Some(permit) => Ok(permit?),
+            None => {
+                stats.timed_out.fetch_add(1, Ordering::Relaxed);
+                log::error!(
+                    "Request to upstream {} timed out waiting for a free slot",
+                    host
+                );
+                Err(anyhow!(
+                    "Upstream {} is saturated: no free slot within {}ms",
+                    host,
+                    self.queue_timeout.unwrap_or_default().as_millis()
+                ))
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<Http: HttpIO> HttpIO for Bulkhead<Http> {
+    async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let Some(semaphore) = self.semaphore(&host) else {
+            return self.http.execute(request).await;
+        };
+
+        let _permit = self.acquire(&host, semaphore).await?;
+        let _in_flight = Gauge::new(self.state.clone(), |stats| &stats.in_flight);
+
+        self.http.execute(request).await
+    }
+
+    async fn execute_stream(&self, request: reqwest::Request) -> Result<StreamResponse> {
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let Some(semaphore) = self.semaphore(&host) else {
+            return self.http.execute_stream(request).await;
+        };
+
+        // the slot is released once the whole body has been read
+        let permit = self.acquire(&host, semaphore).await?;
+        let in_flight = Gauge::new(self.state.clone(), |stats| &stats.in_flight);
+        let mut response = self.http.execute_stream(request).await?;
+        response.body = response
+            .body
+            .map(move |chunk| {
+                let _ = (&permit, &in_flight);
+                chunk
+            })
+            .boxed();
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct SlowHttp {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for Arc<SlowHttp> {
+        async fn execute(&self, _request: reqwest::Request) -> Result<Response<Bytes>> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(Response::empty())
+        }
+    }
+
+    fn request(url: &str) -> reqwest::Request {
+        reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_limits_concurrent_requests() {
+        let http = Arc::new(SlowHttp::default());
+        let upstream = Upstream::default().max_concurrent_requests(Some(2));
+        let bulkhead = Bulkhead::new(http.clone(), &upstream);
+
+        let requests = (0..6).map(|_| bulkhead.execute(request("http://a.com/")));
+        let results = join_all(requests).await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(http.max_running.load(Ordering::SeqCst), 2);
+        assert!(bulkhead.stats().saturated > 0);
+    }
+
+    #[tokio::test]
+    async fn test_host_limit_overrides_default() {
+        let http = Arc::new(SlowHttp::default());
+        let upstream = Upstream::default()
+            .max_concurrent_requests(Some(5))
+            .concurrency_limits(BTreeMap::from([("a.com".to_string(), 1)]));
+        let bulkhead = Bulkhead::new(http.clone(), &upstream);
+
+        let requests = (0..3).map(|_| bulkhead.execute(request("http://a.com/")));
+        join_all(requests).await;
+
+        assert_eq!(http.max_running.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_queue_timeout() {
+        let http = Arc::new(SlowHttp::default());
+        let upstream = Upstream::default()
+            .max_concurrent_requests(Some(1))
+            .concurrency_queue_timeout(Some(1));
+        let bulkhead = Bulkhead::new(http, &upstream);
+
+        let requests = (0..2).map(|_| bulkhead.execute(request("http://a.com/")));
+        let results = join_all(requests).await;
+
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+        assert_eq!(
+            bulkhead.stats(),
+            BulkheadSnapshot { in_flight: 0, queued: 0, saturated: 1, timed_out: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropped_requests_release_gauges() {
+        let http = Arc::new(SlowHttp::default());
+        let upstream = Upstream::default().max_concurrent_requests(Some(1));
+        let bulkhead = Bulkhead::new(http, &upstream);
+
+        let requests = (0..2).map(|_| bulkhead.execute(request("http://a.com/")));
+        let result = tokio::time::timeout(Duration::from_millis(5), join_all(requests)).await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            bulkhead.stats(),
+            BulkheadSnapshot { in_flight: 0, queued: 0, saturated: 1, timed_out: 0 }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_state() {
+        let http = Arc::new(SlowHttp::default());
+        let upstream = Upstream::default().max_concurrent_requests(Some(1));
+        let state = Arc::new(BulkheadState::default());
+        let http1 = Bulkhead::new(http.clone(), &upstream).state(state.clone());
+        let http2 = Bulkhead::new(http.clone(), &upstream).state(state);
+
+        let requests = [
+            http1.execute(request("http://a.com/")),
+            http2.execute(request("http://a.com/")),
+        ];
+        join_all(requests).await;
+
+        assert_eq!(http.max_running.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_by_default() {
+        let http = Arc::new(SlowHttp::default());
+        let bulkhead = Bulkhead::new(http.clone(), &Upstream::default());
+
+        let requests = (0..4).map(|_| bulkhead.execute(request("http://a.com/")));
+        join_all(requests).await;
+
+        assert_eq!(http.max_running.load(Ordering::SeqCst), 4);
+    }
+}
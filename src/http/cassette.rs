@@ -0,0 +1,538 @@
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use futures_util::future::{BoxFuture, Shared};
+use futures_util::stream::{self, StreamExt};
+use futures_util::FutureExt;
+use hyper::body::Bytes;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+use super::{Response, StatusError, StreamResponse};
+use crate::{FileIO, HttpIO};
+
+/// Decides whether upstream traffic is recorded into, or replayed from, the
+/// cassettes stored in a directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record(String),
+    Replay(String),
+}
+
+/// A single request/response pair. A cassette file holds a list of them in the
+/// same shape as the `mock` blocks of the execution specs, so recorded files
+/// can be pasted into a spec as they are.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub body: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CassetteResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub body: serde_json::Value,
+    /// Body that isn't JSON (eg. gRPC frames), with non printable bytes escaped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_body: Option<String>,
+}
+
+impl Cassette {
+    fn new(request: &reqwest::Request, response: &Response<Bytes>) -> Self {
+        let request_body = request_body(request)
+            .and_then(|body| std::str::from_utf8(body).ok())
+            .map(serde_json::Value::from)
+            .unwrap_or_default();
+
+        let (body, text_body) = match serde_json::from_slice(&response.body) {
+            Ok(body) if !response.body.is_empty() => (body, None),
+            _ => (serde_json::Value::Null, Some(escape_bytes(&response.body))),
+        };
+
+        Cassette {
+            request: CassetteRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                headers: to_map(request.headers()),
+                body: request_body,
+            },
+            response: CassetteResponse {
+                status: response.status.as_u16(),
+                headers: to_map(&response.headers),
+                body,
+                text_body,
+            },
+        }
+    }
+
+    fn to_response(&self) -> Result<Response<Bytes>> {
+        let mut response = Response::empty();
+        response.status = reqwest::StatusCode::from_u16(self.response.status)?;
+        for (name, value) in self.response.headers.iter() {
+            response
+                .headers
+                .insert(HeaderName::try_from(name)?, HeaderValue::try_from(value)?);
+        }
+        response.body = match &self.response.text_body {
+            Some(text) => Bytes::from(unescape_bytes(text)?),
+            None => Bytes::from(serde_json::to_vec(&self.response.body)?),
+        };
+
+        Ok(response)
+    }
+}
+
+fn request_body(request: &reqwest::Request) -> Option<&[u8]> {
+    request.body().and_then(|body| body.as_bytes())
+}
+
+fn to_map(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Path of the cassette for a request, derived from its method, url and body.
+fn cassette_path(dir: &str, request: &reqwest::Request) -> String {
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(request.method().as_str().as_bytes());
+    hasher.write(request.url().as_str().as_bytes());
+    hasher.write(request_body(request).unwrap_or_default());
+
+    format!("{}/{:016x}.yml", dir.trim_end_matches('/'), hasher.finish())
+}
+
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for byte in bytes {
+        match byte {
+            0 => text.push_str("\\0"),
+            b'\n' => text.push_str("\\n"),
+            b'\t' => text.push_str("\\t"),
+            b'\r' => text.push_str("\\r"),
+            b'\\' => text.push_str("\\\\"),
+            b'"' => text.push_str("\\\""),
+            0x20..=0x7e => text.push(*byte as char),
+            _ => text.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    text
+}
+
+fn unescape_bytes(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('0') => bytes.push(0),
+                Some('n') => bytes.push(b'\n'),
+                Some('t') => bytes.push(b'\t'),
+                Some('r') => bytes.push(b'\r'),
+                Some('\\') => bytes.push(b'\\'),
+                Some('"') => bytes.push(b'"'),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    bytes.push(u8::from_str_radix(&hex, 16)?);
+                }
+                other => return Err(anyhow!("Unsupported escape sequence: \\{:?}", other)),
+            },
+            c => {
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Wraps an [HttpIO] and writes every exchange that got a response, including
+/// the ones failing with a [StatusError], into a cassette file inside `dir`.
+/// Streamed bodies are recorded once they have been read to the end, with their
+/// trailers kept as headers.
+pub struct Recorder<Http> {
+    http: Http,
+    file: Arc<dyn FileIO>,
+    dir: String,
+}
+
+impl<Http: HttpIO> Recorder<Http> {
+    pub fn new(http: Http, file: Arc<dyn FileIO>, dir: String) -> Self {
+        Self { http, file, dir }
+    }
+}
+
+/// The response to record for a request that failed with an error status.
+fn status_response(error: &anyhow::Error) -> Option<Response<Bytes>> {
+    let error = error.downcast_ref::<StatusError>()?;
+    Some(
+        Response::empty()
+            .status(error.status)
+            .body(error.body.clone()),
+    )
+}
+
+async fn record(
+    file: &dyn FileIO,
+    dir: &str,
+    request: &reqwest::Request,
+    response: &Response<Bytes>,
+) {
+    let path = cassette_path(dir, request);
+    let result = match serde_yaml::to_string(&[Cassette::new(request, response)]) {
+        Ok(cassette) => file.write(&path, cassette.as_bytes()).await,
+        Err(error) => Err(error.into()),
+    };
+    if let Err(error) = result {
+        log::error!(
+            "Failed to record {} into {}: {}",
+            request.url(),
+            path,
+            error
+        );
+    }
+}
+
+/// A streamed response whose body is being collected for its cassette.
+struct Recording {
+    file: Arc<dyn FileIO>,
+    dir: String,
+    request: reqwest::Request,
+    response: Response<Bytes>,
+    body: Vec<u8>,
+    trailers: Shared<BoxFuture<'static, Option<HeaderMap>>>,
+}
+
+impl Recording {
+    /// Writes the cassette, with the trailers recorded as headers.
+    async fn finish(self) {
+        let mut response = self.response.body(Bytes::from(self.body));
+        response
+            .headers
+            .extend(self.trailers.await.unwrap_or_default());
+        record(self.file.as_ref(), &self.dir, &self.request, &response).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<Http: HttpIO> HttpIO for Recorder<Http> {
+    async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+        let Some(copy) = request.try_clone() else {
+            log::warn!("Request to {} can't be recorded", request.url());
+            return self.http.execute(request).await;
+        };
+
+        let result = self.http.execute(request).await;
+        let response = match &result {
+            Ok(response) => response.clone(),
+            Err(error) => match status_response(error) {
+                Some(response) => response,
+                None => return result,
+            },
+        };
+        record(self.file.as_ref(), &self.dir, &copy, &response).await;
+
+        result
+    }
+
+    async fn execute_stream(&self, request: reqwest::Request) -> Result<StreamResponse> {
+        let Some(copy) = request.try_clone() else {
+            log::warn!("Request to {} can't be recorded", request.url());
+            return self.http.execute_stream(request).await;
+        };
+
+        let response = match self.http.execute_stream(request).await {
+            Ok(response) => response,
+            Err(error) => {
+                if let Some(response) = status_response(&error) {
+                    record(self.file.as_ref(), &self.dir, &copy, &response).await;
+                }
+                return Err(error);
+            }
+        };
+
+        let trailers = response.trailers.shared();
+        let recording = Recording {
+            file: self.file.clone(),
+            dir: self.dir.clone(),
+            request: copy,
+            response: Response::empty()
+                .status(response.status)
+                .headers(response.headers.clone()),
+            body: Vec::new(),
+            trailers: trailers.clone(),
+        };
+        // a body that fails halfway isn't recorded
+        let body = stream::unfold(
+            (response.body, Some(recording)),
+            |(mut body, mut recording)| async move {
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        if let Some(recording) = recording.as_mut() {
+                            recording.body.extend_from_slice(&chunk);
+                        }
+                        Some((Ok(chunk), (body, recording)))
+                    }
+                    Some(Err(error)) => Some((Err(error), (body, None))),
+                    None => {
+                        if let Some(recording) = recording {
+                            recording.finish().await;
+                        }
+                        None
+                    }
+                }
+            },
+        );
+
+        Ok(StreamResponse {
+            status: response.status,
+            headers: response.headers,
+            body: body.boxed(),
+            trailers: trailers.boxed(),
+        })
+    }
+}
+
+/// Serves responses from the cassettes recorded by [Recorder] without
+/// touching the network. Streamed bodies are served in a single chunk.
+pub struct Replayer {
+    file: Arc<dyn FileIO>,
+    dir: String,
+}
+
+impl Replayer {
+    pub fn new(file: Arc<dyn FileIO>, dir: String) -> Self {
+        Self { file, dir }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpIO for Replayer {
+    async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+        let path = cassette_path(&self.dir, &request);
+        let content = self.file.read(&path).await.map_err(|_| {
+            anyhow!(
+                "No cassette found for request: {} {} in {}",
+                request.method(),
+                request.url(),
+                self.dir
+            )
+        })?;
+        let cassettes: Vec<Cassette> = serde_yaml::from_str(&content)?;
+        let cassette = cassettes
+            .first()
+            .ok_or(anyhow!("Cassette {} is empty", path))?;
+        let response = cassette.to_response()?;
+
+        if response.status.is_client_error() || response.status.is_server_error() {
+            let url = request.url().to_string();
+            return Err(StatusError { status: response.status, url, body: response.body }.into());
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct InMemoryFileIO {
+        files: Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl FileIO for InMemoryFileIO {
+        async fn write<'a>(&'a self, path: &'a str, content: &'a [u8]) -> Result<()> {
+            let content = String::from_utf8(content.to_vec())?;
+            self.files.lock().unwrap().insert(path.to_string(), content);
+            Ok(())
+        }
+
+        async fn read<'a>(&'a self, path: &'a str) -> Result<String> {
+            let files = self.files.lock().unwrap();
+            files.get(path).cloned().ok_or(anyhow!("Not found"))
+        }
+    }
+
+    struct EchoHttp;
+
+    #[async_trait::async_trait]
+    impl HttpIO for EchoHttp {
+        async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+            let body = match request_body(&request) {
+                Some(body) => Bytes::copy_from_slice(body),
+                None => Bytes::from(r#"{"id":1}"#),
+            };
+            Ok(Response::empty().body(body))
+        }
+    }
+
+    struct NotFoundHttp;
+
+    #[async_trait::async_trait]
+    impl HttpIO for NotFoundHttp {
+        async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
+            let status = reqwest::StatusCode::NOT_FOUND;
+            let url = request.url().to_string();
+            Err(StatusError { status, url, body: Bytes::from("missing") }.into())
+        }
+    }
+
+    struct ChunkedHttp;
+
+    #[async_trait::async_trait]
+    impl HttpIO for ChunkedHttp {
+        async fn execute(&self, _request: reqwest::Request) -> Result<Response<Bytes>> {
+            Err(anyhow!("only streams are sent"))
+        }
+
+        async fn execute_stream(&self, _request: reqwest::Request) -> Result<StreamResponse> {
+            let chunks = [Ok(Bytes::from("\0\x01")), Ok(Bytes::from("grpc"))];
+            let trailers = HeaderMap::from_iter([(
+                HeaderName::from_static("grpc-status"),
+                HeaderValue::from_static("0"),
+            )]);
+            Ok(StreamResponse {
+                status: reqwest::StatusCode::OK,
+                headers: Default::default(),
+                body: stream::iter(chunks).boxed(),
+                trailers: futures_util::future::ready(Some(trailers)).boxed(),
+            })
+        }
+    }
+
+    fn request(method: reqwest::Method, body: Option<&'static [u8]>) -> reqwest::Request {
+        let mut request = reqwest::Request::new(method, "http://a.com/users".parse().unwrap());
+        *request.body_mut() = body.map(reqwest::Body::from);
+        request
+    }
+
+    #[test]
+    fn test_escape_roundtrip() {
+        let bytes = b"\0\0\0\0\x05\n\x03foo\"\\\xff\r\t".to_vec();
+        let text = escape_bytes(&bytes);
+
+        assert_eq!(text, "\\0\\0\\0\\0\\x05\\n\\x03foo\\\"\\\\\\xff\\r\\t");
+        assert_eq!(unescape_bytes(&text).unwrap(), bytes);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let file = Arc::new(InMemoryFileIO::default());
+        let recorder = Recorder::new(EchoHttp, file.clone(), "cassettes".to_string());
+        let replayer = Replayer::new(file.clone(), "cassettes".to_string());
+
+        let json = recorder
+            .execute(request(reqwest::Method::GET, None))
+            .await
+            .unwrap();
+        let binary = recorder
+            .execute(request(reqwest::Method::POST, Some(b"\0\x01grpc")))
+            .await
+            .unwrap();
+
+        assert_eq!(file.files.lock().unwrap().len(), 2);
+        let replayed = replayer
+            .execute(request(reqwest::Method::GET, None))
+            .await
+            .unwrap();
+        assert_eq!(replayed.body, json.body);
+        let replayed = replayer
+            .execute(request(reqwest::Method::POST, Some(b"\0\x01grpc")))
+            .await
+            .unwrap();
+        assert_eq!(replayed.body, binary.body);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_error_status() {
+        let file = Arc::new(InMemoryFileIO::default());
+        let recorder = Recorder::new(NotFoundHttp, file.clone(), "cassettes".to_string());
+        let replayer = Replayer::new(file.clone(), "cassettes".to_string());
+
+        let recorded = recorder
+            .execute(request(reqwest::Method::GET, None))
+            .await
+            .unwrap_err();
+        let replayed = replayer
+            .execute(request(reqwest::Method::GET, None))
+            .await
+            .unwrap_err();
+
+        let replayed = replayed.downcast_ref::<StatusError>().unwrap();
+        assert_eq!(replayed.status, reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(replayed.body, Bytes::from("missing"));
+        assert_eq!(replayed.to_string(), recorded.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_stream() {
+        let file = Arc::new(InMemoryFileIO::default());
+        let recorder = Recorder::new(ChunkedHttp, file.clone(), "cassettes".to_string());
+        let replayer = Replayer::new(file.clone(), "cassettes".to_string());
+
+        let response = recorder
+            .execute_stream(request(reqwest::Method::POST, Some(b"stream")))
+            .await
+            .unwrap();
+        assert!(file.files.lock().unwrap().is_empty());
+        let chunks: Vec<_> = response.body.map(Result::unwrap).collect().await;
+        assert_eq!(chunks, vec![Bytes::from("\0\x01"), Bytes::from("grpc")]);
+        assert!(response.trailers.await.is_some());
+
+        let replayed = replayer
+            .execute_stream(request(reqwest::Method::POST, Some(b"stream")))
+            .await
+            .unwrap();
+        assert_eq!(replayed.headers["grpc-status"], "0");
+        let chunks: Vec<_> = replayed.body.map(Result::unwrap).collect().await;
+        assert_eq!(chunks, vec![Bytes::from("\0\x01grpc")]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_missing_cassette() {
+        let replayer = Replayer::new(Arc::new(InMemoryFileIO::default()), "cassettes".into());
+        let result = replayer
+            .execute(request(reqwest::Method::POST, Some(b"unknown")))
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No cassette found for request: POST http://a.com/users in cassettes"
+        );
+    }
+
+    #[test]
+    fn test_cassette_format() {
+        let request = request(reqwest::Method::POST, Some(b"{\"a\":1}"));
+        let response = Response::empty().body(Bytes::from(r#"{"id":1}"#));
+        let cassette = serde_yaml::to_string(&Cassette::new(&request, &response)).unwrap();
+
+        assert_eq!(
+            cassette,
+            "request:\n  method: POST\n  url: http://a.com/users\n  body: '{\"a\":1}'\nresponse:\n  status: 200\n  body:\n    id: 1\n"
+        );
+    }
+}
@@ -5,11 +5,13 @@ use std::time::Duration;
 use async_graphql::async_trait;
 use async_graphql::futures_util::future::join_all;
 use async_graphql_value::ConstValue;
+use derive_setters::Setters;
+use hyper::body::Bytes;
 
 use crate::config::group_by::GroupBy;
-use crate::config::Batch;
+use crate::config::{Batch, Hedge, ResponseFormat};
 use crate::data_loader::{DataLoader, Loader};
-use crate::http::{DataLoaderRequest, Response};
+use crate::http::{execute_hedged, DataLoaderRequest, Response};
 use crate::json::JsonLike;
 use crate::runtime::TargetRuntime;
 
@@ -31,11 +33,13 @@ fn get_body_value_list(body_value: &HashMap<String, Vec<&ConstValue>>, id: &str)
     )
 }
 
-#[derive(Clone)]
+#[derive(Clone, Setters)]
 pub struct HttpDataLoader {
     pub runtime: TargetRuntime,
     pub group_by: Option<GroupBy>,
     pub body: fn(&HashMap<String, Vec<&ConstValue>>, &str) -> ConstValue,
+    pub hedge: Option<Hedge>,
+    pub response_format: Option<ResponseFormat>,
 }
 impl HttpDataLoader {
     pub fn new(runtime: TargetRuntime, group_by: Option<GroupBy>, is_list: bool) -> Self {
@@ -47,9 +51,15 @@ impl HttpDataLoader {
             } else {
                 get_body_value_single
             },
+            hedge: None,
+            response_format: None,
         }
     }
 
+    async fn execute(&self, request: reqwest::Request) -> anyhow::Result<Response<Bytes>> {
+        execute_hedged(self.runtime.http.as_ref(), request, self.hedge.as_ref()).await
+    }
+
     pub fn to_data_loader(self, batch: Batch) -> DataLoader<DataLoaderRequest, HttpDataLoader> {
         DataLoader::new(self)
             .delay(Duration::from_millis(batch.delay as u64))
@@ -79,7 +89,10 @@ impl Loader<DataLoaderRequest> for HttpDataLoader {
                 first_url.query_pairs_mut().extend_pairs(url.query_pairs());
             }
 
-            let res = self.runtime.http.execute(request).await?.to_json()?;
+            let res = self
+                .execute(request)
+                .await?
+                .to_value(self.response_format.as_ref())?;
             #[allow(clippy::mutable_key_type)]
             let mut hashmap = HashMap::with_capacity(keys.len());
             let path = &group_by.path();
@@ -97,7 +110,7 @@ impl Loader<DataLoaderRequest> for HttpDataLoader {
             Ok(hashmap)
         } else {
             let results = keys.iter().map(|key| async {
-                let result = self.runtime.http.execute(key.to_request()).await;
+                let result = self.execute(key.to_request()).await;
                 (key.clone(), result)
             });
 
@@ -106,7 +119,7 @@ impl Loader<DataLoaderRequest> for HttpDataLoader {
             #[allow(clippy::mutable_key_type)]
             let mut hashmap = HashMap::new();
             for (key, value) in results {
-                hashmap.insert(key, value?.to_json()?);
+                hashmap.insert(key, value?.to_value(self.response_format.as_ref())?);
             }
 
             Ok(hashmap)
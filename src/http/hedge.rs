@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use futures_timer::Delay;
+use futures_util::future::{select, Either};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use hyper::body::Bytes;
+
+use super::Response;
+use crate::config::Hedge;
+use crate::HttpIO;
+
+enum Event {
+    Response(Option<Result<Response<Bytes>>>),
+    Timeout,
+}
+
+/// Executes the request, sending a duplicate every `delay_ms` while no
+/// response has arrived, up to `max_extra` duplicates. The first successful
+/// response wins and the requests still in flight are dropped, which cancels
+/// them. Requests whose body can't be cloned are never hedged.
+pub async fn execute_hedged(
+    http: &dyn HttpIO,
+    request: reqwest::Request,
+    hedge: Option<&Hedge>,
+) -> Result<Response<Bytes>> {
+    let Some(hedge) = hedge.filter(|hedge| hedge.max_extra > 0) else {
+        return http.execute(request).await;
+    };
+    let Some(first) = request.try_clone() else {
+        return http.execute(request).await;
+    };
+
+    let delay = Duration::from_millis(hedge.delay_ms);
+    let mut timer = Delay::new(delay);
+    let mut pending = FuturesUnordered::new();
+    let mut extra = 0;
+    pending.push(http.execute(first));
+
+    loop {
+        let event = if extra < hedge.max_extra {
+            match select(pending.next(), &mut timer).await {
+                Either::Left((response, _)) => Event::Response(response),
+                Either::Right(_) => Event::Timeout,
+            }
+        } else {
+            Event::Response(pending.next().await)
+        };
+
+        match event {
+            Event::Response(Some(Ok(response))) => return Ok(response),
+            Event::Response(Some(Err(error))) if pending.is_empty() => return Err(error),
+            Event::Response(Some(Err(error))) => {
+                log::debug!("Hedged request to {} failed: {}", request.url(), error);
+            }
+            Event::Response(None) => return Err(anyhow!("No response from {}", request.url())),
+            Event::Timeout => {
+                extra += 1;
+                log::debug!(
+                    "No response from {} within {}ms, sending hedged request {}/{}",
+                    request.url(),
+                    hedge.delay_ms,
+                    extra,
+                    hedge.max_extra
+                );
+                if let Some(request) = request.try_clone() {
+                    pending.push(http.execute(request));
+                }
+                timer.reset(delay);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use hyper::body::Bytes;
+
+    use super::*;
+
+    /// Answers the n-th request after the n-th delay
+    struct DelayedHttp {
+        delays: Vec<u64>,
+        calls: AtomicUsize,
+    }
+
+    impl DelayedHttp {
+        fn new(delays: Vec<u64>) -> Self {
+            Self { delays, calls: AtomicUsize::new(0) }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for DelayedHttp {
+        async fn execute(&self, _request: reqwest::Request) -> Result<Response<Bytes>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let delay = self.delays.get(call).copied().unwrap_or_default();
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(Response::empty().body(Bytes::from(call.to_string())))
+        }
+    }
+
+    fn request() -> reqwest::Request {
+        reqwest::Request::new(reqwest::Method::GET, "http://a.com/".parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_without_hedge() {
+        let http = DelayedHttp::new(vec![10]);
+        let response = execute_hedged(&http, request(), None).await.unwrap();
+
+        assert_eq!(response.body, Bytes::from("0"));
+        assert_eq!(http.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fast_response_is_not_hedged() {
+        let http = DelayedHttp::new(vec![1]);
+        let hedge = Hedge { delay_ms: 100, max_extra: 2 };
+        let response = execute_hedged(&http, request(), Some(&hedge))
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, Bytes::from("0"));
+        assert_eq!(http.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hedged_response_wins() {
+        let http = DelayedHttp::new(vec![1000, 1]);
+        let hedge = Hedge { delay_ms: 10, max_extra: 1 };
+        let response = execute_hedged(&http, request(), Some(&hedge))
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, Bytes::from("1"));
+        assert_eq!(http.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_extra() {
+        let http = DelayedHttp::new(vec![200, 200, 200, 200]);
+        let hedge = Hedge { delay_ms: 10, max_extra: 2 };
+        let response = execute_hedged(&http, request(), Some(&hedge))
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, Bytes::from("0"));
+        assert_eq!(http.calls.load(Ordering::SeqCst), 3);
+    }
+}
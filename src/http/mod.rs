@@ -1,18 +1,27 @@
 mod data_loader;
 
+mod bulkhead;
 mod cache;
+mod cassette;
 mod data_loader_request;
+mod hedge;
 mod method;
+mod paginate;
 mod request_context;
 mod request_handler;
 mod request_template;
 mod response;
+mod response_format;
 pub mod showcase;
 
+pub use bulkhead::*;
 pub use cache::*;
+pub use cassette::*;
 pub use data_loader::*;
 pub use data_loader_request::*;
+pub use hedge::execute_hedged;
 pub use method::Method;
+pub use paginate::execute_paginated;
 pub use request_context::RequestContext;
 pub use request_handler::{graphiql, handle_request};
 pub use request_template::RequestTemplate;
@@ -0,0 +1,368 @@
+use std::collections::HashSet;
+use std::future::Future;
+
+use anyhow::{anyhow, Result};
+use async_graphql_value::ConstValue;
+use reqwest::header::{HeaderMap, LINK};
+use url::Url;
+
+use super::Response;
+use crate::config::{Paginate, PaginationStrategy};
+use crate::json::JsonLike;
+
+/// Finds the `rel="next"` url in a `Link` header, eg.
+/// `<https://api.com/users?page=2>; rel="next", <...>; rel="last"`
+fn next_link(headers: &HeaderMap, base: &Url) -> Option<Url> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+            parts
+                .any(|param| {
+                    let param = param.trim().replace(' ', "");
+                    param == "rel=\"next\"" || param == "rel=next"
+                })
+                .then(|| base.join(url).ok())?
+        })
+}
+
+fn with_param(url: &Url, name: &str, value: &str) -> Url {
+    let mut url = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != name)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(name, value);
+    url
+}
+
+fn request_for(template: &reqwest::Request, url: Url) -> Result<reqwest::Request> {
+    let mut request = template
+        .try_clone()
+        .ok_or(anyhow!("Unable to paginate {}", template.url()))?;
+    *request.url_mut() = url;
+    Ok(request)
+}
+
+/// Executes the request and the ones for the following pages with `fetch`,
+/// concatenating the items of every page. The status and headers of the first
+/// page are returned along with the items. Pagination stops at `maxPages`, or
+/// as soon as a page points to a url that was already fetched.
+pub async fn execute_paginated<Fetch, Fut>(
+    request: reqwest::Request,
+    paginate: &Paginate,
+    fetch: Fetch,
+) -> Result<Response<ConstValue>>
+where
+    Fetch: Fn(reqwest::Request) -> Fut,
+    Fut: Future<Output = Result<Response<ConstValue>>>,
+{
+    let template = request
+        .try_clone()
+        .ok_or(anyhow!("Unable to paginate {}", request.url()))?;
+    let param = paginate.get_param();
+    let mut page_number = template
+        .url()
+        .query_pairs()
+        .find(|(key, _)| key == param)
+        .and_then(|(_, value)| value.parse::<u64>().ok())
+        .unwrap_or(1);
+
+    let mut items = Vec::new();
+    let mut first: Option<Response<ConstValue>> = None;
+    let mut visited = HashSet::new();
+    let mut next = Some(request);
+
+    while let Some(request) = next.take() {
+        let url = request.url().clone();
+        visited.insert(url.clone());
+        let response = fetch(request).await?;
+        let link = next_link(&response.headers, &url);
+
+        let page = match response.body.get_path(&paginate.data_path) {
+            Some(ConstValue::List(page)) => page.clone(),
+            Some(ConstValue::Null) | None => Vec::new(),
+            Some(_) => {
+                return Err(anyhow!(
+                    "Expected a list of items at {:?} in the response of {}",
+                    paginate.data_path,
+                    url
+                ))
+            }
+        };
+        let cursor = match response.body.get_path(&paginate.cursor_path) {
+            Some(ConstValue::String(cursor)) if !cursor.is_empty() => Some(cursor.clone()),
+            Some(ConstValue::Number(cursor)) => Some(cursor.to_string()),
+            _ => None,
+        };
+
+        let is_empty = page.is_empty();
+        items.extend(page);
+        if first.is_none() {
+            first = Some(response);
+        }
+
+        if is_empty || visited.len() >= paginate.get_max_pages() {
+            break;
+        }
+
+        let next_url = match paginate.strategy {
+            PaginationStrategy::LinkHeader => link,
+            PaginationStrategy::Cursor => cursor.map(|cursor| with_param(&url, param, &cursor)),
+            PaginationStrategy::Page => {
+                page_number += 1;
+                Some(with_param(&url, param, &page_number.to_string()))
+            }
+        };
+        next = next_url
+            .filter(|next_url| !visited.contains(next_url))
+            .map(|next_url| request_for(&template, next_url))
+            .transpose()?;
+    }
+
+    let first = first.unwrap_or_default();
+    Ok(Response {
+        status: first.status,
+        headers: first.headers,
+        body: ConstValue::List(items),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use hyper::body::Bytes;
+    use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Serves the pages in order and records the requested urls
+    struct PagedHttp {
+        pages: Vec<(Option<&'static str>, serde_json::Value)>,
+        urls: Mutex<Vec<String>>,
+    }
+
+    impl PagedHttp {
+        fn new(pages: Vec<(Option<&'static str>, serde_json::Value)>) -> Self {
+            Self { pages, urls: Mutex::new(Vec::new()) }
+        }
+
+        fn urls(&self) -> Vec<String> {
+            self.urls.lock().unwrap().clone()
+        }
+    }
+
+    impl PagedHttp {
+        async fn fetch(&self, request: reqwest::Request) -> Result<Response<ConstValue>> {
+            let mut urls = self.urls.lock().unwrap();
+            urls.push(request.url().to_string());
+            let (link, body) = self.pages.get(urls.len() - 1).cloned().unwrap_or_default();
+            let mut response = Response::empty().body(Bytes::from(body.to_string()));
+            if let Some(link) = link {
+                response
+                    .headers
+                    .insert(LINK, HeaderValue::from_static(link));
+            }
+            response.to_json()
+        }
+    }
+
+    fn request(url: &str) -> reqwest::Request {
+        reqwest::Request::new(reqwest::Method::GET, url.parse().unwrap())
+    }
+
+    fn paginate(strategy: PaginationStrategy) -> Paginate {
+        Paginate {
+            strategy,
+            cursor_path: vec![],
+            data_path: vec![],
+            param: None,
+            max_pages: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_link_header() {
+        let http = PagedHttp::new(vec![
+            (
+                Some(r#"</users?page=2>; rel="next", </users?page=3>; rel="last""#),
+                json!([1, 2]),
+            ),
+            (
+                Some(r#"<http://a.com/users?page=3>; rel="next""#),
+                json!([3]),
+            ),
+            (None, json!([4])),
+        ]);
+        let response = execute_paginated(
+            request("http://a.com/users"),
+            &paginate(PaginationStrategy::LinkHeader),
+            |request| http.fetch(request),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.body.into_json().unwrap(), json!([1, 2, 3, 4]));
+        assert_eq!(
+            http.urls(),
+            vec![
+                "http://a.com/users",
+                "http://a.com/users?page=2",
+                "http://a.com/users?page=3"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cursor() {
+        let http = PagedHttp::new(vec![
+            (None, json!({"data": [1], "meta": {"next": "abc"}})),
+            (None, json!({"data": [2], "meta": {"next": ""}})),
+            (None, json!({"data": [3]})),
+        ]);
+        let paginate = Paginate {
+            cursor_path: vec!["meta".into(), "next".into()],
+            data_path: vec!["data".into()],
+            param: Some("after".into()),
+            ..paginate(PaginationStrategy::Cursor)
+        };
+        let response = execute_paginated(
+            request("http://a.com/users?limit=1"),
+            &paginate,
+            |request| http.fetch(request),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.body.into_json().unwrap(), json!([1, 2]));
+        assert_eq!(
+            http.urls(),
+            vec![
+                "http://a.com/users?limit=1",
+                "http://a.com/users?limit=1&after=abc"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_page_stops_on_empty_page() {
+        let http = PagedHttp::new(vec![
+            (None, json!([1])),
+            (None, json!([2])),
+            (None, json!([])),
+        ]);
+        let response = execute_paginated(
+            request("http://a.com/users?page=1"),
+            &paginate(PaginationStrategy::Page),
+            |request| http.fetch(request),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.body.into_json().unwrap(), json!([1, 2]));
+        assert_eq!(http.urls().len(), 3);
+        assert_eq!(http.urls()[2], "http://a.com/users?page=3");
+    }
+
+    #[tokio::test]
+    async fn test_max_pages() {
+        let http = PagedHttp::new(vec![
+            (None, json!([1])),
+            (None, json!([2])),
+            (None, json!([3])),
+        ]);
+        let paginate = Paginate { max_pages: Some(2), ..paginate(PaginationStrategy::Page) };
+        let response = execute_paginated(request("http://a.com/users"), &paginate, |request| {
+            http.fetch(request)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(response.body.into_json().unwrap(), json!([1, 2]));
+        assert_eq!(http.urls()[1], "http://a.com/users?page=2");
+    }
+
+    #[tokio::test]
+    async fn test_repeated_cursor() {
+        let http = PagedHttp::new(vec![
+            (None, json!({"data": [1], "next": "abc"})),
+            (None, json!({"data": [2], "next": "abc"})),
+            (None, json!({"data": [3], "next": "def"})),
+        ]);
+        let paginate = Paginate {
+            cursor_path: vec!["next".into()],
+            data_path: vec!["data".into()],
+            ..paginate(PaginationStrategy::Cursor)
+        };
+        let response = execute_paginated(request("http://a.com/users"), &paginate, |request| {
+            http.fetch(request)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(response.body.into_json().unwrap(), json!([1, 2]));
+        assert_eq!(http.urls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_link_header_cycle() {
+        let http = PagedHttp::new(vec![
+            (Some(r#"</users?page=2>; rel="next""#), json!([1])),
+            (Some(r#"</users>; rel="next""#), json!([2])),
+            (Some(r#"</users?page=2>; rel="next""#), json!([3])),
+        ]);
+        let response = execute_paginated(
+            request("http://a.com/users"),
+            &paginate(PaginationStrategy::LinkHeader),
+            |request| http.fetch(request),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.body.into_json().unwrap(), json!([1, 2]));
+        assert_eq!(http.urls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_default_max_pages() {
+        let fetched = Mutex::new(0);
+        let response = execute_paginated(
+            request("http://a.com/users"),
+            &paginate(PaginationStrategy::Page),
+            |_| {
+                *fetched.lock().unwrap() += 1;
+                let response = Response::empty().body(Bytes::from("[1]"));
+                async { response.to_json() }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(*fetched.lock().unwrap(), 100);
+        assert_eq!(response.body.into_json().unwrap(), json!(vec![1; 100]));
+    }
+
+    #[tokio::test]
+    async fn test_items_must_be_a_list() {
+        let http = PagedHttp::new(vec![(None, json!({"id": 1}))]);
+        let result = execute_paginated(
+            request("http://a.com/users"),
+            &paginate(PaginationStrategy::Page),
+            |request| http.fetch(request),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -1,20 +1,22 @@
 use std::num::NonZeroU64;
 use std::sync::{Arc, Mutex};
 
-use async_graphql_value::ConstValue;
 use cache_control::{Cachability, CacheControl};
 use derive_setters::Setters;
+use hyper::header::{HeaderName, HeaderValue};
 use hyper::HeaderMap;
 
 use crate::blueprint::{Server, Upstream};
+use crate::config::{HeaderMerge, PropagateHeader};
 use crate::data_loader::DataLoader;
 use crate::graphql::GraphqlDataLoader;
 use crate::grpc;
 use crate::grpc::data_loader::GrpcDataLoader;
 use crate::http::{AppContext, DataLoaderRequest, HttpDataLoader};
+use crate::lambda::CachedValue;
 use crate::runtime::TargetRuntime;
 
-#[derive(Setters)]
+#[derive(Clone, Setters)]
 pub struct RequestContext {
     pub server: Server,
     pub upstream: Upstream,
@@ -24,6 +26,8 @@ pub struct RequestContext {
     pub grpc_data_loaders: Arc<Vec<DataLoader<grpc::DataLoaderRequest, GrpcDataLoader>>>,
     pub min_max_age: Arc<Mutex<Option<i32>>>,
     pub cache_public: Arc<Mutex<Option<bool>>>,
+    pub response_headers: Arc<Mutex<HeaderMap>>,
+    pub grpc_metadata: Arc<Mutex<HeaderMap>>,
     pub runtime: TargetRuntime,
 }
 
@@ -72,7 +76,73 @@ impl RequestContext {
         }
     }
 
-    pub async fn cache_get(&self, key: &u64) -> anyhow::Result<Option<ConstValue>> {
+    /// Copies the headers of an upstream response listed in `rules` into the
+    /// headers returned to the client, merging them with the values returned
+    /// by the other upstreams.
+    pub fn propagate_headers(&self, headers: &HeaderMap, rules: &[PropagateHeader]) {
+        if rules.is_empty() {
+            return;
+        }
+        let mut response_headers = self.response_headers.lock().unwrap();
+        for rule in rules {
+            let Ok(name) = HeaderName::from_bytes(rule.name.as_bytes()) else {
+                continue;
+            };
+            for value in headers.get_all(&name) {
+                let keep_existing = match rule.merge {
+                    HeaderMerge::Append => {
+                        response_headers.append(&name, value.clone());
+                        continue;
+                    }
+                    HeaderMerge::First => response_headers.contains_key(&name),
+                    HeaderMerge::Last => false,
+                    HeaderMerge::Min | HeaderMerge::Max => {
+                        match (
+                            to_number(response_headers.get(&name)),
+                            to_number(Some(value)),
+                        ) {
+                            (_, None) => true,
+                            (Some(existing), Some(new)) if rule.merge == HeaderMerge::Min => {
+                                existing <= new
+                            }
+                            (Some(existing), Some(new)) => existing >= new,
+                            (None, Some(_)) => false,
+                        }
+                    }
+                };
+                if !keep_existing {
+                    response_headers.insert(&name, value.clone());
+                }
+            }
+        }
+    }
+
+    pub fn get_response_headers(&self) -> HeaderMap {
+        self.response_headers.lock().unwrap().clone()
+    }
+
+    /// Keeps the metadata of a gRPC response listed in `names`, to be returned
+    /// in the extensions of the GraphQL response.
+    pub fn forward_grpc_metadata(&self, headers: &HeaderMap, names: &[String]) {
+        if names.is_empty() {
+            return;
+        }
+        let mut grpc_metadata = self.grpc_metadata.lock().unwrap();
+        for name in names {
+            let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+                continue;
+            };
+            for value in headers.get_all(&name) {
+                grpc_metadata.append(&name, value.clone());
+            }
+        }
+    }
+
+    pub fn get_grpc_metadata(&self) -> HeaderMap {
+        self.grpc_metadata.lock().unwrap().clone()
+    }
+
+    pub async fn cache_get(&self, key: &u64) -> anyhow::Result<Option<CachedValue>> {
         self.runtime.cache.get(key).await
     }
 
@@ -80,7 +150,7 @@ impl RequestContext {
     pub async fn cache_insert(
         &self,
         key: u64,
-        value: ConstValue,
+        value: CachedValue,
         ttl: NonZeroU64,
     ) -> anyhow::Result<()> {
         self.runtime.cache.set(key, value, ttl).await
@@ -91,6 +161,10 @@ impl RequestContext {
     }
 }
 
+fn to_number(value: Option<&HeaderValue>) -> Option<f64> {
+    value?.to_str().ok()?.trim().parse().ok()
+}
+
 impl From<&AppContext> for RequestContext {
     fn from(app_ctx: &AppContext) -> Self {
         Self {
@@ -102,6 +176,8 @@ impl From<&AppContext> for RequestContext {
             grpc_data_loaders: app_ctx.grpc_data_loaders.clone(),
             min_max_age: Arc::new(Mutex::new(None)),
             cache_public: Arc::new(Mutex::new(None)),
+            response_headers: Arc::new(Mutex::new(HeaderMap::new())),
+            grpc_metadata: Arc::new(Mutex::new(HeaderMap::new())),
             runtime: app_ctx.runtime.clone(),
         }
     }
@@ -112,10 +188,11 @@ mod test {
     use std::sync::{Arc, Mutex};
 
     use cache_control::Cachability;
+    use hyper::header::HeaderValue;
     use hyper::HeaderMap;
 
     use crate::blueprint::{Server, Upstream};
-    use crate::config::{self, Batch};
+    use crate::config::{self, Batch, HeaderMerge, PropagateHeader};
     use crate::http::RequestContext;
 
     impl Default for RequestContext {
@@ -135,6 +212,8 @@ mod test {
                 grpc_data_loaders: Arc::new(vec![]),
                 min_max_age: Arc::new(Mutex::new(None)),
                 cache_public: Arc::new(Mutex::new(None)),
+                response_headers: Arc::new(Mutex::new(HeaderMap::new())),
+                grpc_metadata: Arc::new(Mutex::new(HeaderMap::new())),
             }
         }
     }
@@ -176,6 +255,63 @@ mod test {
         assert_eq!(req_ctx.is_cache_public(), None);
     }
 
+    fn upstream_headers(name: &'static str, values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn propagate(merge: HeaderMerge, responses: &[&[&'static str]]) -> Vec<String> {
+        let req_ctx = RequestContext::default();
+        let rules = [PropagateHeader { name: "x-header".to_string(), merge }];
+        for values in responses {
+            req_ctx.propagate_headers(&upstream_headers("x-header", values), &rules);
+        }
+        req_ctx
+            .get_response_headers()
+            .get_all("x-header")
+            .iter()
+            .map(|value| value.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_propagate_headers_merge() {
+        let responses: &[&[&str]] = &[&["10"], &[], &["5", "7"], &["20"]];
+
+        assert_eq!(
+            propagate(HeaderMerge::Append, responses),
+            ["10", "5", "7", "20"]
+        );
+        assert_eq!(propagate(HeaderMerge::First, responses), ["10"]);
+        assert_eq!(propagate(HeaderMerge::Last, responses), ["20"]);
+        assert_eq!(propagate(HeaderMerge::Min, responses), ["5"]);
+        assert_eq!(propagate(HeaderMerge::Max, responses), ["20"]);
+    }
+
+    #[test]
+    fn test_propagate_headers_min_ignores_non_numeric_values() {
+        let responses: &[&[&str]] = &[&["abc"], &["3"], &["n/a"]];
+
+        assert_eq!(propagate(HeaderMerge::Min, responses), ["3"]);
+    }
+
+    #[test]
+    fn test_propagate_only_listed_headers() {
+        let req_ctx = RequestContext::default();
+        let rules =
+            [PropagateHeader { name: "set-cookie".to_string(), merge: HeaderMerge::Append }];
+        let mut headers = upstream_headers("set-cookie", &["a=1"]);
+        headers.insert("x-other", HeaderValue::from_static("1"));
+        req_ctx.propagate_headers(&headers, &rules);
+
+        let response_headers = req_ctx.get_response_headers();
+        assert_eq!(response_headers.len(), 1);
+        assert_eq!(response_headers.get("set-cookie").unwrap(), "a=1");
+    }
+
     #[test]
     fn test_is_batching_enabled_default() {
         // create ctx with default batch
@@ -5,12 +5,15 @@ use std::sync::Arc;
 use anyhow::Result;
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
 use async_graphql::ServerError;
+use async_graphql_value::{ConstValue, Name};
+use hyper::header::ACCEPT;
 use hyper::{Body, HeaderMap, Request, Response, StatusCode};
+use indexmap::IndexMap;
 use serde::de::DeserializeOwned;
 
 use super::request_context::RequestContext;
 use super::{showcase, AppContext};
-use crate::async_graphql_hyper::{GraphQLRequestLike, GraphQLResponse};
+use crate::async_graphql_hyper::{to_event_stream, GraphQLRequestLike, GraphQLResponse};
 
 pub fn graphiql(req: &Request<Body>) -> Result<Response<Body>> {
     let query = req.uri().query();
@@ -56,6 +59,41 @@ fn update_cache_control_header(
     response
 }
 
+/// Returns the forwarded gRPC metadata in the `grpcMetadata` extension, the
+/// values of each name being listed in the order they were received.
+fn update_grpc_metadata(response: GraphQLResponse, req_ctx: &RequestContext) -> GraphQLResponse {
+    let metadata = req_ctx.get_grpc_metadata();
+    if metadata.is_empty() {
+        return response;
+    }
+
+    let mut extension = IndexMap::new();
+    for name in metadata.keys() {
+        let values = metadata
+            .get_all(name)
+            .iter()
+            .map(|value| ConstValue::String(String::from_utf8_lossy(value.as_bytes()).to_string()))
+            .collect();
+        extension.insert(Name::new(name.as_str()), ConstValue::List(values));
+    }
+    response.set_extension("grpcMetadata", ConstValue::Object(extension))
+}
+
+/// Adds the headers propagated from the upstream responses, leaving the ones
+/// already set by the server untouched.
+fn propagate_response_headers(resp: &mut Response<Body>, req_ctx: &RequestContext) {
+    let propagated = req_ctx.get_response_headers();
+    let headers = resp.headers_mut();
+    for name in propagated.keys() {
+        if headers.contains_key(name) {
+            continue;
+        }
+        for value in propagated.get_all(name) {
+            headers.append(name, value.clone());
+        }
+    }
+}
+
 pub fn update_response_headers(resp: &mut hyper::Response<hyper::Body>, app_ctx: &AppContext) {
     if !app_ctx.blueprint.server.response_headers.is_empty() {
         resp.headers_mut()
@@ -68,13 +106,24 @@ pub async fn graphql_request<T: DeserializeOwned + GraphQLRequestLike>(
     app_ctx: &AppContext,
 ) -> Result<Response<Body>> {
     let req_ctx = Arc::new(create_request_context(&req, app_ctx));
+    let event_stream = accepts_event_stream(req.headers());
     let bytes = hyper::body::to_bytes(req.into_body()).await?;
     let request = serde_json::from_slice::<T>(&bytes);
     match request {
+        Ok(request) if event_stream => {
+            let responses = request
+                .data(req_ctx.clone())
+                .execute_stream(&app_ctx.schema);
+            let mut resp = to_event_stream(responses)?;
+            update_response_headers(&mut resp, app_ctx);
+            Ok(resp)
+        }
         Ok(request) => {
             let mut response = request.data(req_ctx.clone()).execute(&app_ctx.schema).await;
-            response = update_cache_control_header(response, app_ctx, req_ctx);
+            response = update_cache_control_header(response, app_ctx, req_ctx.clone());
+            response = update_grpc_metadata(response, &req_ctx);
             let mut resp = response.to_response()?;
+            propagate_response_headers(&mut resp, &req_ctx);
             update_response_headers(&mut resp, app_ctx);
             Ok(resp)
         }
@@ -94,6 +143,15 @@ pub async fn graphql_request<T: DeserializeOwned + GraphQLRequestLike>(
     }
 }
 
+/// Subscriptions are served as Server-Sent Events, when the client accepts
+/// them.
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.contains("text/event-stream"))
+}
+
 fn create_allowed_headers(headers: &HeaderMap, allowed: &BTreeSet<String>) -> HeaderMap {
     let mut new_headers = HeaderMap::new();
     for (k, v) in headers.iter() {
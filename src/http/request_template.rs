@@ -2,6 +2,8 @@ use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use derive_setters::Setters;
 use hyper::HeaderMap;
 use reqwest::header::HeaderValue;
@@ -116,7 +118,7 @@ impl RequestTemplate {
     ) -> anyhow::Result<reqwest::Request> {
         if let Some(body_path) = &self.body_path {
             match &self.encoding {
-                Encoding::ApplicationJson => {
+                Encoding::ApplicationJson | Encoding::TextPlain => {
                     req.body_mut().replace(body_path.render(ctx).into());
                 }
                 Encoding::ApplicationXWwwFormUrlencoded => {
@@ -130,6 +132,26 @@ impl RequestTemplate {
 
                     req.body_mut().replace(form_data.into());
                 }
+                Encoding::MultipartFormData => {
+                    let (boundary, form_data) = to_multipart(&body_path.render(ctx))?;
+                    req.headers_mut().insert(
+                        reqwest::header::CONTENT_TYPE,
+                        HeaderValue::from_str(&format!(
+                            "multipart/form-data; boundary={}",
+                            boundary
+                        ))?,
+                    );
+                    req.body_mut().replace(form_data.into());
+                }
+                Encoding::ApplicationXml => {
+                    let body: String = body_path.render(ctx);
+                    let xml = match serde_json::from_str::<serde_json::Value>(&body) {
+                        Ok(serde_json::Value::Object(map)) => to_xml_document(&map)?,
+                        _ => body,
+                    };
+
+                    req.body_mut().replace(xml.into());
+                }
             }
         }
         Ok(req)
@@ -158,6 +180,10 @@ impl RequestTemplate {
                     Encoding::ApplicationXWwwFormUrlencoded => {
                         HeaderValue::from_static("application/x-www-form-urlencoded")
                     }
+                    // the boundary is only known once the body is rendered
+                    Encoding::MultipartFormData => HeaderValue::from_static("multipart/form-data"),
+                    Encoding::TextPlain => HeaderValue::from_static("text/plain"),
+                    Encoding::ApplicationXml => HeaderValue::from_static("application/xml"),
                 },
             );
         }
@@ -188,6 +214,147 @@ impl RequestTemplate {
     }
 }
 
+/// Escapes the quotes and line breaks of a name in a `Content-Disposition`
+/// header, the way browsers do.
+fn escape_part_name(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Reads the content of a file of a multipart form, which is text unless its
+/// `contentEncoding` is `base64`.
+fn to_file_content(
+    file: &serde_json::Map<String, serde_json::Value>,
+    content: &str,
+    name: &str,
+) -> anyhow::Result<Vec<u8>> {
+    match file.get("contentEncoding").and_then(|value| value.as_str()) {
+        None => Ok(content.as_bytes().to_vec()),
+        Some("base64") => STANDARD
+            .decode(content)
+            .map_err(|_| anyhow::anyhow!("Content of file {} is not base64", name)),
+        Some(encoding) => {
+            anyhow::bail!("Unsupported content encoding {} of file {}", encoding, name)
+        }
+    }
+}
+
+/// Encodes every field of a JSON object as a part of a multipart form. List
+/// values are sent as repeated parts and non string values as JSON, except
+/// for objects with a `filename` and a `content`, which are sent as files
+/// with their optional `contentType` and `contentEncoding`. The boundary is
+/// derived from the body so the same body is always encoded the same way.
+fn to_multipart(body: &str) -> anyhow::Result<(String, Vec<u8>)> {
+    let serde_json::Value::Object(fields) = serde_json::from_str(body)? else {
+        anyhow::bail!("Multipart form data can only be created from an object");
+    };
+
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(body.as_bytes());
+    let boundary = format!("tailcall-{:016x}", hasher.finish());
+
+    let mut form_data = Vec::new();
+    for (name, value) in fields.iter() {
+        let values = match value {
+            serde_json::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        let name = escape_part_name(name);
+        for value in values {
+            form_data.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+                    boundary, name
+                )
+                .as_bytes(),
+            );
+            let value = match value {
+                serde_json::Value::Object(file) if file.contains_key("filename") => {
+                    let filename = file.get("filename").and_then(|value| value.as_str());
+                    let content = file.get("content").and_then(|value| value.as_str());
+                    let (Some(filename), Some(content)) = (filename, content) else {
+                        anyhow::bail!("File {} must have a string filename and content", name);
+                    };
+                    let content_type = file
+                        .get("contentType")
+                        .and_then(|value| value.as_str())
+                        .unwrap_or("application/octet-stream");
+                    let content = to_file_content(file, content, &name)?;
+                    form_data.extend_from_slice(
+                        format!(
+                            "; filename=\"{}\"\r\nContent-Type: {}",
+                            escape_part_name(filename),
+                            content_type.replace(['\r', '\n'], "")
+                        )
+                        .as_bytes(),
+                    );
+                    content
+                }
+                serde_json::Value::String(value) => value.as_bytes().to_vec(),
+                value => value.to_string().into_bytes(),
+            };
+            form_data.extend_from_slice(b"\r\n\r\n");
+            form_data.extend_from_slice(&value);
+            form_data.extend_from_slice(b"\r\n");
+        }
+    }
+    form_data.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    Ok((boundary, form_data))
+}
+
+/// Encodes a JSON object as an XML document. An object with a single field
+/// is that field's element, any other object is wrapped in a `root` element
+/// so the document has a single root.
+fn to_xml_document(fields: &serde_json::Map<String, serde_json::Value>) -> anyhow::Result<String> {
+    let xml = to_xml(fields)?;
+    match fields.values().next() {
+        Some(value) if fields.len() == 1 && !value.is_array() => Ok(xml),
+        _ => Ok(format!("<root>{}</root>", xml)),
+    }
+}
+
+/// Checks that a field can be used as the name of an XML element.
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = chars.next();
+    first.is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !name.to_lowercase().starts_with("xml")
+}
+
+/// Encodes every field of a JSON object as an XML element. List values are
+/// sent as repeated elements.
+fn to_xml(fields: &serde_json::Map<String, serde_json::Value>) -> anyhow::Result<String> {
+    let mut xml = String::new();
+    for (name, value) in fields.iter() {
+        if !is_xml_name(name) {
+            anyhow::bail!("{} is not a valid XML element name", name);
+        }
+        let values = match value {
+            serde_json::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                serde_json::Value::Null => xml.push_str(&format!("<{}/>", name)),
+                serde_json::Value::Object(fields) => {
+                    xml.push_str(&format!("<{}>{}</{}>", name, to_xml(fields)?, name))
+                }
+                serde_json::Value::String(value) => xml.push_str(&format!(
+                    "<{}>{}</{}>",
+                    name,
+                    quick_xml::escape::escape(value),
+                    name
+                )),
+                value => xml.push_str(&format!("<{}>{}</{}>", name, value, name)),
+            }
+        }
+    }
+    Ok(xml)
+}
+
 impl TryFrom<Endpoint> for RequestTemplate {
     type Error = anyhow::Error;
     fn try_from(endpoint: Endpoint) -> anyhow::Result<Self> {
@@ -229,6 +396,7 @@ impl<Ctx: PathString + HasHeaders> CacheKey<Ctx> for RequestTemplate {
         let state = &mut hasher;
 
         self.method.hash(state);
+        self.encoding.hash(state);
 
         let mut headers = vec![];
         for (name, mustache) in self.headers.iter() {
@@ -474,6 +642,16 @@ mod tests {
         );
     }
     #[test]
+    fn test_header_encoding_text_plain() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .method(reqwest::Method::POST)
+            .encoding(crate::config::Encoding::TextPlain);
+        let ctx = Context::default();
+        let req = tmpl.to_request(&ctx).unwrap();
+        assert_eq!(req.headers().get("Content-Type").unwrap(), "text/plain");
+    }
+    #[test]
     fn test_method() {
         let tmpl = RequestTemplate::new("http://localhost:3000")
             .unwrap()
@@ -519,6 +697,178 @@ mod tests {
         assert_eq!(body, "baz");
     }
 
+    #[test]
+    fn test_body_encoding_multipart_form_data() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .method(reqwest::Method::POST)
+            .encoding(crate::config::Encoding::MultipartFormData)
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let ctx = Context::default().value(json!({
+          "foo": {
+            "name": "baz",
+            "tags": ["a", "b"],
+            "meta": {"id": 1}
+          }
+        }));
+        let req = tmpl.to_request(&ctx).unwrap();
+        let content_type = req.headers().get("Content-Type").unwrap().to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = tmpl.to_body(&ctx).unwrap();
+        let part = |name: &str, value: &str| {
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+        };
+        let expected = [
+            part("name", "baz"),
+            part("tags", "a"),
+            part("tags", "b"),
+            part("meta", r#"{"id":1}"#),
+            format!("--{}--\r\n", boundary),
+        ]
+        .concat();
+        assert_eq!(body, expected);
+    }
+    #[test]
+    fn test_body_encoding_multipart_form_data_files() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .method(reqwest::Method::POST)
+            .encoding(crate::config::Encoding::MultipartFormData)
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let ctx = Context::default().value(json!({
+          "foo": {
+            "my \"doc\"": {
+              "filename": "a.txt",
+              "contentType": "text/plain",
+              "content": "hello"
+            }
+          }
+        }));
+        let body = tmpl.to_body(&ctx).unwrap();
+        let boundary = body.lines().next().unwrap().strip_prefix("--").unwrap();
+        let expected = format!(
+            "--{0}\r\nContent-Disposition: form-data; name=\"my %22doc%22\"; filename=\"a.txt\"\r\nContent-Type: text/plain\r\n\r\nhello\r\n--{0}--\r\n",
+            boundary
+        );
+        assert_eq!(body, expected);
+    }
+    #[test]
+    fn test_body_encoding_multipart_form_data_binary_files() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .method(reqwest::Method::POST)
+            .encoding(crate::config::Encoding::MultipartFormData)
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let ctx = Context::default().value(json!({
+          "foo": {
+            "image": {
+              "filename": "a.png",
+              "contentType": "image/png",
+              "contentEncoding": "base64",
+              "content": "iVBORw0KGgr/AA=="
+            }
+          }
+        }));
+        let req = tmpl.to_request(&ctx).unwrap();
+        let body = req.body().and_then(|body| body.as_bytes()).unwrap();
+        let boundary = req.headers()["Content-Type"]
+            .to_str()
+            .unwrap()
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let expected = [
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\n",
+                boundary
+            )
+            .as_bytes(),
+            b"\x89PNG\r\n\x1a\n\xff\x00",
+            format!("\r\n--{}--\r\n", boundary).as_bytes(),
+        ]
+        .concat();
+        assert_eq!(body, expected);
+        assert!(std::str::from_utf8(body).is_err());
+    }
+    #[test]
+    fn test_body_encoding_multipart_form_data_invalid_base64() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .encoding(crate::config::Encoding::MultipartFormData)
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let ctx = Context::default().value(json!({
+          "foo": {
+            "image": {"filename": "a.png", "contentEncoding": "base64", "content": "not base64!"}
+          }
+        }));
+        assert!(tmpl.to_request(&ctx).is_err());
+    }
+    #[test]
+    fn test_body_encoding_multipart_form_data_requires_object() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .encoding(crate::config::Encoding::MultipartFormData)
+            .body_path(Some(Mustache::parse("foo").unwrap()));
+        let ctx = Context::default();
+        assert!(tmpl.to_request(&ctx).is_err());
+    }
+    #[test]
+    fn test_body_encoding_application_xml() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .encoding(crate::config::Encoding::ApplicationXml)
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let ctx = Context::default().value(json!({
+          "foo": {
+            "user": {"name": "a & b", "tags": ["x", "y"], "id": 1, "bio": null}
+          }
+        }));
+        let body = tmpl.to_body(&ctx).unwrap();
+        assert_eq!(
+            body,
+            "<user><name>a &amp; b</name><tags>x</tags><tags>y</tags><id>1</id><bio/></user>"
+        );
+    }
+    #[test]
+    fn test_body_encoding_application_xml_root() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .encoding(crate::config::Encoding::ApplicationXml)
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let ctx = Context::default().value(json!({"foo": {"id": 1, "name": "a"}}));
+        let body = tmpl.to_body(&ctx).unwrap();
+        assert_eq!(body, "<root><id>1</id><name>a</name></root>");
+
+        let ctx = Context::default().value(json!({"foo": {"a b": 1}}));
+        let error = tmpl.to_request(&ctx).unwrap_err();
+        assert_eq!(error.to_string(), "a b is not a valid XML element name");
+    }
+    #[test]
+    fn test_body_encoding_application_xml_template() {
+        let tmpl = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .encoding(crate::config::Encoding::ApplicationXml)
+            .body_path(Some(Mustache::parse("<id>{{foo}}</id>").unwrap()));
+        let ctx = Context::default().value(json!({"foo": 1}));
+        let body = tmpl.to_body(&ctx).unwrap();
+        assert_eq!(body, "<id>1</id>");
+    }
+    #[test]
+    fn test_cache_key_encoding() {
+        use crate::lambda::CacheKey;
+
+        let json = RequestTemplate::new("http://localhost:3000")
+            .unwrap()
+            .body_path(Some(Mustache::parse("{{foo}}").unwrap()));
+        let text = json.clone().encoding(crate::config::Encoding::TextPlain);
+        let ctx = Context::default().value(json!({"foo": "bar"}));
+        assert_ne!(json.cache_key(&ctx), text.cache_key(&ctx));
+    }
+
     mod endpoint {
         use hyper::HeaderMap;
         use serde_json::json;
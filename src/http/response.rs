@@ -1,9 +1,45 @@
 use anyhow::Result;
 use derive_setters::Setters;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{future, FutureExt, StreamExt};
 use hyper::body::Bytes;
 
+use crate::config::ResponseFormat;
+use crate::grpc::compression::grpc_encoding;
 use crate::grpc::protobuf::ProtobufOperation;
 
+/// The upstream answered with a client or server error status. Unlike
+/// `reqwest::Error` the body of the response is kept.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("HTTP status {status} for url ({url})")]
+pub struct StatusError {
+    pub status: reqwest::StatusCode,
+    pub url: String,
+    pub body: Bytes,
+}
+
+/// A response whose body is read as it arrives.
+pub struct StreamResponse {
+    pub status: reqwest::StatusCode,
+    pub headers: reqwest::header::HeaderMap,
+    pub body: BoxStream<'static, Result<Bytes>>,
+    /// The trailers sent after the body, known once the body has been read to
+    /// the end. Resolves to `None` when the client can't read them.
+    pub trailers: BoxFuture<'static, Option<reqwest::header::HeaderMap>>,
+}
+
+impl From<Response<Bytes>> for StreamResponse {
+    fn from(response: Response<Bytes>) -> Self {
+        StreamResponse {
+            status: response.status,
+            headers: response.headers,
+            body: stream::once(future::ready(Ok(response.body))).boxed(),
+            trailers: future::ready(None).boxed(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Setters)]
 pub struct Response<Body: Default + Clone> {
     pub status: reqwest::StatusCode,
@@ -18,6 +54,17 @@ impl Response<Bytes> {
         let body = resp.bytes().await?;
         Ok(Response { status, headers, body })
     }
+    /// Like [Response::from_reqwest], but fails with a [StatusError] when the
+    /// upstream answers with an error status.
+    pub async fn from_reqwest_checked(resp: reqwest::Response) -> Result<Self> {
+        let url = resp.url().to_string();
+        let response = Self::from_reqwest(resp).await?;
+        if response.status.is_client_error() || response.status.is_server_error() {
+            return Err(StatusError { status: response.status, url, body: response.body }.into());
+        }
+        Ok(response)
+    }
+
     pub fn empty() -> Self {
         Response {
             status: reqwest::StatusCode::OK,
@@ -35,12 +82,25 @@ impl Response<Bytes> {
         Ok(resp)
     }
 
+    /// Decodes the body in the given format, or the one detected from the
+    /// response headers.
+    pub fn to_value(
+        self,
+        format: Option<&ResponseFormat>,
+    ) -> Result<Response<async_graphql::Value>> {
+        let format = format
+            .cloned()
+            .unwrap_or_else(|| ResponseFormat::detect(&self.headers));
+        let body = format.decode(&self.body)?;
+        Ok(Response { status: self.status, headers: self.headers, body })
+    }
+
     pub fn to_grpc_value(
         self,
         operation: &ProtobufOperation,
     ) -> Result<Response<async_graphql::Value>> {
         let mut resp = Response::default();
-        let body = operation.convert_output(&self.body)?;
+        let body = operation.convert_output(&self.body, grpc_encoding(&self.headers))?;
         resp.body = body;
         resp.status = self.status;
         resp.headers = self.headers;
@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use async_graphql_value::{ConstValue, Name};
+use indexmap::IndexMap;
+use quick_xml::events::{BytesStart, Event};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+
+use crate::config::ResponseFormat;
+
+impl ResponseFormat {
+    /// Detects the format from the `Content-Type` header, falling back to
+    /// JSON.
+    pub fn detect(headers: &HeaderMap) -> Self {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if content_type.contains("xml") {
+            ResponseFormat::Xml
+        } else if content_type.contains("csv") {
+            ResponseFormat::Csv
+        } else if content_type.starts_with("text/") {
+            ResponseFormat::Text
+        } else {
+            ResponseFormat::Json
+        }
+    }
+
+    pub fn decode(&self, body: &[u8]) -> Result<ConstValue> {
+        match self {
+            ResponseFormat::Json => Ok(serde_json::from_slice(body)?),
+            ResponseFormat::Xml => decode_xml(body),
+            ResponseFormat::Csv => decode_csv(body),
+            ResponseFormat::Text => Ok(ConstValue::String(String::from_utf8(body.to_vec())?)),
+        }
+    }
+}
+
+/// Converts text into a number or a boolean when that doesn't lose
+/// information, so `"01"` stays a string.
+fn to_scalar(text: &str) -> ConstValue {
+    if text.is_empty() {
+        return ConstValue::Null;
+    }
+    match text {
+        "true" => return ConstValue::Boolean(true),
+        "false" => return ConstValue::Boolean(false),
+        _ => {}
+    }
+    if let Ok(number) = text.parse::<i64>() {
+        if number.to_string() == text {
+            return ConstValue::Number(number.into());
+        }
+    }
+    if let Ok(number) = text.parse::<f64>() {
+        if number.to_string() == text {
+            if let Some(number) = serde_json::Number::from_f64(number) {
+                return ConstValue::Number(number);
+            }
+        }
+    }
+    ConstValue::String(text.to_string())
+}
+
+/// Every row becomes an object keyed by the column names of the header row.
+fn decode_csv(body: &[u8]) -> Result<ConstValue> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let row = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(name, value)| (Name::new(name), to_scalar(value)))
+            .collect();
+        rows.push(ConstValue::Object(row));
+    }
+
+    Ok(ConstValue::List(rows))
+}
+
+#[derive(Default)]
+struct XmlNode {
+    name: String,
+    fields: IndexMap<Name, ConstValue>,
+    text: String,
+}
+
+impl XmlNode {
+    fn new(start: &BytesStart) -> Result<Self> {
+        let name = String::from_utf8(start.local_name().as_ref().to_vec())?;
+        let mut fields = IndexMap::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            if attribute.key.as_namespace_binding().is_some() {
+                continue;
+            }
+            let key = String::from_utf8(attribute.key.local_name().as_ref().to_vec())?;
+            fields.insert(Name::new(key), to_scalar(&attribute.unescape_value()?));
+        }
+
+        Ok(Self { name, fields, text: String::new() })
+    }
+
+    fn insert(&mut self, name: String, value: ConstValue) {
+        match self.fields.get_mut(name.as_str()) {
+            Some(ConstValue::List(list)) => list.push(value),
+            Some(existing) => {
+                let first = std::mem::take(existing);
+                *existing = ConstValue::List(vec![first, value]);
+            }
+            None => {
+                self.fields.insert(Name::new(name), value);
+            }
+        }
+    }
+
+    /// Elements holding only text become scalars, the text of elements that
+    /// also have attributes or children is kept in a `text` field.
+    fn into_value(self) -> ConstValue {
+        let text = self.text.trim();
+        if self.fields.is_empty() {
+            return to_scalar(text);
+        }
+
+        let mut fields = self.fields;
+        if !text.is_empty() {
+            fields.insert(Name::new("text"), to_scalar(text));
+        }
+        ConstValue::Object(fields)
+    }
+}
+
+/// Converts the root element into an object, with attributes and child
+/// elements as fields. Repeated child elements are collected into lists.
+fn decode_xml(body: &[u8]) -> Result<ConstValue> {
+    let mut reader = quick_xml::Reader::from_reader(body);
+    let mut stack: Vec<XmlNode> = vec![XmlNode::default()];
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(XmlNode::new(&start)?),
+            Event::Empty(start) => {
+                let node = XmlNode::new(&start)?;
+                let parent = stack.last_mut().ok_or(anyhow!("Malformed XML"))?;
+                parent.insert(node.name.clone(), node.into_value());
+            }
+            Event::End(_) => {
+                let node = stack.pop().ok_or(anyhow!("Malformed XML"))?;
+                let parent = stack.last_mut().ok_or(anyhow!("Malformed XML"))?;
+                parent.insert(node.name.clone(), node.into_value());
+            }
+            Event::Text(text) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(std::str::from_utf8(&data.into_inner())?);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let document = stack.pop().filter(|_| stack.is_empty());
+    document
+        .and_then(|document| document.fields.into_values().next())
+        .ok_or(anyhow!("Malformed XML"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn decode(format: ResponseFormat, body: &str) -> serde_json::Value {
+        format.decode(body.as_bytes()).unwrap().into_json().unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(ResponseFormat::detect(&headers), ResponseFormat::Json);
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/xml; charset=utf-8"),
+        );
+        assert_eq!(ResponseFormat::detect(&headers), ResponseFormat::Xml);
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/csv"));
+        assert_eq!(ResponseFormat::detect(&headers), ResponseFormat::Csv);
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        assert_eq!(ResponseFormat::detect(&headers), ResponseFormat::Text);
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        assert_eq!(ResponseFormat::detect(&headers), ResponseFormat::Json);
+    }
+
+    #[test]
+    fn test_xml() {
+        let body = r#"<?xml version="1.0"?>
+            <users xmlns="urn:users">
+                <user id="1"><name>Leanne &amp; co</name><zip>01234</zip></user>
+                <user id="2" active="true"><name><![CDATA[Ervin]]></name></user>
+                <note lang="en">Hello</note>
+                <empty/>
+            </users>"#;
+
+        assert_eq!(
+            decode(ResponseFormat::Xml, body),
+            json!({
+                "user": [
+                    {"id": 1, "name": "Leanne & co", "zip": "01234"},
+                    {"id": 2, "active": true, "name": "Ervin"}
+                ],
+                "note": {"lang": "en", "text": "Hello"},
+                "empty": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_malformed_xml() {
+        assert!(ResponseFormat::Xml.decode(b"<a><b></a>").is_err());
+    }
+
+    #[test]
+    fn test_csv() {
+        let body = "id, name, score\n1, Leanne, 1.5\n2,\"Ervin, Jr\",\n";
+
+        assert_eq!(
+            decode(ResponseFormat::Csv, body),
+            json!([
+                {"id": 1, "name": "Leanne", "score": 1.5},
+                {"id": 2, "name": "Ervin, Jr", "score": null}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(decode(ResponseFormat::Text, "1"), json!("1"));
+    }
+}
@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use prost_reflect::{FieldDescriptor, Kind, MessageDescriptor};
 use serde::{Deserialize, Serialize};
 
+use crate::grpc::protobuf::{is_object, oneof_json_name, oneofs, type_name};
 use crate::valid::{Valid, Validator};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
@@ -14,6 +15,17 @@ pub enum JsonSchema {
     Str,
     Num,
     Bool,
+    /// A string among the values of an enum.
+    Enum(BTreeSet<String>),
+    /// One of the object types of a union, by their names.
+    Union(HashMap<String, JsonSchema>),
+    /// A protobuf `oneof` group, of which at most one field is set. The
+    /// `types` are the names of the messages held by its fields, which can be
+    /// exposed as a union.
+    OneOf {
+        fields: HashMap<String, JsonSchema>,
+        types: HashMap<String, String>,
+    },
 }
 
 impl<const L: usize> From<[(&'static str, JsonSchema); L]> for JsonSchema {
@@ -48,6 +60,28 @@ impl JsonSchema {
                 async_graphql::Value::Boolean(_) => Valid::succeed(()),
                 _ => Valid::fail("expected boolean"),
             },
+            JsonSchema::Enum(values) => match value {
+                async_graphql::Value::String(value) if values.contains(value) => Valid::succeed(()),
+                async_graphql::Value::Enum(value) if values.contains(value.as_str()) => {
+                    Valid::succeed(())
+                }
+                _ => Valid::fail("expected enum value"),
+            },
+            JsonSchema::Union(types) => match value {
+                async_graphql::Value::Object(map) => {
+                    // the type is told by the upstream to resolve the union
+                    match map.get("__typename") {
+                        Some(async_graphql::Value::String(name)) => match types.get(name) {
+                            Some(schema) => schema.validate(value),
+                            None => Valid::fail("expected a type of the union"),
+                        },
+                        _ => Valid::fail("expected __typename"),
+                    }
+                }
+                _ => Valid::fail("expected object"),
+            },
+            // only describes protobuf messages, which aren't validated
+            JsonSchema::OneOf { .. } => Valid::succeed(()),
             JsonSchema::Arr(schema) => match value {
                 async_graphql::Value::List(list) => {
                     // TODO: add unit tests
@@ -59,7 +93,7 @@ impl JsonSchema {
                 _ => Valid::fail("expected array"),
             },
             JsonSchema::Obj(fields) => {
-                let field_schema_list: Vec<(&String, &JsonSchema)> = fields.iter().collect();
+                let field_schema_list = sorted(fields);
                 match value {
                     async_graphql::Value::Object(map) => {
                         Valid::from_iter(field_schema_list, |(name, schema)| {
@@ -92,9 +126,18 @@ impl JsonSchema {
         match self {
             JsonSchema::Obj(a) => {
                 if let JsonSchema::Obj(b) = other {
-                    return Valid::from_iter(b.iter(), |(key, b)| {
-                        Valid::from_option(a.get(key), format!("missing key: {}", key))
-                            .and_then(|a| a.compare(b, key))
+                    return Valid::from_iter(sorted(b), |(key, b)| match b {
+                        // a oneof is exposed either as a union or as its fields
+                        JsonSchema::OneOf { fields, types } => match a.get(key) {
+                            Some(a) => a.compare_one_of(fields, types, key),
+                            None => Valid::from_iter(sorted(fields), |(key, b)| {
+                                Valid::from_option(a.get(key), format!("missing key: {}", key))
+                                    .and_then(|a| a.compare(b, key))
+                            })
+                            .unit(),
+                        },
+                        b => Valid::from_option(a.get(key), format!("missing key: {}", key))
+                            .and_then(|a| a.compare(b, key)),
                     })
                     .trace(name)
                     .unit();
@@ -117,7 +160,7 @@ impl JsonSchema {
                 }
             }
             JsonSchema::Str => {
-                if other != self {
+                if other != self && !matches!(other, JsonSchema::Enum(_)) {
                     return Valid::fail(format!("expected String, got {:?}", other)).trace(name);
                 }
             }
@@ -131,10 +174,60 @@ impl JsonSchema {
                     return Valid::fail(format!("expected Boolean, got {:?}", other)).trace(name);
                 }
             }
+            JsonSchema::Enum(a) => match other {
+                JsonSchema::Enum(b) => {
+                    return Valid::from_iter(b.iter(), |value| {
+                        if a.contains(value) {
+                            Valid::succeed(())
+                        } else {
+                            Valid::fail(format!("missing enum value: {}", value))
+                        }
+                    })
+                    .trace(name)
+                    .unit();
+                }
+                JsonSchema::Str => {}
+                _ => return Valid::fail(format!("expected Enum, got {:?}", other)).trace(name),
+            },
+            JsonSchema::Union(_) | JsonSchema::OneOf { .. } => {
+                return Valid::fail(format!("expected Union, got {:?}", other)).trace(name);
+            }
         }
         Valid::succeed(())
     }
 
+    /// Compares a nullable union to the messages held by the fields of a
+    /// `oneof` group.
+    fn compare_one_of(
+        &self,
+        fields: &HashMap<String, JsonSchema>,
+        types: &HashMap<String, String>,
+        name: &str,
+    ) -> Valid<(), String> {
+        let JsonSchema::Opt(schema) = self else {
+            return Valid::fail("expected type to be nullable".to_string()).trace(name);
+        };
+        let JsonSchema::Union(a) = schema.as_ref() else {
+            return Valid::fail(format!("expected Union, got {:?}", schema)).trace(name);
+        };
+
+        Valid::from_iter(sorted(fields), |(field, b)| {
+            Valid::from_option(
+                types.get(field),
+                format!("{} doesn't hold an object type", field),
+            )
+            .and_then(|type_name| {
+                Valid::from_option(a.get(type_name), format!("missing type: {}", type_name))
+            })
+            .and_then(|a| match b {
+                JsonSchema::Opt(b) => a.compare(b, field),
+                b => a.compare(b, field),
+            })
+        })
+        .trace(name)
+        .unit()
+    }
+
     pub fn optional(self) -> JsonSchema {
         JsonSchema::Opt(Box::new(self))
     }
@@ -152,19 +245,66 @@ impl TryFrom<&MessageDescriptor> for JsonSchema {
     type Error = crate::valid::ValidationError<String>;
 
     fn try_from(value: &MessageDescriptor) -> Result<Self, Self::Error> {
+        if let Some(schema) = well_known_type(value.full_name()) {
+            return Ok(schema);
+        }
+
         let mut map = std::collections::HashMap::new();
         let fields = value.fields();
 
         for field in fields {
             let field_schema = JsonSchema::try_from(&field)?;
 
-            map.insert(field.name().to_string(), field_schema);
+            // messages are serialized with the JSON names of their fields
+            map.insert(field.json_name().to_string(), field_schema);
+        }
+
+        for oneof in oneofs(value) {
+            let mut fields = HashMap::new();
+            let mut types = HashMap::new();
+            for field in oneof.fields() {
+                let name = field.json_name().to_string();
+                if let Some(schema) = map.remove(&name) {
+                    fields.insert(name.clone(), schema);
+                }
+                if let Kind::Message(message) = field.kind() {
+                    if is_object(&message) {
+                        types.insert(name, type_name(message.full_name(), message.package_name()));
+                    }
+                }
+            }
+            map.insert(oneof_json_name(&oneof), JsonSchema::OneOf { fields, types });
         }
 
         Ok(JsonSchema::Obj(map))
     }
 }
 
+/// The fields sorted by name, so that their errors are reported in a stable
+/// order.
+fn sorted(fields: &HashMap<String, JsonSchema>) -> Vec<(&String, &JsonSchema)> {
+    let mut fields: Vec<_> = fields.iter().collect();
+    fields.sort_by_key(|(name, _)| *name);
+    fields
+}
+
+/// The schema of the JSON representation of the well-known types, which
+/// aren't serialized as objects.
+fn well_known_type(full_name: &str) -> Option<JsonSchema> {
+    let schema = match full_name.strip_prefix("google.protobuf.")? {
+        "Timestamp" | "Duration" | "FieldMask" => JsonSchema::Str,
+        "StringValue" | "BytesValue" => JsonSchema::Str,
+        "DoubleValue" | "FloatValue" | "Int32Value" | "UInt32Value" => JsonSchema::Num,
+        // 64-bit integers are serialized as strings to keep their precision
+        "Int64Value" | "UInt64Value" => JsonSchema::Str,
+        "BoolValue" => JsonSchema::Bool,
+        "Struct" | "Value" | "ListValue" | "Any" => JsonSchema::Obj(HashMap::new()),
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
 impl TryFrom<&FieldDescriptor> for JsonSchema {
     type Error = crate::valid::ValidationError<String>;
 
@@ -173,22 +313,29 @@ impl TryFrom<&FieldDescriptor> for JsonSchema {
             Kind::Double => JsonSchema::Num,
             Kind::Float => JsonSchema::Num,
             Kind::Int32 => JsonSchema::Num,
-            Kind::Int64 => JsonSchema::Num,
             Kind::Uint32 => JsonSchema::Num,
-            Kind::Uint64 => JsonSchema::Num,
             Kind::Sint32 => JsonSchema::Num,
-            Kind::Sint64 => JsonSchema::Num,
             Kind::Fixed32 => JsonSchema::Num,
-            Kind::Fixed64 => JsonSchema::Num,
             Kind::Sfixed32 => JsonSchema::Num,
-            Kind::Sfixed64 => JsonSchema::Num,
+            // 64-bit integers are serialized as strings to keep their precision
+            Kind::Int64 => JsonSchema::Str,
+            Kind::Uint64 => JsonSchema::Str,
+            Kind::Sint64 => JsonSchema::Str,
+            Kind::Fixed64 => JsonSchema::Str,
+            Kind::Sfixed64 => JsonSchema::Str,
             Kind::Bool => JsonSchema::Bool,
             Kind::String => JsonSchema::Str,
             Kind::Bytes => JsonSchema::Str,
+            // maps are objects whose keys are the keys of the entries
+            Kind::Message(_) if value.is_map() => JsonSchema::Obj(HashMap::new()),
             Kind::Message(msg) => JsonSchema::try_from(&msg)?,
-            Kind::Enum(_) => {
-                todo!("Enum")
-            }
+            // enums are serialized with the names of their values
+            Kind::Enum(descriptor) => JsonSchema::Enum(
+                descriptor
+                    .values()
+                    .map(|value| value.name().to_string())
+                    .collect(),
+            ),
         };
         let field_schema = if value
             .cardinality()
@@ -212,10 +359,25 @@ impl TryFrom<&FieldDescriptor> for JsonSchema {
 mod tests {
     use async_graphql::Name;
     use indexmap::IndexMap;
+    use prost_reflect::DescriptorPool;
 
     use crate::json::JsonSchema;
     use crate::valid::{Valid, Validator};
 
+    #[test]
+    fn test_64_bit_integers() {
+        let files = protox::compile(["well_known.proto"], ["src/grpc/tests"]).unwrap();
+        let pool = DescriptorPool::from_file_descriptor_set(files).unwrap();
+        let event = pool.get_message_by_name("events.Event").unwrap();
+
+        let JsonSchema::Obj(fields) = JsonSchema::try_from(&event).unwrap() else {
+            panic!("expected an object");
+        };
+        assert_eq!(fields["likes"], JsonSchema::Str.optional());
+        assert_eq!(fields["views"], JsonSchema::Str.optional());
+        assert_eq!(fields["id"], JsonSchema::Num.optional());
+    }
+
     #[test]
     fn test_validate_string() {
         let schema = JsonSchema::Str;
@@ -1,11 +1,22 @@
 use core::future::Future;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroU64;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::Result;
 use async_graphql_value::ConstValue;
+use serde::{Deserialize, Serialize};
 
-use super::{Concurrent, Eval, EvaluationContext, Expression, ResolverContextLike, IO};
+use super::{
+    Concurrent, DetachedResolverContext, Eval, EvaluationContext, EvaluationError, Expression,
+    ResolverContextLike, IO,
+};
+use crate::mustache::Mustache;
+use crate::{config, CacheEntry};
 
 pub trait CacheKey<Ctx> {
     fn cache_key(&self, ctx: &Ctx) -> u64;
@@ -14,21 +25,235 @@ pub trait CacheKey<Ctx> {
 #[derive(Clone, Debug)]
 pub struct Cache {
     pub max_age: NonZeroU64,
+    pub stale_while_revalidate: Option<NonZeroU64>,
+    pub stale_if_error: Option<NonZeroU64>,
+    pub key: Option<Mustache>,
+    pub tags: Vec<Mustache>,
+    pub negative_ttl: Option<NonZeroU64>,
+    pub negative_statuses: Vec<u16>,
+    /// The keys being revalidated, so that a stale value is refreshed by a
+    /// single request at a time.
+    pub revalidating: Arc<Mutex<HashSet<u64>>>,
     pub expr: IO,
 }
 
+/// Marks a key as being revalidated for as long as it's alive.
+struct Revalidating {
+    keys: Arc<Mutex<HashSet<u64>>>,
+    key: u64,
+}
+
+impl Revalidating {
+    fn start(keys: &Arc<Mutex<HashSet<u64>>>, key: u64) -> Option<Self> {
+        keys.lock()
+            .unwrap()
+            .insert(key)
+            .then(|| Self { keys: keys.clone(), key })
+    }
+}
+
+impl Drop for Revalidating {
+    fn drop(&mut self) {
+        self.keys.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// What is cached for a resolver. Negative results, ie. `null` and the errors
+/// returned by the upstream, are kept apart from successful values so that
+/// they can be cached for a shorter time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CachedValue {
+    Value(ConstValue),
+    Null,
+    Error(CachedError),
+}
+
+/// An upstream error, as reported by [EvaluationError::HTTPException].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedError {
+    pub status: u16,
+    pub url: String,
+    pub body: String,
+    pub code: Option<String>,
+}
+
+impl CachedValue {
+    fn into_result(self) -> Result<ConstValue> {
+        match self {
+            CachedValue::Value(value) => Ok(value),
+            CachedValue::Null => Ok(ConstValue::Null),
+            CachedValue::Error(CachedError { status, url, body, code }) => {
+                Err(EvaluationError::HTTPException { status, url, body, code }.into())
+            }
+        }
+    }
+}
+
+/// Evicts the cached values tagged with any of the rendered `tags` once
+/// `expr` has been evaluated successfully.
+#[derive(Clone, Debug)]
+pub struct CacheInvalidate {
+    pub tags: Vec<Mustache>,
+    pub expr: Box<Expression>,
+}
+
+/// What can be done with a cached value, depending on its age.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Freshness {
+    Fresh,
+    Revalidate,
+    Expired,
+}
+
 impl Cache {
     ///
     /// Wraps an expression with the cache primitive.
     /// Performance DFS on the cache on the expression and identifies all the IO nodes.
     /// Then wraps each IO node with the cache primitive.
     ///
-    pub fn wrap(max_age: NonZeroU64, expr: Expression) -> Expression {
+    pub fn wrap(
+        cache: &config::Cache,
+        key: Option<Mustache>,
+        tags: Vec<Mustache>,
+        expr: Expression,
+    ) -> Expression {
+        let revalidating = Arc::new(Mutex::new(HashSet::new()));
         expr.modify(move |expr| match expr {
-            Expression::IO(io) => Some(Expression::Cache(Cache { max_age, expr: io.clone() })),
+            Expression::IO(io) => Some(Expression::Cache(Cache {
+                max_age: cache.max_age,
+                stale_while_revalidate: cache.stale_while_revalidate,
+                stale_if_error: cache.stale_if_error,
+                key: key.clone(),
+                tags: tags.clone(),
+                negative_ttl: cache.negative_ttl,
+                negative_statuses: cache.negative_statuses.clone(),
+                revalidating: revalidating.clone(),
+                expr: io.clone(),
+            })),
             _ => None,
         })
     }
+
+    fn cache_key<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &self,
+        ctx: &EvaluationContext<'a, Ctx>,
+    ) -> u64 {
+        match &self.key {
+            Some(key) => {
+                let mut hasher = DefaultHasher::new();
+                key.render(ctx).hash(&mut hasher);
+                hasher.finish()
+            }
+            None => self.expr.cache_key(ctx),
+        }
+    }
+
+    fn freshness(&self, age: Duration) -> Freshness {
+        let age = age.as_millis() as u64;
+        let max_age = self.max_age.get();
+        if age < max_age {
+            Freshness::Fresh
+        } else if self
+            .stale_while_revalidate
+            .is_some_and(|stale| age < max_age + stale.get())
+        {
+            Freshness::Revalidate
+        } else {
+            Freshness::Expired
+        }
+    }
+
+    fn is_usable_on_error(&self, age: Duration) -> bool {
+        let age = age.as_millis() as u64;
+        self.stale_if_error
+            .is_some_and(|stale| age < self.max_age.get() + stale.get())
+    }
+
+    /// Values are kept past `max_age` for as long as they can be served stale.
+    fn ttl(&self) -> NonZeroU64 {
+        let stale = self
+            .stale_while_revalidate
+            .max(self.stale_if_error)
+            .map_or(0, NonZeroU64::get);
+        self.max_age.saturating_add(stale)
+    }
+
+    /// The value to cache for a result, along with how long to keep it.
+    fn to_cached(&self, result: &Result<ConstValue>) -> Option<(CachedValue, NonZeroU64)> {
+        match (result, self.negative_ttl) {
+            (Ok(ConstValue::Null), Some(negative_ttl)) => Some((CachedValue::Null, negative_ttl)),
+            (Ok(value), _) => Some((CachedValue::Value(value.clone()), self.ttl())),
+            (Err(error), Some(negative_ttl)) => match error.downcast_ref() {
+                Some(EvaluationError::HTTPException { status, url, body, code })
+                    if self.negative_statuses.contains(status) =>
+                {
+                    let error = CachedError {
+                        status: *status,
+                        url: url.clone(),
+                        body: body.clone(),
+                        code: code.clone(),
+                    };
+                    Some((CachedValue::Error(error), negative_ttl))
+                }
+                _ => None,
+            },
+            (Err(_), None) => None,
+        }
+    }
+
+    async fn insert<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &self,
+        ctx: &EvaluationContext<'a, Ctx>,
+        key: u64,
+        result: &Result<ConstValue>,
+    ) -> Result<()> {
+        let Some((value, ttl)) = self.to_cached(result) else {
+            return Ok(());
+        };
+        let tags = match &value {
+            CachedValue::Value(value) => render_tags(&self.tags, ctx, value),
+            _ => render_tags(&self.tags, ctx, &ConstValue::Null),
+        };
+        let cache = &ctx.req_ctx.runtime.cache;
+        cache.set(key, value, ttl).await?;
+        if !tags.is_empty() {
+            cache.tag(&key, tags, ttl).await?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates the expression again with a copy of the context and updates
+    /// the cache, without making the current request wait for it. Nothing is
+    /// done while the key is already being revalidated.
+    fn revalidate<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &self,
+        ctx: &'a EvaluationContext<'a, Ctx>,
+        key: u64,
+    ) {
+        let Some(revalidating) = Revalidating::start(&self.revalidating, key) else {
+            return;
+        };
+        let cache = self.clone();
+        let req_ctx = ctx.req_ctx.clone();
+        let graphql_ctx = DetachedResolverContext::new(ctx.graphql_ctx);
+        let task = async move {
+            let ctx = EvaluationContext::new(&req_ctx, &graphql_ctx);
+            let result = cache.expr.eval(&ctx, &Concurrent::Sequential).await;
+            let result = match result {
+                Ok(value) => cache.insert(&ctx, key, &Ok(value)).await,
+                Err(error) => Err(error),
+            };
+            if let Err(error) = result {
+                log::warn!("Failed to revalidate a cached value: {}", error);
+            }
+            drop(revalidating);
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        tokio::spawn(task);
+        #[cfg(target_arch = "wasm32")]
+        async_std::task::spawn_local(task);
+    }
 }
 
 impl Eval for Cache {
@@ -38,18 +263,256 @@ impl Eval for Cache {
         conc: &'a Concurrent,
     ) -> Pin<Box<dyn Future<Output = Result<ConstValue>> + 'a + Send>> {
         Box::pin(async move {
-            let key = self.expr.cache_key(ctx);
-            if let Some(val) = ctx.req_ctx.runtime.cache.get(&key).await? {
-                Ok(val)
-            } else {
-                let val = self.expr.eval(ctx, conc).await?;
-                ctx.req_ctx
-                    .runtime
-                    .cache
-                    .set(key, val.clone(), self.max_age)
-                    .await?;
-                Ok(val)
+            let key = self.cache_key(ctx);
+            let stale = match ctx.req_ctx.runtime.cache.get_entry(&key).await? {
+                Some(CacheEntry { value: CachedValue::Value(value), age }) => {
+                    match self.freshness(age) {
+                        Freshness::Fresh => return Ok(value),
+                        Freshness::Revalidate => {
+                            self.revalidate(ctx, key);
+                            return Ok(value);
+                        }
+                        Freshness::Expired => Some((value, age)),
+                    }
+                }
+                Some(CacheEntry { value: negative, .. }) => return negative.into_result(),
+                None => None,
+            };
+
+            let result = self.expr.eval(ctx, conc).await;
+            match stale {
+                Some((value, age)) if result.is_err() && self.is_usable_on_error(age) => Ok(value),
+                _ => {
+                    self.insert(ctx, key, &result).await?;
+                    result
+                }
             }
         })
     }
 }
+
+impl Eval for CacheInvalidate {
+    fn eval<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &'a self,
+        ctx: &'a EvaluationContext<'a, Ctx>,
+        conc: &'a Concurrent,
+    ) -> Pin<Box<dyn Future<Output = Result<ConstValue>> + 'a + Send>> {
+        Box::pin(async move {
+            let value = self.expr.eval(ctx, conc).await?;
+            let tags = render_tags(&self.tags, ctx, &value);
+            if let Err(error) = ctx.req_ctx.runtime.cache.invalidate(&tags).await {
+                log::warn!("Failed to invalidate the cache tags {:?}: {}", tags, error);
+            }
+            Ok(value)
+        })
+    }
+}
+
+/// Renders the tags with `value` referring to the given value, or to each of
+/// its items when it's a list.
+fn render_tags<'a, Ctx: ResolverContextLike<'a>>(
+    tags: &[Mustache],
+    ctx: &EvaluationContext<'a, Ctx>,
+    value: &ConstValue,
+) -> Vec<String> {
+    if tags.is_empty() {
+        return vec![];
+    }
+
+    let items = match value {
+        ConstValue::List(items) => items.iter().collect(),
+        value => vec![value],
+    };
+    let args = ctx.graphql_ctx.args().cloned();
+    let mut rendered = Vec::new();
+    for item in items {
+        let graphql_ctx = DetachedResolverContext {
+            value: Some(item.clone()),
+            args: args.clone(),
+            ..Default::default()
+        };
+        let item_ctx = EvaluationContext::new(ctx.req_ctx, &graphql_ctx);
+        for tag in tags {
+            let tag = tag.render(&item_ctx);
+            if !rendered.contains(&tag) {
+                rendered.push(tag);
+            }
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_graphql_value::ConstValue;
+    use futures_util::future::join_all;
+    use hyper::body::Bytes;
+
+    use super::{Cache, CachedError, CachedValue, Freshness};
+    use crate::http::{RequestContext, RequestTemplate, Response};
+    use crate::lambda::{
+        Concurrent, EmptyResolverContext, Eval, EvaluationContext, EvaluationError, IO,
+    };
+    use crate::HttpIO;
+
+    /// Answers with the number of requests it received
+    #[derive(Default)]
+    struct CountingHttp {
+        requests: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpIO for CountingHttp {
+        async fn execute(&self, _request: reqwest::Request) -> anyhow::Result<Response<Bytes>> {
+            let requests = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(Response::empty().body(Bytes::from(requests.to_string())))
+        }
+    }
+
+    fn cache(stale_while_revalidate: Option<u64>, stale_if_error: Option<u64>) -> Cache {
+        Cache {
+            max_age: NonZeroU64::new(100).unwrap(),
+            stale_while_revalidate: stale_while_revalidate.and_then(NonZeroU64::new),
+            stale_if_error: stale_if_error.and_then(NonZeroU64::new),
+            key: None,
+            tags: vec![],
+            negative_ttl: None,
+            negative_statuses: vec![],
+            revalidating: Default::default(),
+            expr: IO::Http {
+                req_template: RequestTemplate::new("http://localhost:8080").unwrap(),
+                group_by: None,
+                dl_id: None,
+                hedge: None,
+                response_format: None,
+                paginate: None,
+                on_status: vec![],
+                propagate_headers: vec![],
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stale_while_revalidate() {
+        let http = Arc::new(CountingHttp::default());
+        let mut req_ctx = RequestContext::default();
+        req_ctx.runtime.http = http.clone();
+        let graphql_ctx = EmptyResolverContext;
+        let ctx = EvaluationContext::new(&req_ctx, &graphql_ctx);
+        let cache = Cache {
+            max_age: NonZeroU64::new(1).unwrap(),
+            ..cache(Some(60_000), None)
+        };
+        let key = cache.cache_key(&ctx);
+        let ttl = NonZeroU64::new(60_000).unwrap();
+        let stale = CachedValue::Value(ConstValue::from(0));
+        req_ctx.runtime.cache.set(key, stale, ttl).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let results = join_all((0..5).map(|_| cache.eval(&ctx, &Concurrent::Sequential))).await;
+        let values: Vec<_> = results.into_iter().map(|value| value.unwrap()).collect();
+        assert_eq!(values, vec![ConstValue::from(0); 5]);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(http.requests.load(Ordering::SeqCst), 1);
+        let value = cache.eval(&ctx, &Concurrent::Sequential).await.unwrap();
+        assert_eq!(value, ConstValue::from(1));
+    }
+
+    #[test]
+    fn test_freshness() {
+        let cache = cache(Some(50), None);
+
+        assert_eq!(cache.freshness(Duration::from_millis(99)), Freshness::Fresh);
+        assert_eq!(
+            cache.freshness(Duration::from_millis(100)),
+            Freshness::Revalidate
+        );
+        assert_eq!(
+            cache.freshness(Duration::from_millis(150)),
+            Freshness::Expired
+        );
+        assert!(!cache.is_usable_on_error(Duration::from_millis(120)));
+    }
+
+    #[test]
+    fn test_stale_if_error() {
+        let cache = cache(None, Some(200));
+
+        assert_eq!(
+            cache.freshness(Duration::from_millis(100)),
+            Freshness::Expired
+        );
+        assert!(cache.is_usable_on_error(Duration::from_millis(299)));
+        assert!(!cache.is_usable_on_error(Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_ttl_covers_stale_windows() {
+        assert_eq!(cache(None, None).ttl().get(), 100);
+        assert_eq!(cache(Some(50), Some(200)).ttl().get(), 300);
+        assert_eq!(cache(Some(500), Some(200)).ttl().get(), 600);
+    }
+
+    fn not_found(status: u16) -> anyhow::Result<ConstValue> {
+        Err(EvaluationError::HTTPException {
+            status,
+            url: "http://localhost:8080".to_string(),
+            body: "Not Found".to_string(),
+            code: None,
+        }
+        .into())
+    }
+
+    #[test]
+    fn test_negative_results() {
+        let negative_ttl = NonZeroU64::new(10).unwrap();
+        let cache = Cache {
+            negative_ttl: Some(negative_ttl),
+            negative_statuses: vec![404],
+            ..cache(None, None)
+        };
+
+        assert_eq!(
+            cache.to_cached(&Ok(ConstValue::Null)),
+            Some((CachedValue::Null, negative_ttl))
+        );
+        assert_eq!(
+            cache.to_cached(&Ok(ConstValue::Boolean(true))),
+            Some((CachedValue::Value(ConstValue::Boolean(true)), cache.ttl()))
+        );
+        let (error, ttl) = cache.to_cached(&not_found(404)).unwrap();
+        assert_eq!(ttl, negative_ttl);
+        assert_eq!(
+            error,
+            CachedValue::Error(CachedError {
+                status: 404,
+                url: "http://localhost:8080".to_string(),
+                body: "Not Found".to_string(),
+                code: None,
+            })
+        );
+        assert_eq!(
+            error.into_result().unwrap_err().to_string(),
+            not_found(404).unwrap_err().to_string()
+        );
+        assert_eq!(cache.to_cached(&not_found(500)), None);
+    }
+
+    #[test]
+    fn test_without_negative_ttl() {
+        let cache = Cache { negative_statuses: vec![404], ..cache(None, None) };
+
+        assert_eq!(
+            cache.to_cached(&Ok(ConstValue::Null)),
+            Some((CachedValue::Value(ConstValue::Null), cache.ttl()))
+        );
+        assert_eq!(cache.to_cached(&not_found(404)), None);
+    }
+}
@@ -70,13 +70,11 @@ impl<'a, Ctx: ResolverContextLike<'a>> EvaluationContext<'a, Ctx> {
 
 impl<'a, Ctx: ResolverContextLike<'a>> GraphQLOperationContext for EvaluationContext<'a, Ctx> {
     fn selection_set(&self) -> Option<String> {
-        let selection_set = self.graphql_ctx.field()?.selection_set();
-
-        format_selection_set(selection_set)
+        self.graphql_ctx.selection_set()
     }
 }
 
-fn format_selection_set<'a>(
+pub(super) fn format_selection_set<'a>(
     selection_set: impl Iterator<Item = SelectionField<'a>>,
 ) -> Option<String> {
     let set = selection_set
@@ -3,15 +3,21 @@ use std::fmt::Debug;
 use std::pin::Pin;
 
 use anyhow::Result;
+use async_graphql::ErrorExtensions;
 use async_graphql_value::ConstValue;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{future, StreamExt};
 use serde_json::Value;
 use thiserror::Error;
 
 use super::list::List;
 use super::logic::Logic;
 use super::{Concurrent, Eval, EvaluationContext, Math, Relation, ResolverContextLike, IO};
+use crate::config::GrpcCode;
+use crate::grpc::status::GrpcStatus;
+use crate::http::StatusError;
 use crate::json::JsonLike;
-use crate::lambda::cache::Cache;
+use crate::lambda::cache::{Cache, CacheInvalidate};
 
 #[derive(Clone, Debug)]
 pub enum Expression {
@@ -20,6 +26,7 @@ pub enum Expression {
     EqualTo(Box<Expression>, Box<Expression>),
     IO(IO),
     Cache(Cache),
+    CacheInvalidate(CacheInvalidate),
     Input(Box<Expression>, Vec<String>),
     Logic(Logic),
     Relation(Relation),
@@ -44,6 +51,80 @@ pub enum EvaluationError {
 
     #[error("ExprEvalError: {0:?}")]
     ExprEvalError(String),
+
+    #[error("HTTPException: {status} for url ({url})")]
+    HTTPException {
+        status: u16,
+        url: String,
+        /// The beginning of the body of the response
+        body: String,
+        code: Option<String>,
+    },
+
+    #[error("GRPCException: {status}")]
+    GRPCException {
+        status: GrpcCode,
+        message: String,
+        details: Vec<ConstValue>,
+        code: Option<String>,
+    },
+}
+
+/// Number of characters of the body of a failed response kept in the error
+const BODY_EXCERPT_LENGTH: usize = 256;
+
+impl EvaluationError {
+    /// Keeps the status, url and body of the response when the upstream
+    /// answered with an error status.
+    pub fn from_io_error(error: &anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<GrpcStatus>() {
+            return EvaluationError::GRPCException {
+                status: error.status,
+                message: error.message.clone(),
+                details: error.details.clone(),
+                code: None,
+            };
+        }
+
+        match error.downcast_ref::<StatusError>() {
+            Some(error) => EvaluationError::HTTPException {
+                status: error.status.as_u16(),
+                url: error.url.clone(),
+                body: String::from_utf8_lossy(&error.body)
+                    .chars()
+                    .take(BODY_EXCERPT_LENGTH)
+                    .collect(),
+                code: None,
+            },
+            None => EvaluationError::IOException(error.to_string()),
+        }
+    }
+}
+
+impl ErrorExtensions for EvaluationError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.to_string()).extend_with(|_, extensions| {
+            if let EvaluationError::HTTPException { status, url, body, code } = self {
+                if let Some(code) = code {
+                    extensions.set("code", code.as_str());
+                }
+                extensions.set("status", *status);
+                extensions.set("url", url.as_str());
+                extensions.set("body", body.as_str());
+            }
+            if let EvaluationError::GRPCException { status, message, details, code } = self {
+                if let Some(code) = code {
+                    extensions.set("code", code.as_str());
+                }
+                extensions.set("grpcStatus", status.name());
+                extensions.set("grpcCode", status.code());
+                extensions.set("grpcMessage", message.as_str());
+                if !details.is_empty() {
+                    extensions.set("grpcDetails", ConstValue::List(details.clone()));
+                }
+            }
+        })
+    }
 }
 
 impl<'a> From<crate::valid::ValidationError<&'a str>> for EvaluationError {
@@ -78,6 +159,31 @@ impl Expression {
     pub fn in_sequence(self) -> Self {
         self.concurrency(Concurrent::Sequential)
     }
+
+    /// Whether the expression can resolve to null through an `onStatus`
+    /// mapping, see [IO::has_null_status].
+    pub fn has_null_status(&self) -> bool {
+        match self {
+            Expression::IO(io) => io.has_null_status(),
+            Expression::Cache(cache) => cache.expr.has_null_status(),
+            Expression::Concurrency(_, expr) => expr.has_null_status(),
+            _ => false,
+        }
+    }
+
+    /// Resolves the events of a subscription field, see [IO::eval_stream].
+    pub async fn eval_stream<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &'a self,
+        ctx: &'a EvaluationContext<'a, Ctx>,
+    ) -> Result<BoxStream<'static, Result<ConstValue>>> {
+        match self {
+            Expression::IO(io) => io.eval_stream(ctx).await,
+            expr => {
+                let value = expr.eval(ctx, &Concurrent::Sequential).await?;
+                Ok(stream::once(future::ready(Ok(value))).boxed())
+            }
+        }
+    }
 }
 
 impl Eval for Expression {
@@ -111,6 +217,7 @@ impl Eval for Expression {
                 )),
                 Expression::IO(operation) => operation.eval(ctx, conc).await,
                 Expression::Cache(cached) => cached.eval(ctx, conc).await,
+                Expression::CacheInvalidate(invalidate) => invalidate.eval(ctx, conc).await,
                 Expression::Relation(relation) => relation.eval(ctx, conc).await,
                 Expression::Logic(logic) => logic.eval(ctx, conc).await,
                 Expression::List(list) => list.eval(ctx, conc).await,
@@ -4,16 +4,20 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_graphql_value::ConstValue;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{future, StreamExt};
 use reqwest::Request;
 
-use super::{CacheKey, Eval, EvaluationContext, ResolverContextLike};
+use super::{CacheKey, Concurrent, Eval, EvaluationContext, ResolverContextLike};
 use crate::config::group_by::GroupBy;
-use crate::config::GraphQLOperationType;
+use crate::config::{
+    GraphQLOperationType, GrpcOnStatus, Hedge, OnStatus, Paginate, PropagateHeader, ResponseFormat,
+    StatusAction,
+};
 use crate::data_loader::{DataLoader, Loader};
 use crate::graphql::{self, GraphqlDataLoader};
 use crate::grpc::data_loader::GrpcDataLoader;
-use crate::grpc::protobuf::ProtobufOperation;
-use crate::grpc::request::execute_grpc_request;
+use crate::grpc::request::{execute_grpc_request, execute_grpc_stream};
 use crate::grpc::request_template::RenderedRequestTemplate;
 use crate::http::{cache_policy, DataLoaderRequest, HttpDataLoader, Response};
 use crate::json::JsonLike;
@@ -27,6 +31,11 @@ pub enum IO {
         req_template: http::RequestTemplate,
         group_by: Option<GroupBy>,
         dl_id: Option<DataLoaderId>,
+        hedge: Option<Hedge>,
+        response_format: Option<ResponseFormat>,
+        paginate: Option<Paginate>,
+        on_status: Vec<OnStatus>,
+        propagate_headers: Vec<PropagateHeader>,
     },
     GraphQL {
         req_template: graphql::RequestTemplate,
@@ -38,6 +47,8 @@ pub enum IO {
         req_template: grpc::RequestTemplate,
         group_by: Option<GroupBy>,
         dl_id: Option<DataLoaderId>,
+        on_status: Vec<GrpcOnStatus>,
+        metadata: Vec<String>,
     },
 }
 
@@ -52,16 +63,43 @@ impl Eval for IO {
     ) -> Pin<Box<dyn Future<Output = Result<ConstValue>> + 'a + Send>> {
         Box::pin(async move {
             match self {
-                IO::Http { req_template, dl_id, .. } => {
+                IO::Http {
+                    req_template,
+                    dl_id,
+                    hedge,
+                    response_format,
+                    paginate,
+                    on_status,
+                    propagate_headers,
+                    ..
+                } => {
                     let req = req_template.to_request(ctx)?;
                     let is_get = req.method() == reqwest::Method::GET;
 
-                    let res = if is_get && ctx.req_ctx.is_batching_enabled() {
-                        let data_loader: Option<&DataLoader<DataLoaderRequest, HttpDataLoader>> =
-                            dl_id.and_then(|index| ctx.req_ctx.http_data_loaders.get(index.0));
-                        execute_request_with_dl(ctx, req, data_loader).await?
-                    } else {
-                        execute_raw_request(ctx, req).await?
+                    let res = async {
+                        Ok(if let Some(paginate) = paginate {
+                            http::execute_paginated(req, paginate, |req| async {
+                                let http = ctx.req_ctx.runtime.http.as_ref();
+                                http::execute_hedged(http, req, hedge.as_ref())
+                                    .await?
+                                    .to_value(response_format.as_ref())
+                            })
+                            .await
+                            .map_err(|e| EvaluationError::from_io_error(&e))?
+                        } else if is_get && ctx.req_ctx.is_batching_enabled() {
+                            let data_loader: Option<
+                                &DataLoader<DataLoaderRequest, HttpDataLoader>,
+                            > = dl_id.and_then(|index| ctx.req_ctx.http_data_loaders.get(index.0));
+                            execute_request_with_dl(ctx, req, data_loader).await?
+                        } else {
+                            execute_raw_request(ctx, req, hedge.as_ref(), response_format.as_ref())
+                                .await?
+                        })
+                    }
+                    .await;
+                    let res = match res {
+                        Ok(res) => res,
+                        Err(error) => return handle_status(error, on_status),
                     };
 
                     if ctx.req_ctx.server.get_enable_http_validation() {
@@ -74,6 +112,8 @@ impl Eval for IO {
                     }
 
                     set_cache_control(ctx, &res);
+                    ctx.req_ctx
+                        .propagate_headers(&res.headers, propagate_headers);
 
                     Ok(res.body)
                 }
@@ -87,29 +127,26 @@ impl Eval for IO {
                             dl_id.and_then(|index| ctx.req_ctx.gql_data_loaders.get(index.0));
                         execute_request_with_dl(ctx, req, data_loader).await?
                     } else {
-                        execute_raw_request(ctx, req).await?
+                        execute_raw_request(ctx, req, None, Some(&ResponseFormat::Json)).await?
                     };
 
                     set_cache_control(ctx, &res);
+                    ctx.req_ctx
+                        .propagate_headers(&res.headers, &ctx.req_ctx.upstream.propagate_headers);
                     parse_graphql_response(ctx, res, field_name)
                 }
-                IO::Grpc { req_template, dl_id, .. } => {
+                IO::Grpc { req_template, dl_id, on_status, metadata, .. } => {
                     let rendered = req_template.render(ctx)?;
 
-                    let res = if ctx.req_ctx.upstream.batch.is_some() &&
-                    // TODO: share check for operation_type for resolvers
-                    matches!(req_template.operation_type, GraphQLOperationType::Query)
-                    {
-                        let data_loader: Option<
-                            &DataLoader<grpc::DataLoaderRequest, GrpcDataLoader>,
-                        > = dl_id.and_then(|index| ctx.req_ctx.grpc_data_loaders.get(index.0));
-                        execute_grpc_request_with_dl(ctx, rendered, data_loader).await?
-                    } else {
-                        let req = rendered.to_request()?;
-                        execute_raw_grpc_request(ctx, req, &req_template.operation).await?
+                    let res = match execute_grpc(ctx, rendered, req_template, *dl_id).await {
+                        Ok(res) => res,
+                        Err(error) => return handle_grpc_status(error, on_status),
                     };
 
                     set_cache_control(ctx, &res);
+                    ctx.req_ctx
+                        .propagate_headers(&res.headers, &ctx.req_ctx.upstream.propagate_headers);
+                    ctx.req_ctx.forward_grpc_metadata(&res.headers, metadata);
 
                     Ok(res.body)
                 }
@@ -118,6 +155,46 @@ impl Eval for IO {
     }
 }
 
+impl IO {
+    /// Whether a failed request can be resolved to null by `onStatus`.
+    pub fn has_null_status(&self) -> bool {
+        match self {
+            IO::Http { on_status, .. } => on_status
+                .iter()
+                .any(|on_status| on_status.action == StatusAction::Null),
+            IO::Grpc { on_status, .. } => on_status
+                .iter()
+                .any(|on_status| on_status.action == StatusAction::Null),
+            IO::GraphQL { .. } => false,
+        }
+    }
+
+    /// Resolves the events of a subscription field. Server-streaming gRPC
+    /// methods emit an event per message, the other operations a single one.
+    pub async fn eval_stream<'a, Ctx: ResolverContextLike<'a> + Sync + Send>(
+        &'a self,
+        ctx: &'a EvaluationContext<'a, Ctx>,
+    ) -> Result<BoxStream<'static, Result<ConstValue>>> {
+        match self {
+            IO::Grpc { req_template, .. } if req_template.operation.is_server_streaming() => {
+                let req = req_template.render(ctx)?.to_request()?;
+                Ok(execute_grpc_stream(
+                    &ctx.req_ctx.runtime,
+                    &req_template.operation,
+                    req_template.protocol,
+                    req,
+                )
+                .await
+                .map_err(|e| EvaluationError::from_io_error(&e))?)
+            }
+            io => {
+                let value = io.eval(ctx, &Concurrent::Sequential).await?;
+                Ok(stream::once(future::ready(Ok(value))).boxed())
+            }
+        }
+    }
+}
+
 impl<'a, Ctx: ResolverContextLike<'a> + Sync + Send> CacheKey<EvaluationContext<'a, Ctx>> for IO {
     fn cache_key(&self, ctx: &EvaluationContext<'a, Ctx>) -> u64 {
         match self {
@@ -128,6 +205,54 @@ impl<'a, Ctx: ResolverContextLike<'a> + Sync + Send> CacheKey<EvaluationContext<
     }
 }
 
+/// Applies the `onStatus` mapping of the field to a failed request.
+fn handle_status(error: anyhow::Error, on_status: &[OnStatus]) -> Result<ConstValue> {
+    let Some(EvaluationError::HTTPException { status, .. }) = error.downcast_ref() else {
+        return Err(error);
+    };
+    let Some(on_status) = on_status
+        .iter()
+        .find(|on_status| on_status.status == *status)
+    else {
+        return Err(error);
+    };
+
+    match on_status.action {
+        StatusAction::Null => Ok(ConstValue::Null),
+        StatusAction::Error => match error.downcast::<EvaluationError>()? {
+            EvaluationError::HTTPException { status, url, body, .. } => {
+                let code = on_status.code.clone();
+                Err(EvaluationError::HTTPException { status, url, body, code }.into())
+            }
+            error => Err(error.into()),
+        },
+    }
+}
+
+/// Applies the `onStatus` mapping of the field to a failed gRPC call.
+fn handle_grpc_status(error: anyhow::Error, on_status: &[GrpcOnStatus]) -> Result<ConstValue> {
+    let Some(EvaluationError::GRPCException { status, .. }) = error.downcast_ref() else {
+        return Err(error);
+    };
+    let Some(on_status) = on_status
+        .iter()
+        .find(|on_status| on_status.status == *status)
+    else {
+        return Err(error);
+    };
+
+    match on_status.action {
+        StatusAction::Null => Ok(ConstValue::Null),
+        StatusAction::Error => match error.downcast::<EvaluationError>()? {
+            EvaluationError::GRPCException { status, message, details, .. } => {
+                let code = on_status.code.clone();
+                Err(EvaluationError::GRPCException { status, message, details, code }.into())
+            }
+            error => Err(error.into()),
+        },
+    }
+}
+
 fn set_cache_control<'ctx, Ctx: ResolverContextLike<'ctx>>(
     ctx: &EvaluationContext<'ctx, Ctx>,
     res: &Response<async_graphql::Value>,
@@ -142,24 +267,46 @@ fn set_cache_control<'ctx, Ctx: ResolverContextLike<'ctx>>(
 async fn execute_raw_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
     ctx: &EvaluationContext<'ctx, Ctx>,
     req: Request,
+    hedge: Option<&Hedge>,
+    response_format: Option<&ResponseFormat>,
 ) -> Result<Response<async_graphql::Value>> {
-    ctx.req_ctx
-        .runtime
-        .http
-        .execute(req)
+    http::execute_hedged(ctx.req_ctx.runtime.http.as_ref(), req, hedge)
         .await
-        .map_err(|e| EvaluationError::IOException(e.to_string()))?
-        .to_json()
+        .map_err(|e| EvaluationError::from_io_error(&e))?
+        .to_value(response_format)
+}
+
+/// Sends the gRPC request, through the data loader for batched queries.
+async fn execute_grpc<'ctx, Ctx: ResolverContextLike<'ctx>>(
+    ctx: &EvaluationContext<'ctx, Ctx>,
+    rendered: RenderedRequestTemplate,
+    req_template: &grpc::RequestTemplate,
+    dl_id: Option<DataLoaderId>,
+) -> Result<Response<async_graphql::Value>> {
+    // TODO: share check for operation_type for resolvers
+    if ctx.req_ctx.upstream.batch.is_some()
+        && matches!(req_template.operation_type, GraphQLOperationType::Query)
+    {
+        let data_loader: Option<&DataLoader<grpc::DataLoaderRequest, GrpcDataLoader>> =
+            dl_id.and_then(|index| ctx.req_ctx.grpc_data_loaders.get(index.0));
+        execute_grpc_request_with_dl(ctx, rendered, data_loader).await
+    } else {
+        let req = rendered.to_request()?;
+        execute_raw_grpc_request(ctx, req, req_template).await
+    }
 }
 
 async fn execute_raw_grpc_request<'ctx, Ctx: ResolverContextLike<'ctx>>(
     ctx: &EvaluationContext<'ctx, Ctx>,
     req: Request,
-    operation: &ProtobufOperation,
+    req_template: &grpc::RequestTemplate,
 ) -> Result<Response<async_graphql::Value>> {
-    Ok(execute_grpc_request(&ctx.req_ctx.runtime, operation, req)
-        .await
-        .map_err(|e| EvaluationError::IOException(e.to_string()))?)
+    let runtime = &ctx.req_ctx.runtime;
+    Ok(
+        execute_grpc_request(runtime, &req_template.operation, req_template.protocol, req)
+            .await
+            .map_err(|e| EvaluationError::from_io_error(&e))?,
+    )
 }
 
 async fn execute_grpc_request_with_dl<
@@ -188,7 +335,7 @@ async fn execute_grpc_request_with_dl<
         .unwrap()
         .load_one(endpoint_key)
         .await
-        .map_err(|e| EvaluationError::IOException(e.to_string()))?
+        .map_err(|e| EvaluationError::from_io_error(&e))?
         .unwrap_or_default())
 }
 
@@ -214,7 +361,7 @@ async fn execute_request_with_dl<
         .unwrap()
         .load_one(endpoint_key)
         .await
-        .map_err(|e| EvaluationError::IOException(e.to_string()))?
+        .map_err(|e| EvaluationError::from_io_error(&e))?
         .unwrap_or_default())
 }
 
@@ -1,4 +1,4 @@
-use super::{Cache, Expression};
+use super::{Cache, CacheInvalidate, Expression};
 
 impl Expression {
     pub fn modify(self, mut f: impl FnMut(&Expression) -> Option<Expression>) -> Expression {
@@ -38,8 +38,17 @@ impl Expression {
                         Expression::EqualTo(expr1.modify_box(modifier), expr2.modify_box(modifier))
                     }
                     Expression::IO(_) => expr,
-                    Expression::Cache(Cache { expr, .. }) => {
-                        Expression::IO(expr).modify_inner(modifier)
+                    Expression::Cache(cache) => {
+                        match Expression::IO(cache.expr.clone()).modify_inner(modifier) {
+                            Expression::IO(expr) => Expression::Cache(Cache { expr, ..cache }),
+                            expr => expr,
+                        }
+                    }
+                    Expression::CacheInvalidate(invalidate) => {
+                        Expression::CacheInvalidate(CacheInvalidate {
+                            expr: invalidate.expr.modify_box(modifier),
+                            ..invalidate
+                        })
                     }
                     Expression::Input(expr, path) => {
                         Expression::Input(expr.modify_box(modifier), path)
@@ -3,11 +3,16 @@ use async_graphql::dynamic::ResolverContext;
 use async_graphql::{Name, ServerError, Value};
 use indexmap::IndexMap;
 
+use super::evaluation_context::format_selection_set;
+
 pub trait ResolverContextLike<'a> {
     fn value(&'a self) -> Option<&'a Value>;
     fn args(&'a self) -> Option<&'a IndexMap<Name, Value>>;
     fn field(&'a self) -> Option<SelectionField>;
     fn add_error(&'a self, error: ServerError);
+    fn selection_set(&'a self) -> Option<String> {
+        format_selection_set(self.field()?.selection_set())
+    }
 }
 
 pub struct EmptyResolverContext;
@@ -45,3 +50,42 @@ impl<'a> ResolverContextLike<'a> for ResolverContext<'a> {
         self.ctx.add_error(error)
     }
 }
+
+/// An owned copy of a resolver context, which can outlive the GraphQL request
+/// it was taken from, eg. to refresh a cached value in the background.
+#[derive(Clone, Debug, Default)]
+pub struct DetachedResolverContext {
+    pub value: Option<Value>,
+    pub args: Option<IndexMap<Name, Value>>,
+    pub selection_set: Option<String>,
+}
+
+impl DetachedResolverContext {
+    pub fn new<'a, Ctx: ResolverContextLike<'a>>(ctx: &'a Ctx) -> Self {
+        Self {
+            value: ctx.value().cloned(),
+            args: ctx.args().cloned(),
+            selection_set: ctx.selection_set(),
+        }
+    }
+}
+
+impl<'a> ResolverContextLike<'a> for DetachedResolverContext {
+    fn value(&'a self) -> Option<&'a Value> {
+        self.value.as_ref()
+    }
+
+    fn args(&'a self) -> Option<&'a IndexMap<Name, Value>> {
+        self.args.as_ref()
+    }
+
+    fn field(&'a self) -> Option<SelectionField> {
+        None
+    }
+
+    fn add_error(&'a self, _: ServerError) {}
+
+    fn selection_set(&'a self) -> Option<String> {
+        self.selection_set.clone()
+    }
+}
@@ -11,6 +11,7 @@ pub mod data_loader;
 pub mod directive;
 pub mod document;
 pub mod endpoint;
+pub mod generator;
 pub mod graphql;
 pub mod grpc;
 pub mod has_headers;
@@ -27,9 +28,9 @@ pub mod valid;
 
 use std::hash::Hash;
 use std::num::NonZeroU64;
+use std::time::Duration;
 
-use async_graphql_value::ConstValue;
-use http::Response;
+use http::{Response, StreamResponse};
 
 pub trait EnvIO: Send + Sync + 'static {
     fn get(&self, key: &str) -> Option<String>;
@@ -41,28 +42,69 @@ pub trait HttpIO: Sync + Send + 'static {
         &self,
         request: reqwest::Request,
     ) -> anyhow::Result<Response<hyper::body::Bytes>>;
+
+    /// Executes the request and returns the body as it arrives, for the
+    /// upstreams that keep sending data like gRPC server-streaming methods.
+    /// By default the body is read as a whole.
+    async fn execute_stream(&self, request: reqwest::Request) -> anyhow::Result<StreamResponse> {
+        Ok(StreamResponse::from(self.execute(request).await?))
+    }
 }
 
 #[async_trait::async_trait]
 pub trait FileIO: Send + Sync {
     async fn write<'a>(&'a self, path: &'a str, content: &'a [u8]) -> anyhow::Result<()>;
     async fn read<'a>(&'a self, path: &'a str) -> anyhow::Result<String>;
+
+    /// Reads a file whose content may not be valid UTF-8.
+    async fn read_bytes<'a>(&'a self, path: &'a str) -> anyhow::Result<Vec<u8>> {
+        Ok(self.read(path).await?.into_bytes())
+    }
+}
+
+/// A cached value along with the time elapsed since it was stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheEntry<V> {
+    pub value: V,
+    pub age: Duration,
 }
 
 #[async_trait::async_trait]
 pub trait Cache: Send + Sync {
-    type Key: Hash + Eq;
-    type Value;
+    type Key: Hash + Eq + Sync;
+    type Value: Send;
     async fn set<'a>(
         &'a self,
         key: Self::Key,
         value: Self::Value,
         ttl: NonZeroU64,
     ) -> anyhow::Result<()>;
-    async fn get<'a>(&'a self, key: &'a Self::Key) -> anyhow::Result<Option<Self::Value>>;
+    async fn get_entry<'a>(
+        &'a self,
+        key: &'a Self::Key,
+    ) -> anyhow::Result<Option<CacheEntry<Self::Value>>>;
+    async fn get<'a>(&'a self, key: &'a Self::Key) -> anyhow::Result<Option<Self::Value>> {
+        Ok(self.get_entry(key).await?.map(|entry| entry.value))
+    }
+    /// Associates the entry stored for `key` with tags, which are used to
+    /// evict it with [Cache::invalidate]. The association is kept for at
+    /// least `ttl`, the time to live of the entry.
+    async fn tag<'a>(
+        &'a self,
+        key: &'a Self::Key,
+        tags: Vec<String>,
+        ttl: NonZeroU64,
+    ) -> anyhow::Result<()>;
+    /// Evicts every entry associated with one of the tags.
+    async fn invalidate<'a>(&'a self, tags: &'a [String]) -> anyhow::Result<()>;
+    /// The hits, misses and evictions of the cache, when it keeps track of
+    /// them.
+    fn stats(&self) -> Option<cache::CacheSnapshot> {
+        None
+    }
 }
 
-pub type EntityCache = dyn Cache<Key = u64, Value = ConstValue>;
+pub type EntityCache = dyn Cache<Key = u64, Value = lambda::CachedValue>;
 
 pub trait WorkerIO<Event, Command>: Send + Sync {
     fn dispatch(&self, event: Event) -> anyhow::Result<Command>;
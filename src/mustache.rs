@@ -83,12 +83,14 @@ impl Mustache {
 fn parse_name(input: &str) -> IResult<&str, String> {
     let spaces = nom::character::complete::multispace0;
     let alpha = nom::character::complete::alpha1;
-    let alphanumeric_or_underscore = nom::multi::many0(nom::branch::alt((
+    // hyphens are allowed for header names such as `x-tenant`
+    let alphanumeric_underscore_or_hyphen = nom::multi::many0(nom::branch::alt((
         nom::character::complete::alphanumeric1,
         nom::bytes::complete::tag("_"),
+        nom::bytes::complete::tag("-"),
     )));
 
-    let parser = nom::sequence::tuple((spaces, alpha, alphanumeric_or_underscore, spaces));
+    let parser = nom::sequence::tuple((spaces, alpha, alphanumeric_underscore_or_hyphen, spaces));
 
     nom::combinator::map(parser, |(_, a, b, _)| {
         let b: String = b.into_iter().collect();
@@ -190,6 +192,17 @@ mod tests {
             assert_eq!(result, expected);
         }
 
+        #[test]
+        fn test_parse_expression_with_hyphen() {
+            let result = Mustache::parse("{{args.id}}-{{headers.x-tenant}}").unwrap();
+            let expected = Mustache::from(vec![
+                Segment::Expression(vec!["args".to_string(), "id".to_string()]),
+                Segment::Literal("-".to_string()),
+                Segment::Expression(vec!["headers".to_string(), "x-tenant".to_string()]),
+            ]);
+            assert_eq!(result, expected);
+        }
+
         #[test]
         fn test_parse_expression_with_invalid_input() {
             let result = Mustache::parse("foo.bar }}").unwrap();
@@ -1,8 +1,6 @@
 use std::sync::Arc;
 
-use async_graphql_value::ConstValue;
-
-use crate::{Cache, EnvIO, FileIO, HttpIO};
+use crate::{EntityCache, EnvIO, FileIO, HttpIO};
 
 /// The TargetRuntime struct unifies the available runtime-specific
 /// IO implementations. This is used to reduce piping IO structs all
@@ -13,7 +11,7 @@ pub struct TargetRuntime {
     pub http2_only: Arc<dyn HttpIO>,
     pub env: Arc<dyn EnvIO>,
     pub file: Arc<dyn FileIO>,
-    pub cache: Arc<dyn Cache<Key = u64, Value = ConstValue>>,
+    pub cache: Arc<EntityCache>,
 }
 
 #[cfg(test)]
@@ -90,7 +88,7 @@ pub mod test {
     impl HttpIO for TestHttp {
         async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
             let response = self.client.execute(request).await;
-            Response::from_reqwest(response?.error_for_status()?).await
+            Response::from_reqwest_checked(response?).await
         }
     }
 
@@ -114,12 +112,16 @@ pub mod test {
         }
 
         async fn read<'a>(&'a self, path: &'a str) -> anyhow::Result<String> {
+            Ok(String::from_utf8(self.read_bytes(path).await?)?)
+        }
+
+        async fn read_bytes<'a>(&'a self, path: &'a str) -> anyhow::Result<Vec<u8>> {
             let mut file = tokio::fs::File::open(path).await?;
             let mut buffer = Vec::new();
             file.read_to_end(&mut buffer)
                 .await
                 .map_err(|e| anyhow!("{}", e))?;
-            Ok(String::from_utf8(buffer)?)
+            Ok(buffer)
         }
     }
 
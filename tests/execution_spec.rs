@@ -22,7 +22,7 @@ use tailcall::cache::InMemoryCache;
 use tailcall::cli::javascript;
 use tailcall::config::reader::ConfigReader;
 use tailcall::config::{Config, ConfigModule, Source};
-use tailcall::http::{handle_request, AppContext, Method, Response};
+use tailcall::http::{handle_request, AppContext, Method, Response, StatusError};
 use tailcall::print_schema::print_schema;
 use tailcall::runtime::TargetRuntime;
 use tailcall::valid::{Cause, ValidationError, Validator as _};
@@ -103,7 +103,7 @@ pub mod test {
     impl HttpIO for TestHttp {
         async fn execute(&self, request: reqwest::Request) -> Result<Response<Bytes>> {
             let response = self.client.execute(request).await;
-            Response::from_reqwest(response?.error_for_status()?).await
+            Response::from_reqwest_checked(response?).await
         }
     }
 
@@ -605,7 +605,7 @@ impl ExecutionSpec {
             http2_only,
             file: Arc::new(MockFileSystem::new(self.clone())),
             env: Arc::new(Env::init(env)),
-            cache: Arc::new(InMemoryCache::new()),
+            cache: Arc::new(InMemoryCache::init(&blueprint.server.cache)),
         };
         Arc::new(AppContext::new(blueprint, runtime))
     }
@@ -699,10 +699,6 @@ impl HttpIO for MockHttpClient {
         // Build the response with the status code from the mock.
         let status_code = reqwest::StatusCode::from_u16(mock_response.0.status)?;
 
-        if status_code.is_client_error() || status_code.is_server_error() {
-            return Err(anyhow::format_err!("Status code error"));
-        }
-
         let mut response = Response { status: status_code, ..Default::default() };
 
         // Insert headers from the mock into the response.
@@ -726,6 +722,15 @@ impl HttpIO for MockHttpClient {
             response.body = Bytes::from_iter(body);
         }
 
+        if status_code.is_client_error() || status_code.is_server_error() {
+            return Err(StatusError {
+                status: status_code,
+                url: req.url().to_string(),
+                body: response.body,
+            }
+            .into());
+        }
+
         Ok(response)
     }
 }
@@ -940,15 +945,25 @@ async fn assert_spec(spec: ExecutionSpec) {
                 headers.insert(key.to_string(), value.to_str().unwrap().to_string());
             }
 
-            let response: APIResponse = APIResponse {
-                status: response.status().clone().as_u16(),
-                headers,
-                body: serde_json::from_slice(
-                    &hyper::body::to_bytes(response.into_body()).await.unwrap(),
-                )
-                .unwrap(),
-                text_body: None,
-            };
+            let status = response.status().clone().as_u16();
+            let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            // the events of subscriptions are kept as they were sent
+            let response: APIResponse =
+                if headers.get("content-type").map(String::as_str) == Some("text/event-stream") {
+                    APIResponse {
+                        status,
+                        headers,
+                        body: serde_json::Value::Null,
+                        text_body: Some(String::from_utf8(bytes.to_vec()).unwrap()),
+                    }
+                } else {
+                    APIResponse {
+                        status,
+                        headers,
+                        body: serde_json::from_slice(&bytes).unwrap(),
+                        text_body: None,
+                    }
+                };
 
             let snapshot_name = format!("{}_assert_{}", spec.safe_name, i);
 